@@ -15,8 +15,6 @@ mod tests {
         // This test ensures play_beep() can be called without panicking
         // We can't easily test the actual beep sound, but we can ensure it doesn't crash
         play_beep();
-        // If we get here, the function didn't panic
-        assert!(true);
     }
 
     #[test]
@@ -25,6 +23,5 @@ mod tests {
         for _ in 0..3 {
             play_beep();
         }
-        assert!(true);
     }
 }
@@ -0,0 +1,219 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::{Value, json};
+
+use crate::error::Error;
+use crate::notifier::{Notifier, check_response};
+use crate::state_machine::Event;
+
+/// POSTs `body` to a chat service's incoming webhook.
+async fn post_webhook(client: &reqwest::Client, provider: &str, url: &str, body: &Value) -> Result<(), Error> {
+    let response = client
+        .post(url)
+        .json(body)
+        .send()
+        .await
+        .map_err(|source| Error::NotifierHttp {
+            provider: provider.to_string(),
+            source,
+        })?;
+    check_response(provider, response).await
+}
+
+/// Posts events to a Slack channel through an incoming webhook.
+pub struct SlackNotifier {
+    client: reqwest::Client,
+    webhook_url: String,
+}
+
+impl SlackNotifier {
+    pub fn new(client: reqwest::Client, webhook_url: impl Into<String>) -> Self {
+        Self {
+            client,
+            webhook_url: webhook_url.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &str {
+        "Slack"
+    }
+
+    async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+        let body = json!({ "text": event.message(door) });
+        post_webhook(&self.client, "Slack", &self.webhook_url, &body).await
+    }
+}
+
+/// Posts events to a Discord channel through a webhook.
+pub struct DiscordNotifier {
+    client: reqwest::Client,
+    webhook_url: String,
+}
+
+impl DiscordNotifier {
+    pub fn new(client: reqwest::Client, webhook_url: impl Into<String>) -> Self {
+        Self {
+            client,
+            webhook_url: webhook_url.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &str {
+        "Discord"
+    }
+
+    async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+        let body = json!({ "content": event.message(door) });
+        post_webhook(&self.client, "Discord", &self.webhook_url, &body).await
+    }
+}
+
+/// Sends events to a Matrix room as a user, through the client-server API.
+pub struct MatrixNotifier {
+    client: reqwest::Client,
+    homeserver: String,
+    room_id: String,
+    access_token: String,
+    /// Keeps transaction IDs unique within a run
+    next_transaction: AtomicU64,
+}
+
+impl MatrixNotifier {
+    pub fn new(
+        client: reqwest::Client,
+        homeserver: impl Into<String>,
+        room_id: impl Into<String>,
+        access_token: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            homeserver: homeserver.into(),
+            room_id: room_id.into(),
+            access_token: access_token.into(),
+            next_transaction: AtomicU64::new(0),
+        }
+    }
+
+    /// A new transaction ID. The homeserver drops a repeated ID as a retry of
+    /// the same message, so it includes the current time to stay unique across restarts.
+    fn transaction_id(&self) -> String {
+        let count = self.next_transaction.fetch_add(1, Ordering::Relaxed);
+        format!("door-monitor-{}-{}", Utc::now().timestamp_millis(), count)
+    }
+}
+
+#[async_trait]
+impl Notifier for MatrixNotifier {
+    fn name(&self) -> &str {
+        "Matrix"
+    }
+
+    async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+        let url = format!(
+            "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+            self.homeserver.trim_end_matches('/'),
+            urlencoding::encode(&self.room_id),
+            self.transaction_id()
+        );
+        let response = self
+            .client
+            .put(&url)
+            .bearer_auth(&self.access_token)
+            .json(&json!({
+                "msgtype": "m.text",
+                "body": event.message(door),
+            }))
+            .send()
+            .await
+            .map_err(|source| Error::NotifierHttp {
+                provider: "Matrix".to_string(),
+                source,
+            })?;
+        check_response("Matrix", response).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+
+    #[tokio::test]
+    async fn test_slack() {
+        let mut server = Server::new_async().await;
+        let mock = server.mock("POST", "/services/T000/B000/XXXX")
+            .match_body(Matcher::Json(json!({ "text": "Garage door has been opened" })))
+            .with_status(200)
+            .with_body("ok")
+            .create_async()
+            .await;
+
+        let notifier = SlackNotifier::new(reqwest::Client::new(), format!("{}/services/T000/B000/XXXX", server.url()));
+        notifier.send("Garage door", &Event::Opened).await.unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_discord() {
+        let mut server = Server::new_async().await;
+        let mock = server.mock("POST", "/api/webhooks/123/abc")
+            .match_body(Matcher::Json(json!({ "content": "Garage door has been opened" })))
+            .with_status(204)
+            .create_async()
+            .await;
+
+        let notifier = DiscordNotifier::new(reqwest::Client::new(), format!("{}/api/webhooks/123/abc", server.url()));
+        notifier.send("Garage door", &Event::Opened).await.unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_matrix() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("PUT", Matcher::Regex(
+                r"^/_matrix/client/v3/rooms/%21room%3Aexample\.org/send/m\.room\.message/door-monitor-\d+-\d+$".to_string(),
+            ))
+            .match_header("authorization", "Bearer syt_token")
+            .match_body(Matcher::Json(json!({
+                "msgtype": "m.text",
+                "body": "Garage door has been opened",
+            })))
+            .with_status(200)
+            .with_body(r#"{"event_id":"$abc"}"#)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let notifier = MatrixNotifier::new(reqwest::Client::new(), server.url(), "!room:example.org", "syt_token");
+        notifier.send("Garage door", &Event::Opened).await.unwrap();
+        notifier.send("Garage door", &Event::Opened).await.unwrap();
+        mock.assert_async().await;
+
+        assert_ne!(notifier.transaction_id(), notifier.transaction_id());
+    }
+
+    #[tokio::test]
+    async fn test_matrix_rejected() {
+        let mut server = Server::new_async().await;
+        let mock = server.mock("PUT", Matcher::Any)
+            .with_status(403)
+            .with_body(r#"{"errcode":"M_FORBIDDEN","error":"User not in room"}"#)
+            .create_async()
+            .await;
+
+        let notifier = MatrixNotifier::new(reqwest::Client::new(), server.url(), "!room:example.org", "syt_token");
+        let error = notifier.send("Garage door", &Event::Opened).await.unwrap_err();
+
+        mock.assert_async().await;
+        assert!(matches!(error, Error::NotifierRejected { provider, .. } if provider == "Matrix"));
+    }
+}
@@ -1,20 +1,163 @@
-use clap::Parser;
+use std::path::PathBuf;
+use std::time::Duration;
 
-#[derive(Parser, Debug)]
+use clap::{Parser, Subcommand};
+
+use crate::door::SensorCredentials;
+use crate::email::SmtpSecurity;
+use crate::limits::Rate;
+use crate::templates::{EventTemplate, parse_event_template};
+use crate::retry::RetryPolicy;
+use crate::utils::{http_client, parse_duration};
+
+/// Connect timeout used when no `Args` are available.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Request timeout used when no `Args` are available.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A named door and the sensor API URL used to check it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoorConfig {
+    pub name: String,
+    pub api_url: String,
+    /// Used if the sensor asks for authentication
+    pub credentials: Option<SensorCredentials>,
+}
+
+/// Name given to the door configured with `--api-url`.
+pub const DEFAULT_DOOR_NAME: &str = "Door";
+
+/// Parses a `--door` value in the form `NAME=URL`.
+pub fn parse_door(value: &str) -> Result<DoorConfig, String> {
+    match value.split_once('=') {
+        Some((name, api_url)) if !name.trim().is_empty() && !api_url.trim().is_empty() => Ok(DoorConfig {
+            name: name.trim().to_string(),
+            api_url: api_url.trim().to_string(),
+            credentials: None,
+        }),
+        _ => Err(format!("expected NAME=URL, got '{}'", value)),
+    }
+}
+
+/// Parses a `--rate-limit` value in the form `CHANNEL=COUNT/PERIOD`, e.g. `SMS=5/10m`.
+pub fn parse_rate_limit(value: &str) -> Result<(String, Rate), String> {
+    let parsed = value.split_once('=').and_then(|(channel, rate)| {
+        let (burst, period) = rate.split_once('/')?;
+        let burst = burst.trim().parse().ok().filter(|burst| *burst > 0)?;
+        let period = parse_duration(period.trim())?;
+        Some((channel.trim().to_string(), Rate { burst, period })).filter(|(channel, _)| !channel.is_empty())
+    });
+    parsed.ok_or_else(|| format!("expected CHANNEL=COUNT/PERIOD like SMS=5/10m, got '{}'", value))
+}
+
+/// Parses a `--daily-quota` or `--monthly-quota` value in the form `CHANNEL=COUNT`.
+pub fn parse_quota(value: &str) -> Result<(String, u32), String> {
+    match value.split_once('=') {
+        Some((channel, count)) if !channel.trim().is_empty() => match count.trim().parse() {
+            Ok(count) => Ok((channel.trim().to_string(), count)),
+            Err(_) => Err(format!("expected a number of messages, got '{}'", count)),
+        },
+        _ => Err(format!("expected CHANNEL=COUNT, got '{}'", value)),
+    }
+}
+
+/// Parses a `--webhook-header` value in the form `Name: value`.
+pub fn parse_header(value: &str) -> Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((name, header_value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), header_value.trim().to_string()))
+        }
+        _ => Err(format!("expected 'Name: value', got '{}'", value)),
+    }
+}
+
+/// Things to do instead of monitoring.
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Print every message, with the --template options applied, filled in with sample data
+    RenderTemplates,
+}
+
+#[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Door sensor API URL
     #[arg(long)]
     pub api_url: Option<String>,
 
+    /// Named door sensor as NAME=URL, repeat for each door (e.g. --door "Garage door=http://192.168.1.226/rpc/Input.GetStatus?id=0")
+    #[arg(long = "door", value_name = "NAME=URL", value_parser = parse_door)]
+    pub doors: Vec<DoorConfig>,
+
     /// Check interval in seconds
     #[arg(long, default_value = "5")]
     pub check_interval_seconds: u64,
 
+    /// Listen for Shelly action URL / webhook events on this address (e.g. 0.0.0.0:8080)
+    #[arg(long, value_name = "ADDR")]
+    pub listen: Option<std::net::SocketAddr>,
+
+    /// Shared secret the listener requires on /event and /acknowledge, as an
+    /// `Authorization: Bearer` header or a `token` query parameter
+    #[arg(long, value_name = "TOKEN")]
+    pub listen_token: Option<String>,
+
+    /// How often to poll sensors to reconcile state while listening for webhook events
+    #[arg(long, default_value = "300")]
+    pub reconcile_interval_seconds: u64,
+
     /// How many seconds is too long for the door to be open
     #[arg(long, default_value = "15")]
     pub open_too_long_seconds: u64,
 
+    /// Seconds to wait for a connection to a sensor or notification service
+    #[arg(long, default_value = "5")]
+    pub connect_timeout_seconds: u64,
+
+    /// Seconds to wait for a whole HTTP request to a sensor or notification service
+    #[arg(long, default_value = "10")]
+    pub request_timeout_seconds: u64,
+
+    /// Times to retry a sensor read that failed with a timeout, connection error or 5xx
+    #[arg(long, default_value = "2")]
+    pub sensor_retries: u32,
+
+    /// Delay before the first retry in milliseconds, doubled with jitter for each further retry
+    #[arg(long, default_value = "500")]
+    pub sensor_retry_delay_ms: u64,
+
+    /// Username for sensors with authentication enabled (Shelly Gen2 devices always use admin)
+    #[arg(long, default_value = "admin")]
+    pub sensor_username: String,
+
+    /// Password for sensors with authentication enabled, sent with HTTP Digest or Basic auth when asked for
+    #[arg(long)]
+    pub sensor_password: Option<String>,
+
+    /// Alert when a sensor has been unreachable for this many seconds (0 disables offline alerts)
+    #[arg(long, default_value = "300")]
+    pub sensor_offline_seconds: u64,
+
+    /// Consecutive readings that must agree before a door state change is accepted
+    #[arg(long, default_value = "1")]
+    pub debounce_readings: u32,
+
+    /// Seconds a new door state must hold before it is accepted
+    #[arg(long, default_value = "0")]
+    pub debounce_seconds: u64,
+
+    /// State changes within the flap window that mark a sensor as flapping (0 disables flap detection)
+    #[arg(long, default_value = "0")]
+    pub flap_threshold: usize,
+
+    /// Flap detection window in seconds, also how long a flapping sensor must be quiet to settle
+    #[arg(long, default_value = "60")]
+    pub flap_window_seconds: u64,
+
     /// Disable SMS, ignores SMS arguments
     #[arg(long)]
     pub sms_off: bool,
@@ -31,14 +174,27 @@ pub struct Args {
     #[arg(long)]
     pub sms_from_phone_number: Option<String>,
 
-    /// SMS To Phone Number
-    #[arg(long)]
-    pub sms_to_phone_number: Option<String>,
+    /// SMS To Phone Number, repeat for each recipient
+    #[arg(long = "sms-to-phone-number", value_name = "NUMBER")]
+    pub sms_to_phone_numbers: Vec<String>,
 
     /// Disable SMS backoff (send only one SMS instead of progressive intervals)
     #[arg(long)]
     pub no_sms_backoff: bool,
 
+    /// voip.ms REST API URL
+    #[arg(long, default_value = "https://voip.ms/api/v1/rest.php")]
+    pub sms_api_url: String,
+
+    /// Check voip.ms for SMS replies, replying ACK acknowledges the open door alert
+    #[arg(long)]
+    pub sms_replies: bool,
+
+    /// Minutes an acknowledged alert holds back reminders. Without this they
+    /// stop until the door closes
+    #[arg(long)]
+    pub acknowledge_snooze_minutes: Option<u64>,
+
     /// Telegram Off, arguments ignored
     #[arg(long)]
     pub telegram_off: bool,
@@ -47,9 +203,166 @@ pub struct Args {
     #[arg(long)]
     pub telegram_token: Option<String>,
 
-    /// Telegram Conversation ID
+    /// Telegram Conversation ID, repeat for each chat
+    #[arg(long = "telegram-conversation-id", value_name = "ID", allow_hyphen_values = true)]
+    pub telegram_conversation_ids: Vec<String>,
+
+    /// Telegram Bot API base URL
+    #[arg(long, default_value = "https://api.telegram.org")]
+    pub telegram_api_url: String,
+
+    /// Keep one pinned Telegram message per open door and edit it, instead of
+    /// sending a new message for every reminder
+    #[arg(long)]
+    pub telegram_live_message: bool,
+
+    /// Answer commands like /status and /mute sent to the Telegram bot
+    #[arg(long)]
+    pub telegram_commands: bool,
+
+    /// Chat or user ID allowed to send bot commands, repeat for each one.
+    /// Defaults to the Telegram conversation ID
+    #[arg(long = "telegram-allowed-id", allow_hyphen_values = true)]
+    pub telegram_allowed_ids: Vec<i64>,
+
+    /// URL to POST a JSON document to for every event
+    #[arg(long)]
+    pub webhook_url: Option<String>,
+
+    /// Extra header for webhook requests as 'Name: value', repeat for each header
+    #[arg(long = "webhook-header", value_name = "NAME: VALUE", value_parser = parse_header)]
+    pub webhook_headers: Vec<(String, String)>,
+
+    /// JSON body for webhook requests with {event}, {door}, {state}, {message}, {open_seconds},
+    /// {duration}, {opened_at}, {timestamp} and {backoff_index} placeholders
+    #[arg(long)]
+    pub webhook_template: Option<String>,
+
+    /// SMTP relay to send email notifications through, e.g. smtp.example.com
+    #[arg(long, requires_all = ["email_from", "email_to"])]
+    pub email_smtp_host: Option<String>,
+
+    /// SMTP port, defaults to 587 for starttls, 465 for tls and 25 for none
+    #[arg(long)]
+    pub email_smtp_port: Option<u16>,
+
+    /// How to encrypt the SMTP connection
+    #[arg(long, value_enum, default_value = "starttls")]
+    pub email_smtp_security: SmtpSecurity,
+
+    /// SMTP username, AUTH is only used when a username is given
+    #[arg(long, requires = "email_smtp_password")]
+    pub email_smtp_username: Option<String>,
+
+    /// SMTP password
+    #[arg(long, requires = "email_smtp_username")]
+    pub email_smtp_password: Option<String>,
+
+    /// Sender address for email notifications, e.g. "Door Monitor <door@example.com>"
+    #[arg(long)]
+    pub email_from: Option<String>,
+
+    /// Email notification recipient, repeat for each recipient
+    #[arg(long = "email-to", value_name = "ADDRESS")]
+    pub email_to: Vec<String>,
+
+    /// ntfy topic to publish notifications to
+    #[arg(long)]
+    pub ntfy_topic: Option<String>,
+
+    /// ntfy server
+    #[arg(long, default_value = "https://ntfy.sh")]
+    pub ntfy_url: String,
+
+    /// ntfy access token for protected topics
+    #[arg(long)]
+    pub ntfy_token: Option<String>,
+
+    /// Extra ntfy tag added to every notification, repeat for each tag
+    #[arg(long = "ntfy-tag", value_name = "TAG")]
+    pub ntfy_tags: Vec<String>,
+
+    /// Gotify server URL, e.g. http://gotify.lan
+    #[arg(long, requires = "gotify_token")]
+    pub gotify_url: Option<String>,
+
+    /// Gotify application token
+    #[arg(long, requires = "gotify_url")]
+    pub gotify_token: Option<String>,
+
+    /// Pushover application API token
+    #[arg(long, requires = "pushover_user_key")]
+    pub pushover_app_token: Option<String>,
+
+    /// Pushover user or group key
+    #[arg(long, requires = "pushover_app_token")]
+    pub pushover_user_key: Option<String>,
+
+    /// Send Pushover alerts as emergencies that repeat until acknowledged
+    #[arg(long)]
+    pub pushover_emergency: bool,
+
+    /// Seconds between repeats of a Pushover emergency (at least 30)
+    #[arg(long, default_value = "60")]
+    pub pushover_retry_seconds: u64,
+
+    /// Seconds a Pushover emergency keeps repeating if not acknowledged (at most 10800)
+    #[arg(long, default_value = "3600")]
+    pub pushover_expire_seconds: u64,
+
+    /// Slack incoming webhook URL
+    #[arg(long)]
+    pub slack_webhook_url: Option<String>,
+
+    /// Discord channel webhook URL
+    #[arg(long)]
+    pub discord_webhook_url: Option<String>,
+
+    /// Matrix homeserver URL, e.g. https://matrix.example.org
+    #[arg(long, requires_all = ["matrix_room_id", "matrix_access_token"])]
+    pub matrix_homeserver: Option<String>,
+
+    /// Matrix room ID to post to, e.g. !abc123:example.org
+    #[arg(long, requires_all = ["matrix_homeserver", "matrix_access_token"])]
+    pub matrix_room_id: Option<String>,
+
+    /// Access token of the Matrix user that posts the messages
+    #[arg(long, requires_all = ["matrix_homeserver", "matrix_room_id"])]
+    pub matrix_access_token: Option<String>,
+
+    /// File to queue notifications in until they are sent, so they are
+    /// retried after network outages and restarts
+    #[arg(long)]
+    pub outbox_file: Option<PathBuf>,
+
+    /// Rate limit for a channel as CHANNEL=COUNT/PERIOD, e.g. "SMS=5/10m" for
+    /// up to 5 messages at once, refilled at 5 every 10 minutes. Repeat for each channel
+    #[arg(long = "rate-limit", value_name = "CHANNEL=COUNT/PERIOD", value_parser = parse_rate_limit)]
+    pub rate_limits: Vec<(String, Rate)>,
+
+    /// Most messages a channel may send per UTC day as CHANNEL=COUNT, e.g. "SMS=20"
+    #[arg(long = "daily-quota", value_name = "CHANNEL=COUNT", value_parser = parse_quota)]
+    pub daily_quotas: Vec<(String, u32)>,
+
+    /// Most messages a channel may send per UTC month as CHANNEL=COUNT, e.g. "SMS=200"
+    #[arg(long = "monthly-quota", value_name = "CHANNEL=COUNT", value_parser = parse_quota)]
+    pub monthly_quotas: Vec<(String, u32)>,
+
+    /// Channel told when another channel reaches its rate limit or quota,
+    /// Telegram if it's enabled and not set
     #[arg(long)]
-    pub telegram_conversation_id: Option<String>,
+    pub overflow_channel: Option<String>,
+
+    /// File to keep the rate limit and quota counters in across restarts
+    #[arg(long)]
+    pub usage_file: Option<PathBuf>,
+
+    /// Message template for an event as EVENT=TEMPLATE, or EVENT:CHANNEL=TEMPLATE for
+    /// one channel, e.g. "open_too_long:SMS=[{hostname}] {door} open for {duration}".
+    /// Placeholders: {door} {state} {duration} {opened_at} {reminder_number} {hostname}
+    /// {message} {event} {channel}. Repeat for each template
+    #[arg(long = "template", value_name = "EVENT[:CHANNEL]=TEMPLATE", value_parser = parse_event_template)]
+    pub templates: Vec<EventTemplate>,
 
     /// Test Telegram
     #[arg(long)]
@@ -64,6 +377,45 @@ impl Args {
     pub fn sms_backoff(&self) -> bool {
         !self.no_sms_backoff
     }
+
+    /// HTTP client with the configured connect and request timeouts.
+    pub fn http_client(&self) -> reqwest::Client {
+        http_client(
+            Duration::from_secs(self.connect_timeout_seconds),
+            Duration::from_secs(self.request_timeout_seconds),
+        )
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::new(self.sensor_retries, Duration::from_millis(self.sensor_retry_delay_ms))
+    }
+
+    /// Credentials for sensors that require authentication, if a password was given.
+    pub fn sensor_credentials(&self) -> Option<SensorCredentials> {
+        self.sensor_password.as_ref().map(|password| SensorCredentials {
+            username: self.sensor_username.clone(),
+            password: password.clone(),
+        })
+    }
+
+    /// All doors to monitor: the `--api-url` door (if any) followed by every `--door`.
+    pub fn all_doors(&self) -> Vec<DoorConfig> {
+        let mut doors = Vec::new();
+        if let Some(api_url) = self.api_url.as_ref().filter(|url| !url.is_empty()) {
+            doors.push(DoorConfig {
+                name: DEFAULT_DOOR_NAME.to_string(),
+                api_url: api_url.clone(),
+                credentials: None,
+            });
+        }
+        doors.extend(self.doors.iter().cloned());
+
+        let credentials = self.sensor_credentials();
+        for door in &mut doors {
+            door.credentials = door.credentials.take().or_else(|| credentials.clone());
+        }
+        doors
+    }
 }
 
 #[cfg(test)]
@@ -73,7 +425,7 @@ mod tests {
 
     #[test]
     fn test_args_with_required_api_url() {
-        let args = Args::try_parse_from(&[
+        let args = Args::try_parse_from([
             "door-monitor",
             "--api-url", "http://192.168.1.226/rpc/Input.GetStatus?id=0"
         ]).unwrap();
@@ -86,7 +438,7 @@ mod tests {
 
     #[test]
     fn test_args_with_all_options() {
-        let args = Args::try_parse_from(&[
+        let args = Args::try_parse_from([
             "door-monitor",
             "--api-url", "http://test.com",
             "--check-interval-seconds", "10",
@@ -111,9 +463,9 @@ mod tests {
         assert_eq!(args.sms_api_username, Some("user123".to_string()));
         assert_eq!(args.sms_api_password, Some("pass456".to_string()));
         assert_eq!(args.sms_from_phone_number, Some("1234567890".to_string()));
-        assert_eq!(args.sms_to_phone_number, Some("0987654321".to_string()));
+        assert_eq!(args.sms_to_phone_numbers, vec!["0987654321".to_string()]);
         assert_eq!(args.telegram_token, Some("2345:TEsttoKEN".to_string()));
-        assert_eq!(args.telegram_conversation_id, Some("345678".to_string()));
+        assert_eq!(args.telegram_conversation_ids, vec!["345678".to_string()]);
         assert!(args.telegram_off);
         assert!(args.telegram_test);
         assert_eq!(args.test_message, Some("test message 1".to_string()));
@@ -122,7 +474,7 @@ mod tests {
 
     #[test]
     fn test_args_defaults() {
-        let args = Args::try_parse_from(&[
+        let args = Args::try_parse_from([
             "door-monitor",
             "--api-url", "http://test.com"
         ]).unwrap();
@@ -132,13 +484,13 @@ mod tests {
         assert!(args.sms_api_username.is_none());
         assert!(args.sms_api_password.is_none());
         assert!(args.sms_from_phone_number.is_none());
-        assert!(args.sms_to_phone_number.is_none());
+        assert!(args.sms_to_phone_numbers.is_empty());
         assert!(args.sms_backoff());
     }
 
     #[test]
     fn test_args_custom_intervals() {
-        let args = Args::try_parse_from(&[
+        let args = Args::try_parse_from([
             "door-monitor",
             "--api-url", "http://test.com",
             "--check-interval-seconds", "1",
@@ -151,7 +503,7 @@ mod tests {
 
     #[test]
     fn test_args_sms_backoff_disabled() {
-        let args = Args::try_parse_from(&[
+        let args = Args::try_parse_from([
             "door-monitor",
             "--api-url", "http://test.com",
             "--no-sms-backoff"
@@ -159,4 +511,187 @@ mod tests {
 
         assert!(!args.sms_backoff());
     }
+
+    #[test]
+    fn test_args_multiple_doors() {
+        let args = Args::try_parse_from([
+            "door-monitor",
+            "--door", "Garage door=http://192.168.1.226/rpc/Input.GetStatus?id=0",
+            "--door", "Side door=http://192.168.1.227/rpc/Input.GetStatus?id=0",
+        ]).unwrap();
+
+        let doors = args.all_doors();
+        assert_eq!(doors.len(), 2);
+        assert_eq!(doors[0].name, "Garage door");
+        assert_eq!(doors[0].api_url, "http://192.168.1.226/rpc/Input.GetStatus?id=0");
+        assert_eq!(doors[1].name, "Side door");
+        assert_eq!(doors[1].api_url, "http://192.168.1.227/rpc/Input.GetStatus?id=0");
+    }
+
+    #[test]
+    fn test_args_api_url_is_default_door() {
+        let args = Args::try_parse_from([
+            "door-monitor",
+            "--api-url", "http://test.com",
+            "--door", "Garage door=http://garage.test",
+        ]).unwrap();
+
+        let doors = args.all_doors();
+        assert_eq!(doors.len(), 2);
+        assert_eq!(doors[0].name, DEFAULT_DOOR_NAME);
+        assert_eq!(doors[0].api_url, "http://test.com");
+        assert_eq!(doors[1].name, "Garage door");
+    }
+
+    #[test]
+    fn test_args_webhook_listener() {
+        let args = Args::try_parse_from([
+            "door-monitor",
+            "--door", "Garage door=http://garage.test",
+            "--listen", "0.0.0.0:8080",
+            "--reconcile-interval-seconds", "600",
+            "--listen-token", "s3cret",
+        ]).unwrap();
+
+        assert_eq!(args.listen, Some("0.0.0.0:8080".parse().unwrap()));
+        assert_eq!(args.listen_token, Some("s3cret".to_string()));
+        assert_eq!(args.reconcile_interval_seconds, 600);
+
+        let args = Args::try_parse_from(["door-monitor"]).unwrap();
+        assert!(args.listen.is_none());
+        assert!(args.listen_token.is_none());
+        assert_eq!(args.reconcile_interval_seconds, 300);
+    }
+
+    #[test]
+    fn test_args_debounce_and_flapping() {
+        let args = Args::try_parse_from([
+            "door-monitor",
+            "--api-url", "http://test.com",
+            "--debounce-readings", "3",
+            "--debounce-seconds", "2",
+            "--flap-threshold", "6",
+            "--flap-window-seconds", "120",
+        ]).unwrap();
+
+        assert_eq!(args.debounce_readings, 3);
+        assert_eq!(args.debounce_seconds, 2);
+        assert_eq!(args.flap_threshold, 6);
+        assert_eq!(args.flap_window_seconds, 120);
+
+        let args = Args::try_parse_from(["door-monitor", "--api-url", "http://test.com"]).unwrap();
+        assert_eq!(args.debounce_readings, 1);
+        assert_eq!(args.debounce_seconds, 0);
+        assert_eq!(args.flap_threshold, 0);
+        assert_eq!(args.flap_window_seconds, 60);
+    }
+
+    #[test]
+    fn test_args_timeouts_and_retries() {
+        let args = Args::try_parse_from(["door-monitor", "--api-url", "http://test.com"]).unwrap();
+        assert_eq!(args.connect_timeout_seconds, 5);
+        assert_eq!(args.request_timeout_seconds, 10);
+        assert_eq!(args.retry_policy(), RetryPolicy::new(2, Duration::from_millis(500)));
+
+        let args = Args::try_parse_from([
+            "door-monitor",
+            "--api-url", "http://test.com",
+            "--connect-timeout-seconds", "2",
+            "--request-timeout-seconds", "4",
+            "--sensor-retries", "0",
+            "--sensor-retry-delay-ms", "100",
+        ]).unwrap();
+        assert_eq!(args.connect_timeout_seconds, 2);
+        assert_eq!(args.request_timeout_seconds, 4);
+        assert_eq!(args.retry_policy(), RetryPolicy::new(0, Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn test_args_sensor_credentials() {
+        let args = Args::try_parse_from([
+            "door-monitor",
+            "--api-url", "http://192.168.1.226/rpc/Input.GetStatus?id=0",
+            "--door", "Side door=http://192.168.1.227/rpc/Input.GetStatus?id=0",
+            "--sensor-password", "secret",
+        ]).unwrap();
+
+        let credentials = SensorCredentials {
+            username: "admin".to_string(),
+            password: "secret".to_string(),
+        };
+        assert_eq!(args.sensor_credentials(), Some(credentials.clone()));
+        for door in args.all_doors() {
+            assert_eq!(door.credentials, Some(credentials.clone()));
+        }
+
+        let args = Args::try_parse_from(["door-monitor", "--api-url", "http://test.com"]).unwrap();
+        assert!(args.sensor_credentials().is_none());
+        assert!(args.all_doors()[0].credentials.is_none());
+    }
+
+    #[test]
+    fn test_args_sensor_offline_seconds() {
+        let args = Args::try_parse_from(["door-monitor", "--api-url", "http://test.com"]).unwrap();
+        assert_eq!(args.sensor_offline_seconds, 300);
+
+        let args = Args::try_parse_from([
+            "door-monitor",
+            "--api-url", "http://test.com",
+            "--sensor-offline-seconds", "0",
+        ]).unwrap();
+        assert_eq!(args.sensor_offline_seconds, 0);
+    }
+
+    #[test]
+    fn test_args_no_doors() {
+        let args = Args::try_parse_from(["door-monitor"]).unwrap();
+        assert!(args.all_doors().is_empty());
+    }
+
+    #[test]
+    fn test_parse_door_invalid() {
+        assert!(parse_door("http://test.com").is_err());
+        assert!(parse_door("=http://test.com").is_err());
+        assert!(parse_door("Garage=").is_err());
+        assert!(Args::try_parse_from(["door-monitor", "--door", "garage"]).is_err());
+    }
+
+    #[test]
+    fn test_webhook_headers() {
+        let args = Args::try_parse_from([
+            "door-monitor",
+            "--webhook-url", "http://hooks.test/door",
+            "--webhook-header", "Authorization: Bearer abc:123",
+            "--webhook-header", "X-Source:door-monitor",
+        ]).unwrap();
+
+        assert_eq!(args.webhook_url, Some("http://hooks.test/door".to_string()));
+        assert_eq!(args.webhook_headers, vec![
+            ("Authorization".to_string(), "Bearer abc:123".to_string()),
+            ("X-Source".to_string(), "door-monitor".to_string()),
+        ]);
+        assert!(parse_header("no colon").is_err());
+        assert!(parse_header(": value").is_err());
+    }
+
+    #[test]
+    fn test_rate_limits_and_quotas() {
+        let args = Args::try_parse_from([
+            "door-monitor",
+            "--rate-limit", "SMS=5/10m",
+            "--daily-quota", "SMS=20",
+            "--monthly-quota", "SMS=200",
+        ]).unwrap();
+
+        assert_eq!(args.rate_limits, vec![
+            ("SMS".to_string(), Rate { burst: 5, period: Duration::from_secs(600) }),
+        ]);
+        assert_eq!(args.daily_quotas, vec![("SMS".to_string(), 20)]);
+        assert_eq!(args.monthly_quotas, vec![("SMS".to_string(), 200)]);
+        assert!(args.overflow_channel.is_none());
+        assert!(parse_rate_limit("SMS=5").is_err());
+        assert!(parse_rate_limit("SMS=0/1h").is_err());
+        assert!(parse_rate_limit("=5/1h").is_err());
+        assert!(parse_quota("SMS=lots").is_err());
+    }
 }
@@ -0,0 +1,277 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Holds back a change of door state until the sensor has agreed on it for
+/// `readings_required` consecutive readings and for at least `stable_for`.
+///
+/// The default accepts every change straight away.
+#[derive(Debug, Clone)]
+pub struct Debouncer {
+    pub readings_required: u32,
+    pub stable_for: Duration,
+    candidate: Option<Candidate>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    door_closed: bool,
+    readings: u32,
+    since: Instant,
+}
+
+impl Debouncer {
+    pub fn new(readings_required: u32, stable_for: Duration) -> Self {
+        Self {
+            readings_required: readings_required.max(1),
+            stable_for,
+            candidate: None,
+        }
+    }
+
+    /// Records a reading that differs from the accepted state and returns
+    /// `true` once the change has been confirmed.
+    pub fn confirm(&mut self, door_closed: bool, now: Instant) -> bool {
+        let candidate = match self.candidate {
+            Some(mut candidate) if candidate.door_closed == door_closed => {
+                candidate.readings += 1;
+                candidate
+            }
+            _ => Candidate { door_closed, readings: 1, since: now },
+        };
+
+        if candidate.readings >= self.readings_required
+            && now.duration_since(candidate.since) >= self.stable_for
+        {
+            self.candidate = None;
+            true
+        } else {
+            self.candidate = Some(candidate);
+            false
+        }
+    }
+
+    /// Accepts a pending change that already has enough readings once it has
+    /// been stable for `stable_for`, without counting another reading.
+    pub fn settle(&mut self, now: Instant) -> bool {
+        match self.candidate {
+            Some(candidate)
+                if candidate.readings >= self.readings_required
+                    && now.duration_since(candidate.since) >= self.stable_for =>
+            {
+                self.candidate = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Forgets a pending change, called when a reading agrees with the accepted state again.
+    pub fn reset(&mut self) {
+        self.candidate = None;
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.candidate.is_some()
+    }
+}
+
+impl Default for Debouncer {
+    fn default() -> Self {
+        Self::new(1, Duration::ZERO)
+    }
+}
+
+/// Notices a sensor that keeps changing state, e.g. a loose reed switch.
+///
+/// Once `threshold` transitions have happened within `window` the sensor is
+/// flapping, and it settles again after a full `window` without transitions.
+/// A `threshold` of 0 turns detection off.
+#[derive(Debug, Clone)]
+pub struct FlapDetector {
+    pub threshold: usize,
+    pub window: Duration,
+    transitions: VecDeque<Instant>,
+    flapping: bool,
+}
+
+impl FlapDetector {
+    pub fn new(threshold: usize, window: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            transitions: VecDeque::new(),
+            flapping: false,
+        }
+    }
+
+    /// Records an accepted transition and returns `true` if it made the sensor start flapping.
+    pub fn record_transition(&mut self, now: Instant) -> bool {
+        if self.threshold == 0 {
+            return false;
+        }
+
+        self.transitions.push_back(now);
+        while let Some(&oldest) = self.transitions.front() {
+            if now.duration_since(oldest) > self.window {
+                self.transitions.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if !self.flapping && self.transitions.len() >= self.threshold {
+            self.flapping = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns `true` if a flapping sensor has now been quiet for a whole window.
+    pub fn settle(&mut self, now: Instant) -> bool {
+        let quiet = self
+            .transitions
+            .back()
+            .is_none_or(|&last| now.duration_since(last) >= self.window);
+
+        if self.flapping && quiet {
+            self.flapping = false;
+            self.transitions.clear();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_flapping(&self) -> bool {
+        self.flapping
+    }
+
+    /// Number of transitions seen in the current window.
+    pub fn transition_count(&self) -> usize {
+        self.transitions.len()
+    }
+}
+
+impl Default for FlapDetector {
+    fn default() -> Self {
+        Self::new(0, Duration::from_secs(60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_debouncer_accepts_immediately() {
+        let mut debouncer = Debouncer::default();
+        assert!(debouncer.confirm(false, Instant::now()));
+        assert!(!debouncer.is_pending());
+    }
+
+    #[test]
+    fn test_debouncer_requires_consecutive_readings() {
+        let mut debouncer = Debouncer::new(3, Duration::ZERO);
+        let now = Instant::now();
+
+        assert!(!debouncer.confirm(false, now));
+        assert!(!debouncer.confirm(false, now));
+        assert!(debouncer.is_pending());
+        assert!(debouncer.confirm(false, now));
+        assert!(!debouncer.is_pending());
+    }
+
+    #[test]
+    fn test_debouncer_restarts_when_reading_changes() {
+        let mut debouncer = Debouncer::new(2, Duration::ZERO);
+        let now = Instant::now();
+
+        assert!(!debouncer.confirm(false, now));
+        // A bounce back to the accepted state cancels the pending change
+        debouncer.reset();
+        assert!(!debouncer.confirm(false, now));
+        assert!(debouncer.confirm(false, now));
+
+        // A different candidate starts counting from scratch
+        assert!(!debouncer.confirm(true, now));
+        assert!(!debouncer.confirm(false, now));
+    }
+
+    #[test]
+    fn test_debouncer_requires_stable_time() {
+        let mut debouncer = Debouncer::new(1, Duration::from_secs(10));
+        let start = Instant::now();
+
+        assert!(!debouncer.confirm(false, start));
+        assert!(!debouncer.confirm(false, start + Duration::from_secs(5)));
+        assert!(debouncer.confirm(false, start + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_debouncer_settles_without_readings() {
+        let mut debouncer = Debouncer::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+
+        // Time alone doesn't make up for missing readings
+        assert!(!debouncer.confirm(false, start));
+        assert!(!debouncer.settle(start + Duration::from_secs(20)));
+        assert!(!debouncer.confirm(false, start + Duration::from_secs(1)));
+        assert!(!debouncer.settle(start + Duration::from_secs(5)));
+        assert!(debouncer.settle(start + Duration::from_secs(10)));
+        assert!(!debouncer.is_pending());
+    }
+
+    #[test]
+    fn test_flap_detector_disabled_by_default() {
+        let mut detector = FlapDetector::default();
+        let now = Instant::now();
+        for _ in 0..10 {
+            assert!(!detector.record_transition(now));
+        }
+        assert!(!detector.is_flapping());
+    }
+
+    #[test]
+    fn test_flap_detector_starts_flapping_once() {
+        let mut detector = FlapDetector::new(3, Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(!detector.record_transition(start));
+        assert!(!detector.record_transition(start + Duration::from_secs(10)));
+        assert!(detector.record_transition(start + Duration::from_secs(20)));
+        assert!(detector.is_flapping());
+        // Only the first crossing of the threshold is reported
+        assert!(!detector.record_transition(start + Duration::from_secs(30)));
+        assert_eq!(detector.transition_count(), 4);
+    }
+
+    #[test]
+    fn test_flap_detector_ignores_transitions_outside_window() {
+        let mut detector = FlapDetector::new(3, Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(!detector.record_transition(start));
+        assert!(!detector.record_transition(start + Duration::from_secs(50)));
+        assert!(!detector.record_transition(start + Duration::from_secs(120)));
+        assert!(!detector.is_flapping());
+        assert_eq!(detector.transition_count(), 1);
+    }
+
+    #[test]
+    fn test_flap_detector_settles_after_quiet_window() {
+        let mut detector = FlapDetector::new(2, Duration::from_secs(60));
+        let start = Instant::now();
+
+        detector.record_transition(start);
+        assert!(detector.record_transition(start + Duration::from_secs(1)));
+
+        assert!(!detector.settle(start + Duration::from_secs(30)));
+        assert!(detector.is_flapping());
+        assert!(detector.settle(start + Duration::from_secs(61)));
+        assert!(!detector.is_flapping());
+        assert_eq!(detector.transition_count(), 0);
+        // Settling is only reported once
+        assert!(!detector.settle(start + Duration::from_secs(120)));
+    }
+}
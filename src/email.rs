@@ -0,0 +1,260 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use clap::ValueEnum;
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::config::Args;
+use crate::error::Error;
+use crate::notifier::Notifier;
+use crate::state_machine::Event;
+
+/// How the connection to the SMTP relay is encrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SmtpSecurity {
+    /// Connect in plain text and upgrade with STARTTLS, usually on port 587
+    Starttls,
+    /// Connect with TLS straight away, usually on port 465
+    Tls,
+    /// No encryption, only for relays on a trusted network
+    None,
+}
+
+impl SmtpSecurity {
+    pub fn default_port(self) -> u16 {
+        match self {
+            SmtpSecurity::Starttls => 587,
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::None => 25,
+        }
+    }
+}
+
+/// Where and how to connect to the SMTP relay.
+#[derive(Debug, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    /// Defaults to the usual port for `security`
+    pub port: Option<u16>,
+    pub security: SmtpSecurity,
+    /// Username and password for AUTH, if the relay needs them
+    pub credentials: Option<(String, String)>,
+    pub timeout: Duration,
+}
+
+/// Sends events as plain text emails through an SMTP relay.
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailNotifier {
+    pub fn new(settings: &SmtpSettings, from: &str, to: &[String]) -> Result<Self, Error> {
+        let builder = match settings.security {
+            SmtpSecurity::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)?,
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host)?,
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host),
+        };
+        let mut builder = builder
+            .port(settings.port.unwrap_or(settings.security.default_port()))
+            .timeout(Some(settings.timeout));
+        if let Some((username, password)) = &settings.credentials {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        let parse = |address: &str| {
+            address
+                .parse::<Mailbox>()
+                .map_err(|e| Error::InvalidConfig(format!("Invalid email address '{}': {}", address, e)))
+        };
+        Ok(Self {
+            transport: builder.build(),
+            from: parse(from)?,
+            to: to.iter().map(|address| parse(address)).collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// The email notifier configured with the `--email-*` options, if `--email-smtp-host` is set.
+    pub fn from_args(args: &Args) -> Result<Option<Self>, Error> {
+        let Some(host) = &args.email_smtp_host else {
+            return Ok(None);
+        };
+        let Some(from) = &args.email_from else {
+            return Err(Error::InvalidConfig("--email-from is required to send email".to_string()));
+        };
+        if args.email_to.is_empty() {
+            return Err(Error::InvalidConfig("at least one --email-to is required to send email".to_string()));
+        }
+
+        let settings = SmtpSettings {
+            host: host.clone(),
+            port: args.email_smtp_port,
+            security: args.email_smtp_security,
+            credentials: args.email_smtp_username.clone().zip(args.email_smtp_password.clone()),
+            timeout: Duration::from_secs(args.request_timeout_seconds),
+        };
+        Self::new(&settings, from, &args.email_to).map(Some)
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &str {
+        "Email"
+    }
+
+    async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(event.title(door))
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = message
+            .body(event.message(door))
+            .map_err(|e| Error::InvalidConfig(format!("Invalid email: {}", e)))?;
+
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// A minimal SMTP server that accepts one connection, records every line
+    /// the client sends, and rejects recipients if `reject_recipients` is set.
+    async fn smtp_stand_in(reject_recipients: bool) -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let transcript = Arc::new(Mutex::new(Vec::new()));
+        let lines = transcript.clone();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut reader = BufReader::new(reader).lines();
+            writer.write_all(b"220 localhost ESMTP stand-in\r\n").await.unwrap();
+
+            let mut in_data = false;
+            while let Ok(Some(line)) = reader.next_line().await {
+                lines.lock().unwrap().push(line.clone());
+                let reply: &[u8] = if in_data {
+                    if line != "." {
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 2.0.0 Queued\r\n"
+                } else if line.starts_with("EHLO") {
+                    b"250-localhost\r\n250-AUTH PLAIN LOGIN\r\n250 8BITMIME\r\n"
+                } else if line.starts_with("AUTH") {
+                    b"235 2.7.0 Authentication successful\r\n"
+                } else if line.starts_with("RCPT") && reject_recipients {
+                    b"550 5.1.1 No such user\r\n"
+                } else if line == "DATA" {
+                    in_data = true;
+                    b"354 End data with <CR><LF>.<CR><LF>\r\n"
+                } else if line == "QUIT" {
+                    writer.write_all(b"221 2.0.0 Bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 2.0.0 OK\r\n"
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+        });
+
+        (port, transcript)
+    }
+
+    fn stand_in_settings(port: u16) -> SmtpSettings {
+        SmtpSettings {
+            host: "127.0.0.1".to_string(),
+            port: Some(port),
+            security: SmtpSecurity::None,
+            credentials: None,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sends_email_with_auth() {
+        let (port, transcript) = smtp_stand_in(false).await;
+        let settings = SmtpSettings {
+            credentials: Some(("door".to_string(), "secret".to_string())),
+            ..stand_in_settings(port)
+        };
+        let to = ["alice@example.com".to_string(), "bob@example.com".to_string()];
+        let notifier = EmailNotifier::new(&settings, "Door Monitor <door@example.com>", &to).unwrap();
+
+        let event = Event::OpenTooLong { open_for: Duration::from_secs(300), reminder: 0 };
+        notifier.send("Garage door", &event).await.unwrap();
+
+        let transcript = transcript.lock().unwrap();
+        // AUTH PLAIN with base64("\0door\0secret")
+        assert!(transcript.contains(&"AUTH PLAIN AGRvb3IAc2VjcmV0".to_string()));
+        assert!(transcript.contains(&"MAIL FROM:<door@example.com>".to_string()));
+        assert!(transcript.contains(&"RCPT TO:<alice@example.com>".to_string()));
+        assert!(transcript.contains(&"RCPT TO:<bob@example.com>".to_string()));
+        assert!(transcript.contains(&"Subject: ALERT: Garage door open too long".to_string()));
+        assert!(transcript.contains(&"ALERT: Garage door has been open for 00:05:00".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_recipient() {
+        let (port, _) = smtp_stand_in(true).await;
+        let notifier = EmailNotifier::new(&stand_in_settings(port), "door@example.com", &["nobody@example.com".to_string()])
+            .unwrap();
+
+        let error = notifier.send("Garage door", &Event::Opened).await.unwrap_err();
+        assert!(matches!(error, Error::NotifierSmtp(_)));
+        assert!(!error.is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_from_args() {
+        let args = Args::try_parse_from(["test"]).unwrap();
+        assert!(EmailNotifier::from_args(&args).unwrap().is_none());
+
+        // A partial configuration is refused up front rather than disabling email
+        assert!(Args::try_parse_from([
+            "test", "--email-smtp-host", "smtp.example.com", "--email-to", "alice@example.com",
+        ]).is_err());
+        assert!(Args::try_parse_from([
+            "test", "--email-smtp-host", "smtp.example.com", "--email-from", "door@example.com",
+        ]).is_err());
+        assert!(Args::try_parse_from([
+            "test", "--email-smtp-host", "smtp.example.com", "--email-smtp-username", "door",
+            "--email-from", "door@example.com", "--email-to", "alice@example.com",
+        ]).is_err());
+        assert!(Args::try_parse_from([
+            "test", "--email-smtp-host", "smtp.example.com", "--email-smtp-password", "secret",
+            "--email-from", "door@example.com", "--email-to", "alice@example.com",
+        ]).is_err());
+
+        let args = Args::try_parse_from([
+            "test", "--email-smtp-host", "smtp.example.com", "--email-smtp-security", "tls",
+            "--email-from", "door@example.com", "--email-to", "not an address",
+        ]).unwrap();
+        assert!(matches!(EmailNotifier::from_args(&args), Err(Error::InvalidConfig(_))));
+
+        let args = Args::try_parse_from([
+            "test", "--email-smtp-host", "smtp.example.com", "--email-smtp-username", "door",
+            "--email-smtp-password", "secret", "--email-from", "door@example.com",
+            "--email-to", "alice@example.com",
+        ]).unwrap();
+        let notifier = EmailNotifier::from_args(&args).unwrap().unwrap();
+        assert_eq!(notifier.name(), "Email");
+        assert_eq!(notifier.to.len(), 1);
+    }
+}
@@ -0,0 +1,202 @@
+use reqwest::StatusCode;
+
+/// Errors from reading sensors and sending notifications.
+///
+/// The variants say where something went wrong, so callers can decide
+/// whether to retry (see `is_transient`), alert, or give up.
+#[derive(Debug)]
+pub enum Error {
+    /// The sensor couldn't be reached or the request failed part way
+    SensorHttp(reqwest::Error),
+    /// The sensor answered with a status other than 2xx
+    SensorStatus(StatusCode),
+    /// The sensor's response couldn't be turned into a door state
+    SensorParse(String),
+    /// The sensor asked for authentication that couldn't be answered
+    SensorAuth(String),
+    /// The sensor's connection is down
+    SensorDisconnected(String),
+    /// A sensor URL or option is invalid
+    InvalidConfig(String),
+    /// A socket or file operation failed
+    Io(std::io::Error),
+    /// The notification service couldn't be reached
+    NotifierHttp {
+        provider: String,
+        source: reqwest::Error,
+    },
+    /// The SMTP relay couldn't be reached or refused the email
+    NotifierSmtp(lettre::transport::smtp::Error),
+    /// The notification service answered, but didn't accept the message
+    NotifierRejected {
+        provider: String,
+        status: StatusCode,
+        body: String,
+    },
+    /// The notification service answered with a success HTTP status, but its
+    /// response says the message wasn't accepted, e.g. voip.ms's
+    /// `{"status":"invalid_credentials"}`
+    NotifierStatus {
+        provider: String,
+        status: String,
+        description: String,
+        transient: bool,
+    },
+    /// Some of a channel's recipients didn't get the message, the others did
+    RecipientsFailed {
+        provider: String,
+        recipients: usize,
+        failures: Vec<(String, Error)>,
+    },
+    /// A notification channel is enabled but its credentials aren't configured
+    MissingCredentials { provider: String },
+    /// A channel reached its rate limit or quota and is holding the message
+    /// back until it resets
+    ChannelLimited { provider: String, limit: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Whether the same request may succeed if tried again.
+    ///
+    /// Timeouts, dropped connections and 5xx responses are transient. A 404
+    /// or a response that can't be parsed means the sensor is misconfigured
+    /// and retrying won't help.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::SensorHttp(e) | Error::NotifierHttp { source: e, .. } => {
+                !e.is_decode() && !e.is_builder() && !e.is_redirect()
+            }
+            Error::SensorStatus(status) | Error::NotifierRejected { status, .. } => {
+                status.is_server_error()
+                    || *status == StatusCode::REQUEST_TIMEOUT
+                    || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            Error::NotifierSmtp(e) => !(e.is_permanent() || e.is_client() || e.is_response() || e.is_tls()),
+            Error::NotifierStatus { transient, .. } => *transient,
+            Error::RecipientsFailed { failures, .. } => failures.iter().all(|(_, e)| e.is_transient()),
+            Error::Io(_) | Error::SensorDisconnected(_) | Error::ChannelLimited { .. } => true,
+            Error::SensorParse(_)
+            | Error::SensorAuth(_)
+            | Error::InvalidConfig(_)
+            | Error::MissingCredentials { .. } => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::SensorHttp(e) => write!(f, "Sensor request failed: {}", e),
+            Error::SensorStatus(status) => write!(f, "HTTP error: {}", status),
+            Error::SensorParse(message) => f.write_str(message),
+            Error::SensorAuth(message) => write!(f, "Sensor authentication failed: {}", message),
+            Error::SensorDisconnected(message) => write!(f, "Sensor disconnected: {}", message),
+            Error::InvalidConfig(message) => f.write_str(message),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::NotifierHttp { provider, source } => write!(f, "{} request failed: {}", provider, source),
+            Error::NotifierSmtp(e) => write!(f, "Email failed: {}", e),
+            Error::NotifierRejected { provider, status, body } => {
+                write!(f, "{} rejected the message with HTTP {}: {}", provider, status, body)
+            }
+            Error::NotifierStatus { provider, status, description, .. } => {
+                write!(f, "{} refused the message: {} ({})", provider, status, description)
+            }
+            Error::RecipientsFailed { provider, recipients, failures } => {
+                write!(f, "{} failed for {} of {} recipients", provider, failures.len(), recipients)?;
+                for (i, (recipient, e)) in failures.iter().enumerate() {
+                    write!(f, "{} {}: {}", if i == 0 { ":" } else { ";" }, recipient, e)?;
+                }
+                Ok(())
+            }
+            Error::MissingCredentials { provider } => write!(f, "{} credentials are not configured", provider),
+            Error::ChannelLimited { provider, limit } => write!(f, "{} {} reached", provider, limit),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::SensorHttp(e) | Error::NotifierHttp { source: e, .. } => Some(e),
+            Error::NotifierSmtp(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::SensorHttp(e)
+    }
+}
+
+impl From<lettre::transport::smtp::Error> for Error {
+    fn from(e: lettre::transport::smtp::Error) -> Self {
+        Error::NotifierSmtp(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::SensorParse(format!("Invalid JSON from sensor: {}", e))
+    }
+}
+
+impl From<std::string::FromUtf8Error> for Error {
+    fn from(e: std::string::FromUtf8Error) -> Self {
+        Error::InvalidConfig(format!("Invalid percent-encoding: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_errors() {
+        assert!(Error::SensorStatus(StatusCode::SERVICE_UNAVAILABLE).is_transient());
+        assert!(Error::SensorStatus(StatusCode::TOO_MANY_REQUESTS).is_transient());
+        assert!(!Error::SensorStatus(StatusCode::NOT_FOUND).is_transient());
+        assert!(!Error::SensorStatus(StatusCode::UNAUTHORIZED).is_transient());
+        assert_eq!(Error::SensorStatus(StatusCode::NOT_FOUND).to_string(), "HTTP error: 404 Not Found");
+    }
+
+    #[test]
+    fn test_parse_and_config_errors_are_permanent() {
+        let error = Error::from(serde_json::from_str::<u8>("invalid").unwrap_err());
+        assert!(matches!(error, Error::SensorParse(_)));
+        assert!(!error.is_transient());
+
+        assert!(!Error::InvalidConfig("unknown MQTT option 'colour'".to_string()).is_transient());
+        assert!(!Error::MissingCredentials { provider: "voip.ms".to_string() }.is_transient());
+    }
+
+    #[test]
+    fn test_io_errors_are_transient() {
+        let error = Error::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+        assert!(error.is_transient());
+    }
+
+    #[test]
+    fn test_notifier_rejected_display() {
+        let error = Error::NotifierRejected {
+            provider: "Telegram".to_string(),
+            status: StatusCode::BAD_REQUEST,
+            body: r#"{"ok":false,"description":"Bad Request: chat not found"}"#.to_string(),
+        };
+        assert!(!error.is_transient());
+        assert_eq!(
+            error.to_string(),
+            r#"Telegram rejected the message with HTTP 400 Bad Request: {"ok":false,"description":"Bad Request: chat not found"}"#
+        );
+    }
+}
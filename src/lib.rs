@@ -5,3 +5,20 @@ pub mod utils;
 pub mod sms;
 pub mod telegram;
 pub mod monitor;
+pub mod webhook;
+pub mod shelly_ws;
+pub mod mqtt;
+pub mod debounce;
+pub mod retry;
+pub mod error;
+pub mod clock;
+pub mod state_machine;
+pub mod notifier;
+pub mod webhook_notifier;
+pub mod email;
+pub mod push;
+pub mod chat;
+pub mod telegram_bot;
+pub mod outbox;
+pub mod limits;
+pub mod templates;
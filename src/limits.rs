@@ -0,0 +1,640 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Args;
+use crate::error::Error;
+use crate::notifier::{Notifier, check_recipients, send_logged};
+use crate::state_machine::Event;
+use crate::utils::format_duration;
+
+/// The channel told about limits when `--overflow-channel` isn't given
+const DEFAULT_OVERFLOW_CHANNEL: &str = "Telegram";
+
+/// A token bucket: up to `burst` messages at once, refilled at `burst`
+/// messages per `period`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rate {
+    pub burst: u32,
+    pub period: Duration,
+}
+
+/// How many messages a channel may send.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChannelLimits {
+    pub rate: Option<Rate>,
+    /// Messages per UTC day
+    pub daily: Option<u32>,
+    /// Messages per UTC month
+    pub monthly: Option<u32>,
+}
+
+/// The limit that stopped a message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Rate(Rate),
+    Daily(u32),
+    Monthly(u32),
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Rate(rate) => write!(f, "rate limit ({} per {})", rate.burst, format_duration(rate.period)),
+            Limit::Daily(quota) => write!(f, "daily quota ({} messages)", quota),
+            Limit::Monthly(quota) => write!(f, "monthly quota ({} messages)", quota),
+        }
+    }
+}
+
+/// What a channel has sent, kept across restarts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub day: NaiveDate,
+    pub today: u32,
+    pub this_month: u32,
+    /// Tokens left in the rate limit bucket at `updated_at`
+    pub tokens: f64,
+    pub updated_at: DateTime<Utc>,
+    /// Whether the channel has been held back since it last sent a message,
+    /// so the other channels are only told once
+    #[serde(default)]
+    pub limited: bool,
+}
+
+impl Usage {
+    fn new(limits: &ChannelLimits, now: DateTime<Utc>) -> Self {
+        Self {
+            day: now.date_naive(),
+            today: 0,
+            this_month: 0,
+            tokens: limits.rate.map_or(0.0, |rate| rate.burst as f64),
+            updated_at: now,
+            limited: false,
+        }
+    }
+
+    /// Refills the bucket and starts new days and months up to `now`.
+    fn catch_up(&mut self, limits: &ChannelLimits, now: DateTime<Utc>) {
+        let today = now.date_naive();
+        if today != self.day {
+            if (today.year(), today.month()) != (self.day.year(), self.day.month()) {
+                self.this_month = 0;
+            }
+            self.today = 0;
+            self.day = today;
+        }
+        if let Some(rate) = limits.rate {
+            let elapsed = (now - self.updated_at).to_std().unwrap_or_default();
+            let refill = rate.burst as f64 * elapsed.as_secs_f64() / rate.period.as_secs_f64();
+            self.tokens = (self.tokens + refill).min(rate.burst as f64);
+        }
+        self.updated_at = now;
+    }
+
+    /// The first limit that `limits` puts in the way of another message.
+    fn exceeded(&self, limits: &ChannelLimits) -> Option<Limit> {
+        match (limits.monthly, limits.daily, limits.rate) {
+            (Some(quota), _, _) if self.this_month >= quota => Some(Limit::Monthly(quota)),
+            (_, Some(quota), _) if self.today >= quota => Some(Limit::Daily(quota)),
+            (_, _, Some(rate)) if self.tokens < 1.0 => Some(Limit::Rate(rate)),
+            _ => None,
+        }
+    }
+}
+
+/// Rate limits and daily and monthly quotas per channel, so a flapping door
+/// can't run up a large SMS bill. Channels are matched by name, ignoring case.
+///
+/// With a file, the counters are written to it after every message, so
+/// they carry on where they were after a restart.
+pub struct Limits {
+    limits: HashMap<String, ChannelLimits>,
+    path: Option<PathBuf>,
+    usage: Mutex<HashMap<String, Usage>>,
+}
+
+impl Limits {
+    /// Limits that only count in memory.
+    pub fn new(limits: HashMap<String, ChannelLimits>) -> Self {
+        Self {
+            limits: limits.into_iter().map(|(channel, limits)| (channel.to_lowercase(), limits)).collect(),
+            path: None,
+            usage: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Keeps the counters in the file at `path`, reading them from it if
+    /// it exists.
+    pub fn with_file(mut self, path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        match fs::read_to_string(&path) {
+            Ok(contents) => *self.usage.get_mut().unwrap() = serde_json::from_str(&contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        self.path = Some(path);
+        Ok(self)
+    }
+
+    /// The limits from `--rate-limit`, `--daily-quota` and `--monthly-quota`,
+    /// counted in `--usage-file` if given.
+    pub fn from_args(args: &Args) -> Result<Self, Error> {
+        let mut limits: HashMap<String, ChannelLimits> = HashMap::new();
+        for (channel, rate) in &args.rate_limits {
+            limits.entry(channel.to_lowercase()).or_default().rate = Some(*rate);
+        }
+        for (channel, quota) in &args.daily_quotas {
+            limits.entry(channel.to_lowercase()).or_default().daily = Some(*quota);
+        }
+        for (channel, quota) in &args.monthly_quotas {
+            limits.entry(channel.to_lowercase()).or_default().monthly = Some(*quota);
+        }
+        let limits = Self::new(limits);
+        match &args.usage_file {
+            Some(path) => limits.with_file(path),
+            None => Ok(limits),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.limits.is_empty()
+    }
+
+    /// Whether `channel` has any limits.
+    pub fn has_limits(&self, channel: &str) -> bool {
+        self.limits.contains_key(&channel.to_lowercase())
+    }
+
+    /// Whether `channel` may send another message at `now`. Returns the
+    /// limit in the way if not.
+    pub fn check(&self, channel: &str, now: DateTime<Utc>) -> Result<(), Limit> {
+        let key = channel.to_lowercase();
+        let Some(limits) = self.limits.get(&key) else {
+            return Ok(());
+        };
+        let mut usage = self.usage.lock().unwrap();
+        let entry = usage.entry(key).or_insert_with(|| Usage::new(limits, now));
+        entry.catch_up(limits, now);
+        match entry.exceeded(limits) {
+            Some(limit) => Err(limit),
+            None => Ok(()),
+        }
+    }
+
+    /// Counts a message sent through `channel` at `now`.
+    pub fn record(&self, channel: &str, now: DateTime<Utc>) {
+        let key = channel.to_lowercase();
+        let Some(limits) = self.limits.get(&key) else {
+            return;
+        };
+        let mut usage = self.usage.lock().unwrap();
+        let entry = usage.entry(key).or_insert_with(|| Usage::new(limits, now));
+        entry.catch_up(limits, now);
+        entry.today += 1;
+        entry.this_month += 1;
+        if limits.rate.is_some() {
+            entry.tokens -= 1.0;
+        }
+        entry.limited = false;
+        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+        println!("[{}] {} usage: {}", timestamp, channel, describe(entry, limits));
+        self.save(&usage);
+    }
+
+    /// Checks that `overflow`, if given, names one of `channels` that has no
+    /// limits of its own.
+    pub fn check_overflow(&self, overflow: Option<&str>, channels: &[&str]) -> Result<(), Error> {
+        let Some(overflow) = overflow else {
+            return Ok(());
+        };
+        if !channels.iter().any(|channel| channel.eq_ignore_ascii_case(overflow)) {
+            return Err(Error::InvalidConfig(format!(
+                "--overflow-channel {} is not enabled, the channels are: {}",
+                overflow,
+                channels.join(", ")
+            )));
+        }
+        if self.has_limits(overflow) {
+            return Err(Error::InvalidConfig(format!(
+                "--overflow-channel {} has limits of its own",
+                overflow
+            )));
+        }
+        Ok(())
+    }
+
+    /// Marks `channel` as held back. Returns true the first time since it
+    /// last sent a message, when the other channels should be told.
+    pub fn hold_back(&self, channel: &str) -> bool {
+        let mut usage = self.usage.lock().unwrap();
+        let Some(entry) = usage.get_mut(&channel.to_lowercase()) else {
+            return false;
+        };
+        let first = !entry.limited;
+        entry.limited = true;
+        if first {
+            self.save(&usage);
+        }
+        first
+    }
+
+    /// What `channel` has sent against its limits, e.g. "3 of 20 today,
+    /// 45 of 200 this month", or None if it has no limits.
+    pub fn usage(&self, channel: &str, now: DateTime<Utc>) -> Option<String> {
+        let key = channel.to_lowercase();
+        let limits = self.limits.get(&key)?;
+        let mut usage = self.usage.lock().unwrap();
+        let entry = usage.entry(key).or_insert_with(|| Usage::new(limits, now));
+        entry.catch_up(limits, now);
+        Some(describe(entry, limits))
+    }
+
+    /// Writes the counters to the file, logging a failure.
+    fn save(&self, usage: &HashMap<String, Usage>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let mut temporary = path.clone().into_os_string();
+        temporary.push(".tmp");
+        let result = serde_json::to_string_pretty(usage).map_err(Error::from).and_then(|contents| {
+            fs::write(&temporary, contents)?;
+            fs::rename(&temporary, path)?;
+            Ok(())
+        });
+        if let Err(e) = result {
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            eprintln!("[{}] Failed to save the usage counters to {}: {}", timestamp, path.display(), e);
+        }
+    }
+}
+
+fn describe(usage: &Usage, limits: &ChannelLimits) -> String {
+    let count = |sent: u32, quota: Option<u32>| match quota {
+        Some(quota) => format!("{} of {}", sent, quota),
+        None => sent.to_string(),
+    };
+    let mut text = format!(
+        "{} today, {} this month",
+        count(usage.today, limits.daily),
+        count(usage.this_month, limits.monthly)
+    );
+    if let Some(rate) = limits.rate {
+        text.push_str(&format!(", {} of {} left in the rate limit", usage.tokens.floor() as u32, rate.burst));
+    }
+    text
+}
+
+/// Sends through `inner` while its channel is within its limits, counting
+/// only the messages that went out. Past a limit, `overflow` is told once
+/// with an `Event::LimitReached`. Open door alerts, and backlogs of them,
+/// fail with `Error::ChannelLimited` so the outbox keeps them until the limit
+/// resets; other messages are dropped, since the other channels still get
+/// them.
+pub struct LimitedNotifier {
+    inner: Arc<dyn Notifier>,
+    limits: Arc<Limits>,
+    overflow: Option<Arc<dyn Notifier>>,
+}
+
+impl LimitedNotifier {
+    pub fn new(inner: Arc<dyn Notifier>, limits: Arc<Limits>, overflow: Option<Arc<dyn Notifier>>) -> Self {
+        Self { inner, limits, overflow }
+    }
+
+    /// Sends one message about `event` with `send` while the channel is
+    /// within its limits, and counts it once it went out.
+    async fn send_limited(
+        &self,
+        door: &str,
+        event: &Event,
+        send: impl Future<Output = Result<(), Error>> + Send,
+    ) -> Result<(), Error> {
+        let limit = match self.limits.check(self.name(), Utc::now()) {
+            Ok(()) => {
+                send.await?;
+                self.limits.record(self.name(), Utc::now());
+                return Ok(());
+            }
+            Err(limit) => limit,
+        };
+
+        let held = matches!(event.base(), Event::OpenTooLong { .. } | Event::Missed { .. });
+        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+        if held {
+            println!("[{}] {} {} reached, holding back {} {}", timestamp, self.name(), limit, event.label(), self.name());
+        } else {
+            println!("[{}] {} {} reached, not sending {} {}", timestamp, self.name(), limit, event.label(), self.name());
+        }
+        if self.limits.hold_back(self.name()) {
+            let notice = Event::LimitReached {
+                channel: self.name().to_string(),
+                limit: limit.to_string(),
+            };
+            match &self.overflow {
+                Some(overflow) => {
+                    // A failure is already logged, and isn't this channel's failure
+                    let _ = send_logged(overflow.as_ref(), None, door, &notice, Utc::now()).await;
+                }
+                None => eprintln!("[{}] {}", timestamp, notice.message(door)),
+            }
+        }
+        if held {
+            return Err(Error::ChannelLimited {
+                provider: self.name().to_string(),
+                limit: limit.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for LimitedNotifier {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+        self.send_at(door, event, Utc::now()).await
+    }
+
+    async fn send_at(&self, door: &str, event: &Event, at: DateTime<Utc>) -> Result<(), Error> {
+        let recipients = self.inner.recipients();
+        if recipients.is_empty() {
+            return self.send_limited(door, event, self.inner.send_at(door, event, at)).await;
+        }
+        // Every number or chat is a message of its own against the limits
+        let mut results = Vec::new();
+        for recipient in recipients {
+            let result = self.send_to(&recipient, door, event, at).await;
+            results.push((recipient, result));
+        }
+        check_recipients(self.name(), results)
+    }
+
+    fn recipients(&self) -> Vec<String> {
+        self.inner.recipients()
+    }
+
+    async fn send_to(&self, recipient: &str, door: &str, event: &Event, at: DateTime<Utc>) -> Result<(), Error> {
+        self.send_limited(door, event, self.inner.send_to(recipient, door, event, at)).await
+    }
+}
+
+/// Wraps the notifiers that have limits in a `LimitedNotifier`, telling the
+/// `overflow` channel when they reach them. Without one, Telegram is told
+/// if it's enabled and has no limits.
+pub fn limit_notifiers(
+    notifiers: Vec<Arc<dyn Notifier>>,
+    limits: &Arc<Limits>,
+    overflow: Option<&str>,
+) -> Vec<Arc<dyn Notifier>> {
+    if limits.is_empty() {
+        return notifiers;
+    }
+    let overflow = overflow.unwrap_or(DEFAULT_OVERFLOW_CHANNEL);
+    let fallback = notifiers
+        .iter()
+        .find(|notifier| notifier.name().eq_ignore_ascii_case(overflow) && !limits.has_limits(notifier.name()))
+        .cloned();
+    notifiers
+        .into_iter()
+        .map(|notifier| -> Arc<dyn Notifier> {
+            if limits.has_limits(notifier.name()) {
+                Arc::new(LimitedNotifier::new(notifier, limits.clone(), fallback.clone()))
+            } else {
+                notifier
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use clap::Parser;
+
+    #[derive(Default)]
+    struct Recording {
+        name: &'static str,
+        recipients: Vec<String>,
+        sent: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Notifier for Recording {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+            self.sent.lock().unwrap().push(event.message(door));
+            Ok(())
+        }
+
+        fn recipients(&self) -> Vec<String> {
+            self.recipients.clone()
+        }
+
+        async fn send_to(&self, recipient: &str, door: &str, event: &Event, _at: DateTime<Utc>) -> Result<(), Error> {
+            self.sent.lock().unwrap().push(format!("{}: {}", recipient, event.message(door)));
+            Ok(())
+        }
+    }
+
+    struct Failing;
+
+    #[async_trait]
+    impl Notifier for Failing {
+        fn name(&self) -> &str {
+            "SMS"
+        }
+
+        async fn send(&self, _door: &str, _event: &Event) -> Result<(), Error> {
+            Err(Error::Io(std::io::Error::from(std::io::ErrorKind::TimedOut)))
+        }
+    }
+
+    /// Checks and counts a message, as a successful send does.
+    fn take(limits: &Limits, channel: &str, now: DateTime<Utc>) -> Result<(), Limit> {
+        limits.check(channel, now)?;
+        limits.record(channel, now);
+        Ok(())
+    }
+
+    fn sms_limits(limits: ChannelLimits) -> Limits {
+        Limits::new(HashMap::from([("SMS".to_string(), limits)]))
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let limits = sms_limits(ChannelLimits {
+            rate: Some(Rate { burst: 2, period: Duration::from_secs(600) }),
+            ..Default::default()
+        });
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 10, 0, 0).unwrap();
+
+        assert!(take(&limits, "SMS", now).is_ok());
+        assert!(take(&limits, "sms", now).is_ok());
+        assert!(matches!(take(&limits, "SMS", now), Err(Limit::Rate(_))));
+        // One message every 5 minutes
+        assert!(take(&limits, "SMS", now + chrono::Duration::minutes(4)).is_err());
+        assert!(take(&limits, "SMS", now + chrono::Duration::minutes(5)).is_ok());
+        // Other channels aren't limited
+        assert!(take(&limits, "Telegram", now).is_ok());
+        assert_eq!(
+            limits.usage("SMS", now + chrono::Duration::hours(1)).unwrap(),
+            "3 today, 3 this month, 2 of 2 left in the rate limit"
+        );
+    }
+
+    #[test]
+    fn test_daily_and_monthly_quotas() {
+        let limits = sms_limits(ChannelLimits {
+            daily: Some(2),
+            monthly: Some(3),
+            ..Default::default()
+        });
+        let day = Utc.with_ymd_and_hms(2026, 10, 30, 10, 0, 0).unwrap();
+
+        assert!(take(&limits, "SMS", day).is_ok());
+        assert!(take(&limits, "SMS", day).is_ok());
+        assert_eq!(take(&limits, "SMS", day), Err(Limit::Daily(2)));
+        let next_day = day + chrono::Duration::days(1);
+        assert!(take(&limits, "SMS", next_day).is_ok());
+        assert_eq!(take(&limits, "SMS", next_day), Err(Limit::Monthly(3)));
+        assert_eq!(limits.usage("SMS", next_day).unwrap(), "1 of 2 today, 3 of 3 this month");
+        // A new month starts over
+        assert!(take(&limits, "SMS", next_day + chrono::Duration::days(1)).is_ok());
+        assert_eq!(Limit::Daily(20).to_string(), "daily quota (20 messages)");
+    }
+
+    #[test]
+    fn test_counters_survive_restart() {
+        let path = std::env::temp_dir().join(format!("door-monitor-usage-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let args = Args::try_parse_from([
+            "test", "--daily-quota", "SMS=2", "--usage-file", path.to_str().unwrap(),
+        ]).unwrap();
+        let now = Utc::now();
+
+        let limits = Limits::from_args(&args).unwrap();
+        take(&limits, "SMS", now).unwrap();
+        take(&limits, "SMS", now).unwrap();
+        assert!(take(&limits, "SMS", now).is_err());
+        assert!(limits.hold_back("SMS"));
+
+        let limits = Limits::from_args(&args).unwrap();
+        assert!(take(&limits, "SMS", now).is_err());
+        // Already told before the restart
+        assert!(!limits.hold_back("SMS"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_overflow_channel_is_told_once() {
+        let limits = Arc::new(sms_limits(ChannelLimits {
+            daily: Some(1),
+            ..Default::default()
+        }));
+        let sms = Arc::new(Recording { name: "SMS", ..Default::default() });
+        let telegram = Arc::new(Recording { name: "Telegram", ..Default::default() });
+        let notifiers = limit_notifiers(vec![sms.clone(), telegram.clone()], &limits, Some("telegram"));
+
+        for event in [Event::Opened, Event::Closed { open_for: Duration::from_secs(60) }, Event::Opened] {
+            for notifier in &notifiers {
+                notifier.send("Garage", &event).await.unwrap();
+            }
+        }
+
+        assert_eq!(*sms.sent.lock().unwrap(), vec!["Garage has been opened"]);
+        let sent = telegram.sent.lock().unwrap();
+        assert_eq!(sent.len(), 4);
+        assert_eq!(
+            sent[1],
+            "WARNING: SMS daily quota (1 messages) reached, Garage notifications only go through the other channels until it resets"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_each_recipient_counts_against_the_quota() {
+        let limits = Arc::new(sms_limits(ChannelLimits {
+            daily: Some(3),
+            ..Default::default()
+        }));
+        let sms = Arc::new(Recording {
+            name: "SMS",
+            recipients: vec!["+15551234567".to_string(), "+15557654321".to_string()],
+            ..Default::default()
+        });
+        let notifier = LimitedNotifier::new(sms.clone(), limits.clone(), None);
+
+        notifier.send("Garage", &Event::Opened).await.unwrap();
+        assert_eq!(limits.usage("SMS", Utc::now()).unwrap(), "2 of 3 today, 2 this month");
+        // Only the first number fits in what's left of the quota
+        notifier.send("Garage", &Event::Closed { open_for: Duration::from_secs(60) }).await.unwrap();
+        assert_eq!(limits.usage("SMS", Utc::now()).unwrap(), "3 of 3 today, 3 this month");
+        assert_eq!(
+            *sms.sent.lock().unwrap(),
+            vec![
+                "+15551234567: Garage has been opened",
+                "+15557654321: Garage has been opened",
+                "+15551234567: Garage is now closed after being open for 00:01:00",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_send_is_not_counted() {
+        let limits = Arc::new(sms_limits(ChannelLimits {
+            daily: Some(1),
+            ..Default::default()
+        }));
+        let notifier = LimitedNotifier::new(Arc::new(Failing), limits.clone(), None);
+
+        assert!(notifier.send("Garage", &Event::Opened).await.is_err());
+        assert_eq!(limits.usage("SMS", Utc::now()).unwrap(), "0 of 1 today, 0 this month");
+        assert!(limits.check("SMS", Utc::now()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_open_door_alerts_are_held_back() {
+        let limits = Arc::new(sms_limits(ChannelLimits {
+            daily: Some(1),
+            ..Default::default()
+        }));
+        let sms = Arc::new(Recording { name: "SMS", ..Default::default() });
+        let notifier = LimitedNotifier::new(sms.clone(), limits, None);
+        let alert = Event::OpenTooLong { open_for: Duration::from_secs(600), reminder: 0 };
+
+        notifier.send("Garage", &Event::Opened).await.unwrap();
+        // Other messages are dropped, alerts stay in the outbox until the quota resets
+        notifier.send("Garage", &Event::Closed { open_for: Duration::from_secs(60) }).await.unwrap();
+        let error = notifier.send("Garage", &alert).await.unwrap_err();
+        assert!(matches!(error, Error::ChannelLimited { .. }));
+        assert!(error.is_transient());
+        assert_eq!(sms.sent.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_check_overflow() {
+        let limits = sms_limits(ChannelLimits {
+            daily: Some(1),
+            ..Default::default()
+        });
+        let channels = ["SMS", "Telegram"];
+
+        assert!(limits.check_overflow(None, &channels).is_ok());
+        assert!(limits.check_overflow(Some("telegram"), &channels).is_ok());
+        assert!(limits.check_overflow(Some("Pushover"), &channels).is_err());
+        assert!(limits.check_overflow(Some("SMS"), &channels).is_err());
+    }
+}
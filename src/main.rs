@@ -1,21 +1,16 @@
 use clap::Parser;
 
-mod config;
-mod door;
-mod audio;
-mod utils;
-mod sms;
-mod telegram;
-mod monitor;
-
-use config::Args;
-use monitor::run_monitor;
-use monitor::send_telegram_test_message;
+use door_monitor::config::{Args, Command};
+use door_monitor::monitor::run_monitor;
+use door_monitor::monitor::send_telegram_test_message;
+use door_monitor::templates::render_templates;
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
-    if args.telegram_test {
+    if args.command == Some(Command::RenderTemplates) {
+        render_templates(&args);
+    } else if args.telegram_test {
         send_telegram_test_message(args).await;
     } else {
         run_monitor(args).await;
@@ -1,46 +1,93 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::{Duration, Instant};
-use tokio::time::sleep;
+use tokio::sync::mpsc;
+use tokio::time::sleep_until;
 use chrono::Utc;
 
-use crate::config::Args;
-use crate::door::{DoorStatus, check_door_status};
+use crate::clock::{Clock, SystemClock};
+use crate::config::{Args, DoorConfig, DEFAULT_CONNECT_TIMEOUT, DEFAULT_DOOR_NAME, DEFAULT_REQUEST_TIMEOUT};
+use crate::door::{DoorSensor, DoorState, build_sensor};
+use crate::error::Error;
+use crate::limits::{Limits, limit_notifiers};
 use crate::audio::play_beep;
-use crate::utils::format_duration;
-use crate::sms::send_sms;
+use crate::state_machine::{AlertPolicy, DoorStateMachine, Event, Log, Output};
+use crate::utils::{format_duration, http_client};
+use crate::notifier::{self, Delivery, Notifier, notifiers_from_args};
+use crate::outbox::{Channel, Outbox};
+use crate::templates::{Templates, template_notifiers};
+use crate::sms::SmsReplies;
 use crate::telegram::send_telegram;
-
-pub struct MonitorState {
-    pub door_opened_time: Option<Instant>,
-    pub door_closed_time: Option<Instant>,
-    pub last_door_state: Option<bool>,
-    pub sms_sent: bool,
-    pub sms_backoff_index: usize,
-    pub last_sms_time: Option<Instant>,
+use crate::telegram_bot::TelegramBot;
+use crate::webhook::{self, DoorEventSenders};
+
+pub use crate::state_machine::MonitorState;
+
+/// A door's state machine, shared between its monitor and e.g. the Telegram bot.
+pub type SharedMachine = Arc<Mutex<DoorStateMachine>>;
+
+/// What other tasks need to look at and act on a running door monitor, e.g.
+/// to acknowledge its alerts.
+#[derive(Clone)]
+pub struct DoorHandle {
+    pub machine: SharedMachine,
+    /// Events raised outside the monitor, for it to send
+    notify: mpsc::Sender<Event>,
+    /// The clock the monitor drives the machine with
+    clock: Arc<dyn Clock>,
 }
 
-impl MonitorState {
-    pub fn new() -> Self {
+impl DoorHandle {
+    pub fn new(machine: SharedMachine, notify: mpsc::Sender<Event>) -> Self {
         Self {
-            door_opened_time: None,
-            door_closed_time: None,
-            last_door_state: None,
-            sms_sent: false,
-            sms_backoff_index: 0,
-            last_sms_time: None,
+            machine,
+            notify,
+            clock: Arc::new(SystemClock),
         }
     }
 
-    pub fn reset_sms_state(&mut self) {
-        self.sms_sent = false;
-        self.sms_backoff_index = 0;
-        self.last_sms_time = None;
+    /// Uses `clock`, the one the monitor uses, instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn door(&self) -> String {
+        self.machine.lock().unwrap().door().to_string()
+    }
+
+    /// Acknowledges the door's open alert on behalf of `by` and has the
+    /// monitor tell everyone. Returns false if there was nothing to acknowledge.
+    pub fn acknowledge(&self, by: &str) -> bool {
+        let Some(event) = self.machine.lock().unwrap().acknowledge(by, self.clock.now()) else {
+            return false;
+        };
+        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+        println!("[{}] {} alert acknowledged by {}", timestamp, self.door(), by);
+        if let Err(e) = self.notify.try_send(event) {
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            eprintln!("[{}] Couldn't send the acknowledgement: {}", timestamp, e);
+        }
+        true
     }
 }
 
+/// Acknowledges the alert of every door in `handles` that has one, or only
+/// of the door named `door` (case-insensitively). Returns the names of the
+/// doors that were acknowledged.
+pub fn acknowledge_doors(handles: &[DoorHandle], door: Option<&str>, by: &str) -> Vec<String> {
+    handles
+        .iter()
+        .filter(|handle| door.is_none_or(|door| handle.door().eq_ignore_ascii_case(door)))
+        .filter(|handle| handle.acknowledge(by))
+        .map(|handle| handle.door())
+        .collect()
+}
+
 /// A door monitoring system that tracks door state and sends SMS notifications.
 /// 
 /// The DoorMonitor struct provides comprehensive door monitoring functionality including:
-/// - Polling a REST API to check door status
+/// - Polling a `DoorSensor` backend to check door status
 /// - Logging door state changes with timestamps and durations
 /// - Playing audio alerts when the door opens
 /// - Sending SMS notifications for various events:
@@ -48,8 +95,27 @@ impl MonitorState {
 ///   * Immediate notification when door opens
 ///   * Notification when door closes (with duration open)
 ///   * Progressive warnings if door stays open too long (with backoff)
+/// - Debouncing readings and pausing open/close messages while a sensor is flapping
+/// - Alerting when the sensor can't be reached, and again when it comes back
 /// - Maintaining monitoring state across check cycles
 ///
+/// Each DoorMonitor watches a single named door. `run_monitor` creates one
+/// monitor per configured door and runs them concurrently, so every door keeps
+/// its own `MonitorState` and every message names the door it is about.
+///
+/// Readings can also be pushed to a monitor (see `subscribe`), e.g. by the
+/// webhook listener or by a sensor that holds a connection open to the device
+/// (see `DoorSensor::watch`). Pushed readings go through the same transition
+/// logic as polled ones, and polling then only runs every
+/// `reconcile_interval_seconds` as a fallback in case an event was missed.
+///
+/// The decisions about what to send and when are made by a `DoorStateMachine`,
+/// which never does I/O itself. The monitor reads the sensor, asks its `Clock`
+/// for the time, and sends whatever `Event`s the state machine returns through
+/// each of its `Notifier`s at once. Events go through an `Outbox` first, so
+/// notifications that fail are retried and can be shared across doors and
+/// restarts.
+///
 /// ## SMS Notification Behavior
 /// 
 /// The monitor sends SMS messages for the following events:
@@ -57,23 +123,136 @@ impl MonitorState {
 /// 2. **Door Opens**: Immediate notification when door changes from closed to open
 /// 3. **Door Closes**: Notification when door changes from open to closed (includes duration)
 /// 4. **Door Open Too Long**: Progressive warnings if door exceeds warning threshold
+/// 5. **Sensor Offline**: Warnings with their own backoff while the sensor can't be read,
+///    and a recovery message with the door state once it answers again
 ///
 /// The struct owns a `reqwest::Client` for HTTP requests, which is more efficient
 /// than creating a new client for each request as it reuses connections.
 pub struct DoorMonitor {
     client: reqwest::Client,
-    state: MonitorState,
+    door: DoorConfig,
+    sensor: Option<Box<dyn DoorSensor>>,
+    event_sender: mpsc::Sender<DoorState>,
+    events: Option<mpsc::Receiver<DoorState>>,
+    push_enabled: bool,
+    /// The initial state was unknown, so the first known reading starts the machine
+    awaiting_start: bool,
+    clock: Arc<dyn Clock>,
+    machine: SharedMachine,
+    notify_sender: mpsc::Sender<Event>,
+    notify_queue: Option<mpsc::Receiver<Event>>,
+    notifiers: Option<Vec<Arc<dyn Notifier>>>,
+    outbox: Arc<Outbox>,
+    limits: Arc<Limits>,
+    templates: Arc<Templates>,
+    /// Channels that have been reported as failing and haven't worked since
+    failing_channels: HashSet<String>,
 }
 
 impl DoorMonitor {
     /// Creates a new DoorMonitor with a fresh HTTP client and initial state.
     pub fn new() -> Self {
+        Self::build(
+            http_client(DEFAULT_CONNECT_TIMEOUT, DEFAULT_REQUEST_TIMEOUT),
+            DoorConfig {
+                name: DEFAULT_DOOR_NAME.to_string(),
+                api_url: String::new(),
+                credentials: None,
+            },
+            None,
+        )
+    }
+
+    /// Creates a DoorMonitor for the given door, sharing an existing HTTP client.
+    /// The sensor backend is chosen from the door's configuration.
+    pub fn with_door(client: reqwest::Client, door: DoorConfig) -> Result<Self, Error> {
+        let sensor = if door.api_url.is_empty() {
+            None
+        } else {
+            Some(build_sensor(&client, &door)?)
+        };
+        Ok(Self::build(client, door, sensor))
+    }
+
+    /// Creates a DoorMonitor that reads the door through a custom sensor backend.
+    pub fn with_sensor(client: reqwest::Client, door: DoorConfig, sensor: Box<dyn DoorSensor>) -> Self {
+        Self::build(client, door, Some(sensor))
+    }
+
+    fn build(client: reqwest::Client, door: DoorConfig, sensor: Option<Box<dyn DoorSensor>>) -> Self {
+        let (event_sender, events) = mpsc::channel(32);
+        let (notify_sender, notify_queue) = mpsc::channel(32);
+        let machine = Arc::new(Mutex::new(DoorStateMachine::new(door.name.clone(), AlertPolicy::default())));
         Self {
-            client: reqwest::Client::new(),
-            state: MonitorState::new(),
+            client,
+            door,
+            sensor,
+            event_sender,
+            events: Some(events),
+            push_enabled: false,
+            awaiting_start: false,
+            clock: Arc::new(SystemClock),
+            machine,
+            notify_sender,
+            notify_queue: Some(notify_queue),
+            notifiers: None,
+            outbox: Arc::new(Outbox::in_memory()),
+            limits: Arc::new(Limits::new(HashMap::new())),
+            templates: Arc::new(Templates::new(Vec::new())),
+            failing_channels: HashSet::new(),
         }
     }
 
+    /// Uses `clock` instead of the system clock to time alerts and reminders.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sends events through `notifiers` instead of the channels enabled in `Args`.
+    pub fn with_notifiers(mut self, notifiers: Vec<Arc<dyn Notifier>>) -> Self {
+        self.notifiers = Some(notifiers);
+        self
+    }
+
+    /// Queues notifications in `outbox`, e.g. one backed by a file and shared
+    /// with the other doors, instead of one of its own in memory.
+    pub fn with_outbox(mut self, outbox: Arc<Outbox>) -> Self {
+        self.outbox = outbox;
+        self
+    }
+
+    /// Applies the rate limits and quotas in `limits` to the channels, usually
+    /// shared with the other doors so they count against the same allowance.
+    pub fn with_limits(mut self, limits: Arc<Limits>) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Words messages with `templates` instead of the usual messages.
+    pub fn with_templates(mut self, templates: Arc<Templates>) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// A handle on the live state machine, for anything that needs to look
+    /// at or act on the door while `run` is going, like the Telegram bot.
+    pub fn handle(&self) -> DoorHandle {
+        DoorHandle::new(self.machine.clone(), self.notify_sender.clone()).with_clock(self.clock.clone())
+    }
+
+    fn machine(&self) -> MutexGuard<'_, DoorStateMachine> {
+        self.machine.lock().unwrap()
+    }
+
+    /// Returns a sender that pushes readings straight into this monitor's
+    /// transition logic. Once subscribed, `run` polls the sensor only every
+    /// `reconcile_interval_seconds`.
+    pub fn subscribe(&mut self) -> mpsc::Sender<DoorState> {
+        self.push_enabled = true;
+        self.event_sender.clone()
+    }
+
     pub async fn send_telegram_message(&mut self, args: Args) {
         println!("Door Monitor Sending test message via Telegram...");
         let message = args.test_message.clone().unwrap_or("".to_string());
@@ -84,272 +263,342 @@ impl DoorMonitor {
     }
 
     pub async fn run(&mut self, args: Args) {
-        println!("Door Monitor Starting...");
-        println!("API URL: {}", args.api_url.clone().unwrap_or("".to_string()).as_str());
-        println!("Check interval: {} seconds", args.check_interval_seconds);
-        println!("Warning threshold: {} seconds", args.open_too_long_seconds);
-        println!("SMS Off: {}", args.sms_off);
-        println!("Telegram Off: {}", args.telegram_off);
-
-        if args.api_url.is_none() || args.api_url.clone().unwrap().is_empty() {
+        println!("[{}] Door Monitor Starting...", self.door.name);
+        println!("[{}] API URL: {}", self.door.name, self.door.api_url);
+
+        let Some(sensor) = self.sensor.take() else {
             let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
-            eprintln!("[{}] API URL is missing", timestamp);
-            std::process::exit(1);
-        }
+            eprintln!("[{}] No sensor configured for {}", timestamp, self.door.name);
+            return;
+        };
 
         let check_interval = Duration::from_secs(args.check_interval_seconds);
-        let warning_threshold = Duration::from_secs(args.open_too_long_seconds);
+        self.machine().configure(&args);
         
-        // Send initial status SMS when program starts
-        match check_door_status(&self.client, args.api_url.clone().unwrap_or("".to_string()).as_str()).await {
-            Ok(door_status) => {
-                let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
-                let door_state_msg = if door_status.state { "closed" } else { "open" };
-                let message = format!("Door Monitor started. Current door state: {}", door_state_msg);
+        // Start push-capable sensors early so they can connect while we read the initial state
+        if sensor.watch(self.event_sender.clone()) {
+            self.push_enabled = true;
+        }
 
-                if !args.sms_off {
-                    println!("[{}] Sending initial status SMS...", timestamp);
-                    if let Err(e) = send_sms(&self.client, &args, &message).await {
-                        eprintln!("[{}] Failed to send initial status SMS: {}", timestamp, e);
-                    }
-                }
-                
-                if !args.telegram_off {
-                    println!("[{}] Sending initial status Telegram...", timestamp);
-                    if let Err(e) = send_telegram(&self.client, &args, &message).await {
-                        eprintln!("[{}] Failed to send initial status Telegram: {}", timestamp, e);
-                    }
-                }
-                
-                // Set initial state
-                if door_status.state {
-                    // Door is closed
-                    self.state.door_closed_time = Some(Instant::now());
-                } else {
-                    // Door is open
-                    self.state.door_opened_time = Some(Instant::now());
-                }
-                self.state.last_door_state = Some(door_status.state);
+        // Transient sensor errors are retried before they count as a failed check
+        let retry_policy = args.retry_policy();
+        let status_name = format!("{} status", self.door.name);
+
+        // Send initial status SMS when program starts
+        match retry_policy.run(&status_name, || sensor.read()).await {
+            Ok(door_state) => {
+                // A sensor that hasn't heard from the door yet, e.g. over MQTT,
+                // starts with the first state it reports
+                self.awaiting_start = door_state == DoorState::Unknown;
+                let output = self.machine().start(door_state, self.clock.now());
+                self.handle_output(output, &args).await;
             }
             Err(e) => {
                 let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
-                eprintln!("[{}] Error checking initial door status: {}", timestamp, e);
+                eprintln!("[{}] Error checking initial {} status: {}", timestamp, self.door.name, e);
+                self.handle_sensor_error(&e, &args).await;
             }
         }
+
+        let mut events = self.events.take();
+        let mut notify_queue = self.notify_queue.take();
+        let poll_interval = if self.push_enabled {
+            Duration::from_secs(args.reconcile_interval_seconds)
+        } else {
+            check_interval
+        };
+        let mut last_poll = Instant::now();
         
         loop {
-            match check_door_status(&self.client, args.api_url.clone().unwrap_or("".to_string()).as_str()).await {
-                Ok(door_status) => {
-                    self.handle_door_status(&door_status, &args, warning_threshold).await;
-                }
-                Err(e) => {
-                    let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
-                    eprintln!("[{}] Error checking door status: {}", timestamp, e);
+            if !self.push_enabled || last_poll.elapsed() >= poll_interval {
+                last_poll = Instant::now();
+                match retry_policy.run(&status_name, || sensor.read()).await {
+                    Ok(door_state) => self.handle_reading(door_state, &args).await,
+                    Err(e) => {
+                        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                        eprintln!("[{}] Error checking {} status: {}", timestamp, self.door.name, e);
+                        self.handle_sensor_error(&e, &args).await;
+                    }
                 }
+            } else {
+                // Between reconciliation polls keep checking how long the door has been open
+                self.handle_tick(&args).await;
             }
+
+            // Resend notifications that failed earlier once they are due
+            self.notify_all(Vec::new(), &args).await;
             
-            sleep(check_interval).await;
+            // Wait for the next check, handling pushed readings as they arrive
+            let next_check = tokio::time::Instant::now() + check_interval;
+            loop {
+                tokio::select! {
+                    Some(door_state) = next_event(&mut events) => {
+                        self.handle_reading(door_state, &args).await;
+                    }
+                    Some(event) = next_event(&mut notify_queue) => {
+                        self.notify_all(vec![event], &args).await;
+                    }
+                    _ = sleep_until(next_check) => break,
+                }
+            }
+        }
+    }
+
+    /// Handles a reading that was polled from or pushed by the sensor.
+    pub async fn handle_reading(&mut self, door_state: DoorState, args: &Args) {
+        if self.awaiting_start && door_state != DoorState::Unknown {
+            self.awaiting_start = false;
+            let output = self.machine().start(door_state, self.clock.now());
+            self.handle_output(output, args).await;
+            return;
+        }
+        let (output, open) = {
+            let mut machine = self.machine();
+            (machine.reading(door_state, self.clock.now()), machine.is_open())
+        };
+        if open {
+            play_beep();
+        }
+        self.handle_output(output, args).await;
+    }
+
+    /// Re-checks the last reading without asking the sensor.
+    pub async fn handle_tick(&mut self, args: &Args) {
+        let (output, open) = {
+            let mut machine = self.machine();
+            (machine.tick(self.clock.now()), machine.is_open())
+        };
+        if open {
+            play_beep();
         }
+        self.handle_output(output, args).await;
+    }
+
+    /// Records a failed sensor read, alerting once the sensor has been
+    /// unreachable for `sensor_offline_seconds`.
+    pub async fn handle_sensor_error(&mut self, error: &Error, args: &Args) {
+        let output = self.machine().sensor_error(error, self.clock.now());
+        self.handle_output(output, args).await;
     }
 
-    async fn handle_door_status(
-        &mut self,
-        door_status: &DoorStatus,
-        args: &Args,
-        warning_threshold: Duration,
-    ) {
+    /// Logs what the state machine saw and sends the notifications it emitted.
+    async fn handle_output(&mut self, output: Output, args: &Args) {
         let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
-        let door_closed = door_status.state;
-        
-        // Always log the current door state
-        if door_closed {
-            if let Some(closed_time) = self.state.door_closed_time {
-                let closed_duration = closed_time.elapsed();
-                println!("[{}] The door is closed (closed for {})", timestamp, format_duration(closed_duration));
-            } else {
-                println!("[{}] The door is closed", timestamp);
+        for log in &output.logs {
+            match log {
+                Log::Info(line) => println!("[{}] {}", timestamp, line),
+                Log::Warning(line) => eprintln!("[{}] {}", timestamp, line),
             }
-        } else {
-            if let Some(opened_time) = self.state.door_opened_time {
-                let open_duration = opened_time.elapsed();
-                println!("[{}] The door is open (open for {})", timestamp, format_duration(open_duration));
-            } else {
-                println!("[{}] The door is open", timestamp);
+        }
+        self.notify_all(output.events, args).await;
+    }
+
+    /// Queues each event for every channel and sends whatever is due, unless muted.
+    async fn notify_all(&mut self, events: Vec<Event>, args: &Args) {
+        if events.is_empty() && !self.outbox.is_due(&self.door.name, Utc::now()) {
+            return;
+        }
+        if let Some(muted_for) = self.machine().muted_for(self.clock.now()) {
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            for event in &events {
+                println!("[{}] Muted for another {}, not sending {} notifications",
+                       timestamp, format_duration(muted_for), event.label());
             }
-            play_beep();
+            return;
         }
-        
-        // Track when door state changes
-        if self.state.last_door_state != Some(door_closed) {
-            self.handle_door_state_change(door_closed, args, &timestamp).await;
-            self.state.last_door_state = Some(door_closed);
+        let notifiers = self
+            .notifiers
+            .get_or_insert_with(|| {
+                let notifiers = template_notifiers(notifiers_from_args(&self.client, args), &self.templates);
+                limit_notifiers(notifiers, &self.limits, args.overflow_channel.as_deref())
+            })
+            .clone();
+        let channels: Vec<Channel> = notifiers.iter().map(|notifier| Channel::of(notifier.as_ref())).collect();
+        let now = Utc::now();
+        for event in &events {
+            self.outbox.enqueue(&self.door.name, event, &channels, now);
         }
-        
-        // Check if door has been open too long
-        if !door_closed {
-            self.handle_door_open_too_long(args, warning_threshold, &timestamp).await;
-        }
-    }
-
-    async fn handle_door_state_change(
-        &mut self,
-        door_closed: bool,
-        args: &Args,
-        timestamp: &str,
-    ) {
-        if door_closed {
-            // Door just closed - always send SMS if door was open
-            if let Some(opened_time) = self.state.door_opened_time {
-                let total_time_open = opened_time.elapsed();
-                let message = format!("Door is now closed after being open for {}", format_duration(total_time_open));
-                if !args.sms_off {
-                    println!("[{}] Sending door closed SMS...", timestamp);
-                    if let Err(e) = send_sms(&self.client, args, &message).await {
-                        eprintln!("[{}] Failed to send door closed SMS: {}", timestamp, e);
-                    }
-                }
+        let deliveries = self.outbox.deliver(&notifiers, &self.door.name, now).await;
+        self.report_failing_channels(&notifiers, deliveries).await;
+    }
 
-                if !args.telegram_off {
-                    println!("[{}] Sending door closed Telegram...", timestamp);
-                    if let Err(e) = send_telegram(&self.client, args, &message).await {
-                        eprintln!("[{}] Failed to send door closed Telegram: {}", timestamp, e);
+    /// Tells the other channels when a channel fails with an error that
+    /// retrying won't fix, e.g. wrong credentials. Each channel is reported
+    /// once, and again only after it has worked in between.
+    async fn report_failing_channels(&mut self, notifiers: &[Arc<dyn Notifier>], deliveries: Vec<Delivery>) {
+        for delivery in deliveries {
+            let error = match delivery.result {
+                Ok(()) => {
+                    if self.failing_channels.remove(&delivery.channel) {
+                        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                        println!("[{}] {} notifications are working again", timestamp, delivery.channel);
                     }
+                    continue;
                 }
-            }
-            self.state.door_opened_time = None;
-            self.state.door_closed_time = Some(Instant::now());
-            self.state.reset_sms_state();
-        } else {
-            // Door just opened - send SMS immediately
-            let message = "Door has been opened".to_string();
-            if !args.sms_off {
-                println!("[{}] Sending door opened SMS...", timestamp);
-                if let Err(e) = send_sms(&self.client, args, &message).await {
-                    eprintln!("[{}] Failed to send door opened SMS: {}", timestamp, e);
-                }
+                Err(e) if !e.is_transient() => e,
+                Err(_) => continue,
+            };
+            if !self.failing_channels.insert(delivery.channel.clone()) {
+                continue;
             }
 
-            if !args.telegram_off {
-                println!("[{}] Sending door opened Telegra...", timestamp);
-                if let Err(e) = send_telegram(&self.client, args, &message).await {
-                    eprintln!("[{}] Failed to send door opened Telegram: {}", timestamp, e);
-                }
-            }
-            
-            self.state.door_opened_time = Some(Instant::now());
-            self.state.door_closed_time = None;
-        }
-    }
-
-    async fn handle_door_open_too_long(
-        &mut self,
-        args: &Args,
-        warning_threshold: Duration,
-        timestamp: &str,
-    ) {
-        if let Some(opened_time) = self.state.door_opened_time {
-            let time_open = opened_time.elapsed();
-            if time_open >= warning_threshold {
-                println!("[{}] The door has been opened for too long ({})", 
-                       timestamp, format_duration(time_open));
-                
-                // SMS logic with backoff if enabled
-                if args.sms_backoff() {
-                    self.handle_sms_with_backoff(args, time_open, timestamp).await;
-                } else {
-                    self.handle_single_sms(args, time_open, timestamp).await;
-                }
+            let others: Vec<Arc<dyn Notifier>> = notifiers
+                .iter()
+                .filter(|notifier| notifier.name() != delivery.channel)
+                .cloned()
+                .collect();
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            if others.is_empty() {
+                eprintln!("[{}] {} notifications are failing and there is no other channel to say so: {}",
+                       timestamp, delivery.channel, error);
+                continue;
             }
+            eprintln!("[{}] {} notifications are failing, telling the other channels: {}",
+                   timestamp, delivery.channel, error);
+            let event = Event::ChannelFailing {
+                channel: delivery.channel,
+                error: error.to_string(),
+            };
+            notifier::notify_all(&others, &self.door.name, &event).await;
         }
     }
+}
 
-    async fn handle_sms_with_backoff(
-        &mut self,
-        args: &Args,
-        time_open: Duration,
-        timestamp: &str,
-    ) {
-        // SMS backoff intervals: 5, 15, 30, 60 minutes, then every 60 minutes
-        let sms_intervals = vec![
-            Duration::from_secs(5 * 60),   // 5 minutes
-            Duration::from_secs(15 * 60),  // 15 minutes
-            Duration::from_secs(30 * 60),  // 30 minutes
-            Duration::from_secs(60 * 60),  // 60 minutes
-        ];
-
-        let should_send_message = if !self.state.sms_sent {
-            // First Message - send immediately when threshold is reached
-            true
-        } else if let Some(last_message) = self.state.last_sms_time {
-            // Determine next interval based on backoff index
-            let next_interval = if self.state.sms_backoff_index < sms_intervals.len() {
-                sms_intervals[self.state.sms_backoff_index]
-            } else {
-                Duration::from_secs(60 * 60) // Every 60 minutes after the initial intervals
-            };
-            
-            last_message.elapsed() >= next_interval
-        } else {
-            false
-        };
-        
-        if should_send_message {
-            println!("[{}] Preparing to send SMS (backoff index: {})...", timestamp, self.state.sms_backoff_index);
-            let message = if !self.state.sms_sent {
-                format!("ALERT: Door has been open for {}", format_duration(time_open))
-            } else {
-                format!("REMINDER: Door still open for {}", format_duration(time_open))
-            };
-            
-            if !args.sms_off {
-                if let Err(e) = send_sms(&self.client, args, &message).await {
-                    eprintln!("[{}] Failed to send SMS: {}", timestamp, e);
-                }
-            }
+impl Default for DoorMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-            if !args.telegram_off {
-                if let Err(e) = send_telegram(&self.client, args, &message).await {
-                    eprintln!("[{}] Failed to send Telegram: {}", timestamp, e);
-                }
-            }
-            
-            self.state.sms_sent = true;
-            self.state.last_sms_time = Some(Instant::now());
-            self.state.sms_backoff_index += 1;
+/// Waits for the next pushed reading or event, or forever if there is no channel.
+async fn next_event<T>(events: &mut Option<mpsc::Receiver<T>>) -> Option<T> {
+    match events {
+        Some(receiver) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Monitors every configured door concurrently, one `DoorMonitor` per door.
+pub async fn run_monitor(args: Args) {
+    println!("Door Monitor Starting...");
+    println!("Check interval: {} seconds", args.check_interval_seconds);
+    println!("Warning threshold: {} seconds", args.open_too_long_seconds);
+    println!("SMS Off: {}", args.sms_off);
+    println!("Telegram Off: {}", args.telegram_off);
+
+    let doors = args.all_doors();
+    if doors.is_empty() {
+        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+        eprintln!("[{}] API URL is missing, supply --api-url or at least one --door", timestamp);
+        std::process::exit(1);
+    }
+
+    let outbox = match Outbox::from_args(&args) {
+        Ok(outbox) => Arc::new(outbox),
+        Err(e) => {
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            eprintln!("[{}] Failed to open the outbox: {}", timestamp, e);
+            std::process::exit(1);
         }
+    };
+    if let Some(path) = &args.outbox_file {
+        println!("Queuing notifications in {}, {} still to be sent", path.display(), outbox.len());
     }
 
-    async fn handle_single_sms(
-        &mut self,
-        args: &Args,
-        time_open: Duration,
-        timestamp: &str,
-    ) {
-        if !self.state.sms_sent {
+    let limits = match Limits::from_args(&args) {
+        Ok(limits) => Arc::new(limits),
+        Err(e) => {
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            eprintln!("[{}] Failed to read the usage counters: {}", timestamp, e);
+            std::process::exit(1);
+        }
+    };
+    let mut limited: Vec<&String> = args
+        .rate_limits
+        .iter()
+        .map(|(channel, _)| channel)
+        .chain(args.daily_quotas.iter().chain(&args.monthly_quotas).map(|(channel, _)| channel))
+        .collect();
+    limited.sort_by_key(|channel| channel.to_lowercase());
+    limited.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+    for channel in limited {
+        if let Some(usage) = limits.usage(channel, Utc::now()) {
+            println!("{} usage: {}", channel, usage);
+        }
+    }
 
-            let message = format!("ALERT: Door has been open for {}", format_duration(time_open));
-            if !args.sms_off {
-                println!("[{}] Preparing to send SMS...", timestamp);
-                if let Err(e) = send_sms(&self.client, args, &message).await {
-                    eprintln!("[{}] Failed to send SMS: {}", timestamp, e);
-                }
-            }
+    let templates = Arc::new(Templates::from_args(&args));
 
-            if !args.telegram_off {
-                println!("[{}] Preparing to send Telegram...", timestamp);
-                if let Err(e) = send_telegram(&self.client, args, &message).await {
-                    eprintln!("[{}] Failed to send Telegram: {}", timestamp, e);
-                }
+    let client = args.http_client();
+    let notifiers = notifiers_from_args(&client, &args);
+    let channels: Vec<&str> = notifiers.iter().map(|notifier| notifier.name()).collect();
+    if let Err(e) = limits.check_overflow(args.overflow_channel.as_deref(), &channels) {
+        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+        eprintln!("[{}] {}", timestamp, e);
+        std::process::exit(1);
+    }
+    let mut senders = DoorEventSenders::new();
+    let mut handles = Vec::new();
+    let mut tasks = Vec::new();
+    for door in doors {
+        let name = door.name.clone();
+        let monitor = match DoorMonitor::with_door(client.clone(), door) {
+            Ok(monitor) => monitor,
+            Err(e) => {
+                let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                eprintln!("[{}] Invalid sensor for {}: {}", timestamp, name, e);
+                std::process::exit(1);
             }
+        };
+        let mut monitor = monitor
+            .with_outbox(outbox.clone())
+            .with_limits(limits.clone())
+            .with_templates(templates.clone());
+        if args.listen.is_some() {
+            senders.insert(monitor.door.name.clone(), monitor.subscribe());
+        }
+        handles.push(monitor.handle());
+        let args = args.clone();
+        tasks.push(tokio::spawn(async move {
+            monitor.run(args).await;
+        }));
+    }
 
-            self.state.sms_sent = true;
+    if let Some(bot) = TelegramBot::from_args(client.clone(), &args, handles.clone()) {
+        println!("Answering Telegram bot commands");
+        tokio::spawn(bot.run());
+    }
+
+    if let Some(replies) = SmsReplies::from_args(client.clone(), &args, handles.clone()) {
+        println!("Checking for SMS replies");
+        tokio::spawn(replies.run());
+    }
+
+    if let Some(addr) = args.listen {
+        match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => {
+                println!("Listening for door events on http://{}/event and acknowledgements on http://{}/acknowledge", addr, addr);
+                tokio::spawn(async move {
+                    if let Err(e) = webhook::serve(listener, senders, handles, args.listen_token.clone()).await {
+                        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                        eprintln!("[{}] Webhook listener stopped: {}", timestamp, e);
+                    }
+                });
+            }
+            Err(e) => {
+                let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                eprintln!("[{}] Failed to listen on {}: {}", timestamp, addr, e);
+                std::process::exit(1);
+            }
         }
     }
-}
 
-pub async fn run_monitor(args: Args) {
-    let mut monitor = DoorMonitor::new();
-    monitor.run(args).await;
+    for task in tasks {
+        if let Err(e) = task.await {
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            eprintln!("[{}] Door monitor task failed: {}", timestamp, e);
+        }
+    }
 }
 
 pub async fn send_telegram_test_message(args: Args) {
@@ -362,632 +611,475 @@ mod tests {
     use super::*;
     use std::time::Duration;
 
-    #[test]
-    fn test_monitor_state_new() {
-        let state = MonitorState::new();
-        assert!(state.door_opened_time.is_none());
-        assert!(state.door_closed_time.is_none());
-        assert!(state.last_door_state.is_none());
-        assert!(!state.sms_sent);
-        assert_eq!(state.sms_backoff_index, 0);
-        assert!(state.last_sms_time.is_none());
-    }
-
-    #[test]
-    fn test_monitor_state_reset_sms_state() {
-        let mut state = MonitorState::new();
-        state.sms_sent = true;
-        state.sms_backoff_index = 3;
-        state.last_sms_time = Some(Instant::now());
-
-        state.reset_sms_state();
-
-        assert!(!state.sms_sent);
-        assert_eq!(state.sms_backoff_index, 0);
-        assert!(state.last_sms_time.is_none());
-    }
-
     #[test]
     fn test_door_monitor_new() {
         let monitor = DoorMonitor::new();
-        assert!(monitor.state.door_opened_time.is_none());
-        assert!(monitor.state.door_closed_time.is_none());
-        assert!(monitor.state.last_door_state.is_none());
-        assert!(!monitor.state.sms_sent);
-        assert_eq!(monitor.state.sms_backoff_index, 0);
-        assert!(monitor.state.last_sms_time.is_none());
+        assert!(monitor.machine().state.door_opened_time.is_none());
+        assert!(monitor.machine().state.door_closed_time.is_none());
+        assert!(monitor.machine().state.last_door_state.is_none());
+        assert!(!monitor.machine().state.sms_sent);
+        assert_eq!(monitor.machine().state.sms_backoff_index, 0);
+        assert!(monitor.machine().state.last_sms_time.is_none());
     }
 
     #[test]
-    fn test_sms_intervals() {
-        let sms_intervals = vec![
-            Duration::from_secs(5 * 60),   // 5 minutes
-            Duration::from_secs(15 * 60),  // 15 minutes
-            Duration::from_secs(30 * 60),  // 30 minutes
-            Duration::from_secs(60 * 60),  // 60 minutes
-        ];
+    fn test_door_monitor_with_door() {
+        let door = DoorConfig {
+            name: "Garage door".to_string(),
+            api_url: "http://garage.test".to_string(),
+            credentials: None,
+        };
+        let monitor = DoorMonitor::with_door(reqwest::Client::new(), door.clone()).unwrap();
+        assert_eq!(monitor.door, door);
+        assert!(monitor.sensor.is_some());
+        assert!(monitor.machine().state.last_door_state.is_none());
+    }
 
-        assert_eq!(sms_intervals[0], Duration::from_secs(300));
-        assert_eq!(sms_intervals[1], Duration::from_secs(900));
-        assert_eq!(sms_intervals[2], Duration::from_secs(1800));
-        assert_eq!(sms_intervals[3], Duration::from_secs(3600));
+    #[test]
+    fn test_door_monitor_with_invalid_sensor() {
+        let door = DoorConfig {
+            name: "Side door".to_string(),
+            api_url: "mqtt://broker.lan".to_string(),
+            credentials: None,
+        };
+        assert!(DoorMonitor::with_door(reqwest::Client::new(), door).is_err());
     }
 
     #[tokio::test]
-    async fn test_handle_door_state_change_door_opens() {
-        use crate::config::Args;
+    async fn test_run_returns_when_door_has_no_api_url() {
         use clap::Parser;
-        
+
         let mut monitor = DoorMonitor::new();
-        let args = Args::try_parse_from(&["test", "--api-url", "http://test.com"]).unwrap();
-        let timestamp = "2025-06-28 14:30:15 UTC";
+        let args = Args::try_parse_from(["test"]).unwrap();
 
-        // Simulate door opening
-        monitor.handle_door_state_change(false, &args, timestamp).await;
+        // Without an API URL the monitor should give up instead of polling forever
+        monitor.run(args).await;
 
-        assert!(monitor.state.door_opened_time.is_some());
-        assert!(monitor.state.door_closed_time.is_none());
+        assert!(monitor.machine().state.last_door_state.is_none());
     }
 
-    #[tokio::test]
-    async fn test_handle_door_state_change_door_closes() {
-        use crate::config::Args;
-        use clap::Parser;
-        
-        let mut monitor = DoorMonitor::new();
-        monitor.state.door_opened_time = Some(Instant::now());
-        let args = Args::try_parse_from(&["test", "--api-url", "http://test.com"]).unwrap();
-        let timestamp = "2025-06-28 14:30:15 UTC";
+    #[test]
+    fn test_door_monitor_with_sensor() {
+        use async_trait::async_trait;
+
+        struct AlwaysOpen;
 
-        // Simulate door closing
-        monitor.handle_door_state_change(true, &args, timestamp).await;
+        #[async_trait]
+        impl DoorSensor for AlwaysOpen {
+            async fn read(&self) -> Result<DoorState, Error> {
+                Ok(DoorState::Open)
+            }
+        }
 
-        assert!(monitor.state.door_opened_time.is_none());
-        assert!(monitor.state.door_closed_time.is_some());
-        assert!(!monitor.state.sms_sent);
-        assert_eq!(monitor.state.sms_backoff_index, 0);
+        let door = DoorConfig {
+            name: "Side door".to_string(),
+            api_url: String::new(),
+            credentials: None,
+        };
+        let monitor = DoorMonitor::with_sensor(reqwest::Client::new(), door, Box::new(AlwaysOpen));
+        assert!(monitor.sensor.is_some());
+        assert_eq!(monitor.door.name, "Side door");
     }
 
     #[tokio::test]
-    async fn test_door_opening_sends_immediate_sms() {
-        use crate::config::Args;
+    async fn test_run_counts_sensor_failures() {
+        use async_trait::async_trait;
         use clap::Parser;
-        
-        let mut monitor = DoorMonitor::new();
-        let args = Args::try_parse_from(&[
-            "test", 
-            "--api-url", "http://test.com",
-            "--sms-api-username", "test_user",
-            "--sms-api-password", "test_pass",
-            "--sms-from-phone-number", "1234567890",
-            "--sms-to-phone-number", "0987654321"
-        ]).unwrap();
-        let timestamp = "2025-06-28 14:30:15 UTC";
 
-        // Simulate door opening - should trigger immediate SMS
-        monitor.handle_door_state_change(false, &args, timestamp).await;
+        struct Unreachable;
 
-        assert!(monitor.state.door_opened_time.is_some());
-        assert!(monitor.state.door_closed_time.is_none());
-    }
+        #[async_trait]
+        impl DoorSensor for Unreachable {
+            async fn read(&self) -> Result<DoorState, Error> {
+                Err(Error::Io(std::io::Error::from(std::io::ErrorKind::ConnectionRefused)))
+            }
+        }
 
-    #[tokio::test]
-    async fn test_door_closing_always_sends_sms() {
-        use crate::config::Args;
-        use clap::Parser;
-        
-        let mut monitor = DoorMonitor::new();
-        // Set door as opened some time ago
-        monitor.state.door_opened_time = Some(Instant::now() - Duration::from_secs(300)); // 5 minutes ago
-        
-        let args = Args::try_parse_from(&[
-            "test", 
-            "--api-url", "http://test.com",
-            "--sms-api-username", "test_user",
-            "--sms-api-password", "test_pass",
-            "--sms-from-phone-number", "1234567890",
-            "--sms-to-phone-number", "0987654321"
+        let door = DoorConfig {
+            name: "Garage door".to_string(),
+            api_url: String::new(),
+            credentials: None,
+        };
+        let mut monitor = DoorMonitor::with_sensor(reqwest::Client::new(), door, Box::new(Unreachable));
+        let args = Args::try_parse_from([
+            "test", "--check-interval-seconds", "60", "--sms-off", "--telegram-off", "--sensor-retries", "0",
         ]).unwrap();
-        let timestamp = "2025-06-28 14:30:15 UTC";
-
-        // Simulate door closing - should always send SMS regardless of sms_sent state
-        monitor.handle_door_state_change(true, &args, timestamp).await;
-
-        assert!(monitor.state.door_opened_time.is_none());
-        assert!(monitor.state.door_closed_time.is_some());
-        assert!(!monitor.state.sms_sent); // Should be reset after closing
-        assert_eq!(monitor.state.sms_backoff_index, 0);
+        let _ = tokio::time::timeout(Duration::from_millis(200), monitor.run(args)).await;
+
+        // The initial read and the first poll both failed
+        assert_eq!(monitor.machine().state.consecutive_sensor_failures, 2);
+        assert!(monitor.machine().state.last_good_reading.is_none());
+        assert!(monitor.machine().state.sensor_offline_for(Instant::now()).is_some());
+        // Not offline long enough to alert yet
+        assert_eq!(monitor.machine().state.sensor_offline_alerts_sent, 0);
     }
 
     #[tokio::test]
-    async fn test_handle_door_status_door_closed_with_duration() {
-        use crate::config::Args;
+    async fn test_run_retries_transient_sensor_errors() {
+        use async_trait::async_trait;
         use clap::Parser;
-        use crate::door::DoorStatus;
-        
-        let mut monitor = DoorMonitor::new();
-        // Set door as closed some time ago
-        monitor.state.door_closed_time = Some(Instant::now() - Duration::from_secs(180)); // 3 minutes ago
-        monitor.state.last_door_state = Some(true); // Previously closed
-        
-        let args = Args::try_parse_from(&["test", "--api-url", "http://test.com"]).unwrap();
-        let door_status = DoorStatus { id: 1, state: true }; // Door is closed
-        let warning_threshold = Duration::from_secs(60);
+        use std::sync::atomic::{AtomicU32, Ordering};
 
-        // This should log the closed duration but not trigger state change
-        monitor.handle_door_status(&door_status, &args, warning_threshold).await;
+        struct FailsTwice(AtomicU32);
 
-        // State should remain unchanged since door was already closed
-        assert!(monitor.state.door_closed_time.is_some());
-        assert!(monitor.state.door_opened_time.is_none());
-        assert_eq!(monitor.state.last_door_state, Some(true));
-    }
-
-    #[tokio::test]
-    async fn test_handle_door_status_door_open_with_duration() {
-        use crate::config::Args;
-        use clap::Parser;
-        use crate::door::DoorStatus;
-        
-        let mut monitor = DoorMonitor::new();
-        // Set door as open some time ago
-        monitor.state.door_opened_time = Some(Instant::now() - Duration::from_secs(300)); // 5 minutes ago
-        monitor.state.last_door_state = Some(false); // Previously open
-        
-        let args = Args::try_parse_from(&["test", "--api-url", "http://test.com"]).unwrap();
-        let door_status = DoorStatus { id: 1, state: false }; // Door is open
-        let warning_threshold = Duration::from_secs(60);
+        #[async_trait]
+        impl DoorSensor for FailsTwice {
+            async fn read(&self) -> Result<DoorState, Error> {
+                if self.0.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err(Error::Io(std::io::Error::from(std::io::ErrorKind::TimedOut)))
+                } else {
+                    Ok(DoorState::Closed)
+                }
+            }
+        }
 
-        // This should log the open duration and trigger warning logic
-        monitor.handle_door_status(&door_status, &args, warning_threshold).await;
+        let door = DoorConfig {
+            name: "Garage door".to_string(),
+            api_url: String::new(),
+            credentials: None,
+        };
+        let mut monitor = DoorMonitor::with_sensor(reqwest::Client::new(), door, Box::new(FailsTwice(AtomicU32::new(0))));
+        let args = Args::try_parse_from([
+            "test", "--check-interval-seconds", "60", "--sms-off", "--telegram-off", "--sensor-retry-delay-ms", "1",
+        ]).unwrap();
+        let _ = tokio::time::timeout(Duration::from_millis(200), monitor.run(args)).await;
 
-        // State should remain unchanged since door was already open
-        assert!(monitor.state.door_opened_time.is_some());
-        assert!(monitor.state.door_closed_time.is_none());
-        assert_eq!(monitor.state.last_door_state, Some(false));
+        // Both timeouts were retried within the initial read
+        assert_eq!(monitor.machine().state.consecutive_sensor_failures, 0);
+        assert_eq!(monitor.machine().state.last_door_state, Some(true));
     }
 
     #[tokio::test]
-    async fn test_handle_door_status_first_time_closed() {
-        use crate::config::Args;
+    async fn test_run_handles_pushed_readings() {
+        use async_trait::async_trait;
         use clap::Parser;
-        use crate::door::DoorStatus;
-        
-        let mut monitor = DoorMonitor::new();
-        // No previous state
-        
-        let args = Args::try_parse_from(&["test", "--api-url", "http://test.com"]).unwrap();
-        let door_status = DoorStatus { id: 1, state: true }; // Door is closed
-        let warning_threshold = Duration::from_secs(60);
 
-        monitor.handle_door_status(&door_status, &args, warning_threshold).await;
+        struct AlwaysClosed;
 
-        // Should log "The door is closed" without duration
-        assert!(monitor.state.door_closed_time.is_some());
-        assert!(monitor.state.door_opened_time.is_none());
-        assert_eq!(monitor.state.last_door_state, Some(true));
-    }
+        #[async_trait]
+        impl DoorSensor for AlwaysClosed {
+            async fn read(&self) -> Result<DoorState, Error> {
+                Ok(DoorState::Closed)
+            }
+        }
 
-    #[tokio::test]
-    async fn test_handle_door_status_first_time_open() {
-        use crate::config::Args;
-        use clap::Parser;
-        use crate::door::DoorStatus;
-        
-        let mut monitor = DoorMonitor::new();
-        // No previous state
-        
-        let args = Args::try_parse_from(&["test", "--api-url", "http://test.com"]).unwrap();
-        let door_status = DoorStatus { id: 1, state: false }; // Door is open
-        let warning_threshold = Duration::from_secs(60);
+        let door = DoorConfig {
+            name: "Garage door".to_string(),
+            api_url: String::new(),
+            credentials: None,
+        };
+        let mut monitor = DoorMonitor::with_sensor(reqwest::Client::new(), door, Box::new(AlwaysClosed));
+        let sender = monitor.subscribe();
+        sender.send(DoorState::Open).await.unwrap();
 
-        monitor.handle_door_status(&door_status, &args, warning_threshold).await;
+        let args = Args::try_parse_from(["test", "--check-interval-seconds", "60"]).unwrap();
+        let _ = tokio::time::timeout(Duration::from_millis(200), monitor.run(args)).await;
 
-        // Should log "The door is open" without duration and send SMS
-        assert!(monitor.state.door_opened_time.is_some());
-        assert!(monitor.state.door_closed_time.is_none());
-        assert_eq!(monitor.state.last_door_state, Some(false));
+        // The sensor said closed at startup, the pushed reading opened the door
+        // and no reconciliation poll has happened since
+        assert_eq!(monitor.machine().state.last_door_state, Some(false));
+        assert!(monitor.machine().state.door_opened_time.is_some());
     }
 
     #[tokio::test]
-    async fn test_handle_door_open_too_long_below_threshold() {
-        use crate::config::Args;
+    async fn test_run_handles_readings_from_watching_sensor() {
+        use async_trait::async_trait;
         use clap::Parser;
-        
-        let mut monitor = DoorMonitor::new();
-        // Set door as opened recently
-        monitor.state.door_opened_time = Some(Instant::now() - Duration::from_secs(30)); // 30 seconds ago
-        
-        let args = Args::try_parse_from(&["test", "--api-url", "http://test.com"]).unwrap();
-        let warning_threshold = Duration::from_secs(60); // 1 minute threshold
-        let timestamp = "2025-06-28 14:30:15 UTC";
 
-        // Door has been open for 30 seconds, threshold is 60 seconds - should not trigger
-        monitor.handle_door_open_too_long(&args, warning_threshold, timestamp).await;
+        struct PushesOpen;
 
-        // SMS state should remain unchanged
-        assert!(!monitor.state.sms_sent);
-        assert_eq!(monitor.state.sms_backoff_index, 0);
-        assert!(monitor.state.last_sms_time.is_none());
-    }
+        #[async_trait]
+        impl DoorSensor for PushesOpen {
+            async fn read(&self) -> Result<DoorState, Error> {
+                Ok(DoorState::Closed)
+            }
 
-    #[tokio::test]
-    async fn test_handle_door_open_too_long_above_threshold_with_backoff() {
-        use crate::config::Args;
-        use clap::Parser;
-        
-        let mut monitor = DoorMonitor::new();
-        // Set door as opened past threshold
-        monitor.state.door_opened_time = Some(Instant::now() - Duration::from_secs(120)); // 2 minutes ago
-        
-        let args = Args::try_parse_from(&[
-            "test", 
-            "--api-url", "http://test.com",
-            "--sms-api-username", "test_user",
-            "--sms-api-password", "test_pass",
-            "--sms-from-phone-number", "1234567890",
-            "--sms-to-phone-number", "0987654321"
-        ]).unwrap();
-        let warning_threshold = Duration::from_secs(60); // 1 minute threshold
-        let timestamp = "2025-06-28 14:30:15 UTC";
+            fn watch(&self, events: mpsc::Sender<DoorState>) -> bool {
+                tokio::spawn(async move {
+                    let _ = events.send(DoorState::Open).await;
+                });
+                true
+            }
+        }
+
+        let door = DoorConfig {
+            name: "Garage door".to_string(),
+            api_url: String::new(),
+            credentials: None,
+        };
+        let mut monitor = DoorMonitor::with_sensor(reqwest::Client::new(), door, Box::new(PushesOpen));
 
-        // Door has been open for 2 minutes, threshold is 1 minute - should trigger first SMS
-        monitor.handle_door_open_too_long(&args, warning_threshold, timestamp).await;
+        let args = Args::try_parse_from(["test", "--check-interval-seconds", "60"]).unwrap();
+        let _ = tokio::time::timeout(Duration::from_millis(200), monitor.run(args)).await;
 
-        // First SMS should be sent
-        assert!(monitor.state.sms_sent);
-        assert_eq!(monitor.state.sms_backoff_index, 1);
-        assert!(monitor.state.last_sms_time.is_some());
+        assert!(monitor.push_enabled);
+        assert_eq!(monitor.machine().state.last_door_state, Some(false));
     }
 
     #[tokio::test]
-    async fn test_handle_door_open_too_long_above_threshold_no_backoff() {
-        use crate::config::Args;
+    async fn test_first_pushed_state_is_the_initial_status() {
+        use async_trait::async_trait;
         use clap::Parser;
-        
-        let mut monitor = DoorMonitor::new();
-        // Set door as opened past threshold
-        monitor.state.door_opened_time = Some(Instant::now() - Duration::from_secs(120)); // 2 minutes ago
-        
-        let args = Args::try_parse_from(&[
-            "test", 
-            "--api-url", "http://test.com",
-            "--no-sms-backoff", // Disable backoff
-            "--sms-api-username", "test_user",
-            "--sms-api-password", "test_pass",
-            "--sms-from-phone-number", "1234567890",
-            "--sms-to-phone-number", "0987654321"
-        ]).unwrap();
-        let warning_threshold = Duration::from_secs(60); // 1 minute threshold
-        let timestamp = "2025-06-28 14:30:15 UTC";
+        use std::sync::Mutex;
 
-        // Door has been open for 2 minutes, threshold is 1 minute - should trigger single SMS
-        monitor.handle_door_open_too_long(&args, warning_threshold, timestamp).await;
+        // Connected, but no message from the door yet
+        struct NotHeardYet;
 
-        // Single SMS should be sent
-        assert!(monitor.state.sms_sent);
-        assert_eq!(monitor.state.sms_backoff_index, 0); // No backoff increment
-        assert!(monitor.state.last_sms_time.is_none()); // No last SMS time tracking
-    }
+        #[async_trait]
+        impl DoorSensor for NotHeardYet {
+            async fn read(&self) -> Result<DoorState, Error> {
+                Ok(DoorState::Unknown)
+            }
 
-    #[tokio::test]
-    async fn test_handle_door_open_too_long_no_door_open_time() {
-        use crate::config::Args;
-        use clap::Parser;
-        
-        let mut monitor = DoorMonitor::new();
-        // No door_opened_time set
-        
-        let args = Args::try_parse_from(&["test", "--api-url", "http://test.com"]).unwrap();
-        let warning_threshold = Duration::from_secs(60);
-        let timestamp = "2025-06-28 14:30:15 UTC";
+            fn watch(&self, events: mpsc::Sender<DoorState>) -> bool {
+                tokio::spawn(async move {
+                    let _ = events.send(DoorState::Open).await;
+                });
+                true
+            }
+        }
 
-        // Should not trigger anything since door_opened_time is None
-        monitor.handle_door_open_too_long(&args, warning_threshold, timestamp).await;
+        #[derive(Default)]
+        struct Recording(Mutex<Vec<Event>>);
 
-        // SMS state should remain unchanged
-        assert!(!monitor.state.sms_sent);
-        assert_eq!(monitor.state.sms_backoff_index, 0);
-        assert!(monitor.state.last_sms_time.is_none());
-    }
+        #[async_trait]
+        impl Notifier for Recording {
+            fn name(&self) -> &str {
+                "Recording"
+            }
 
-    #[tokio::test]
-    async fn test_handle_sms_with_backoff_first_sms() {
-        use crate::config::Args;
-        use clap::Parser;
-        
-        let mut monitor = DoorMonitor::new();
-        let args = Args::try_parse_from(&[
-            "test", 
-            "--api-url", "http://test.com",
-            "--sms-api-username", "test_user",
-            "--sms-api-password", "test_pass",
-            "--sms-from-phone-number", "1234567890",
-            "--sms-to-phone-number", "0987654321"
-        ]).unwrap();
-        let time_open = Duration::from_secs(900); // 15 minutes
-        let timestamp = "2025-06-28 14:30:15 UTC";
+            async fn send(&self, _door: &str, event: &Event) -> Result<(), Error> {
+                self.0.lock().unwrap().push(event.clone());
+                Ok(())
+            }
+        }
 
-        // First SMS - should send immediately
-        monitor.handle_sms_with_backoff(&args, time_open, timestamp).await;
+        let door = DoorConfig {
+            name: "Garage door".to_string(),
+            api_url: String::new(),
+            credentials: None,
+        };
+        let recording = Arc::new(Recording::default());
+        let mut monitor = DoorMonitor::with_sensor(reqwest::Client::new(), door, Box::new(NotHeardYet))
+            .with_notifiers(vec![recording.clone()]);
 
-        assert!(monitor.state.sms_sent);
-        assert_eq!(monitor.state.sms_backoff_index, 1);
-        assert!(monitor.state.last_sms_time.is_some());
+        let args = Args::try_parse_from(["test", "--check-interval-seconds", "60"]).unwrap();
+        let _ = tokio::time::timeout(Duration::from_millis(200), monitor.run(args)).await;
+
+        assert_eq!(*recording.0.lock().unwrap(), vec![Event::Started { state: DoorState::Open }]);
+        assert!(monitor.machine().is_open());
     }
 
     #[tokio::test]
-    async fn test_handle_sms_with_backoff_second_sms_too_early() {
-        use crate::config::Args;
+    async fn test_alerts_follow_injected_clock() {
+        use crate::clock::ManualClock;
         use clap::Parser;
-        
-        let mut monitor = DoorMonitor::new();
-        // Set up state as if first SMS was sent recently
-        monitor.state.sms_sent = true;
-        monitor.state.sms_backoff_index = 0;
-        monitor.state.last_sms_time = Some(Instant::now() - Duration::from_secs(120)); // 2 minutes ago
-        
-        let args = Args::try_parse_from(&[
-            "test", 
-            "--api-url", "http://test.com",
-            "--sms-api-username", "test_user",
-            "--sms-api-password", "test_pass",
-            "--sms-from-phone-number", "1234567890",
-            "--sms-to-phone-number", "0987654321"
-        ]).unwrap();
-        let time_open = Duration::from_secs(900); // 15 minutes
-        let timestamp = "2025-06-28 14:30:15 UTC";
 
-        // Second SMS attempt - should not send (first interval is 5 minutes)
-        monitor.handle_sms_with_backoff(&args, time_open, timestamp).await;
-
-        // Should remain at same backoff level
-        assert!(monitor.state.sms_sent);
-        assert_eq!(monitor.state.sms_backoff_index, 0);
+        let clock = Arc::new(ManualClock::new());
+        let mut monitor = DoorMonitor::new().with_clock(clock.clone());
+        let args = Args::try_parse_from([
+            "test", "--open-too-long-seconds", "60", "--sms-off", "--telegram-off",
+        ]).unwrap();
+        monitor.machine().configure(&args);
+
+        monitor.handle_reading(DoorState::Closed, &args).await;
+        monitor.handle_reading(DoorState::Open, &args).await;
+        monitor.handle_tick(&args).await;
+        assert!(!monitor.machine().state.sms_sent);
+
+        // Two virtual minutes later the door has been open too long
+        clock.advance(Duration::from_secs(120));
+        monitor.handle_tick(&args).await;
+        assert!(monitor.machine().state.sms_sent);
+        assert_eq!(monitor.machine().state.sms_backoff_index, 1);
+        assert_eq!(monitor.machine().state.last_sms_time, Some(clock.now()));
+
+        // The first reminder is due 15 minutes after the alert
+        clock.advance(Duration::from_secs(14 * 60));
+        monitor.handle_tick(&args).await;
+        assert_eq!(monitor.machine().state.sms_backoff_index, 1);
+        clock.advance(Duration::from_secs(60));
+        monitor.handle_tick(&args).await;
+        assert_eq!(monitor.machine().state.sms_backoff_index, 2);
     }
 
     #[tokio::test]
-    async fn test_handle_sms_with_backoff_second_sms_ready() {
-        use crate::config::Args;
+    async fn test_events_go_to_configured_notifiers() {
+        use async_trait::async_trait;
         use clap::Parser;
-        
-        let mut monitor = DoorMonitor::new();
-        // Set up state as if first SMS was sent 6 minutes ago (past first interval)
-        monitor.state.sms_sent = true;
-        monitor.state.sms_backoff_index = 0;
-        monitor.state.last_sms_time = Some(Instant::now() - Duration::from_secs(360)); // 6 minutes ago
-        
-        let args = Args::try_parse_from(&[
-            "test", 
-            "--api-url", "http://test.com",
-            "--sms-api-username", "test_user",
-            "--sms-api-password", "test_pass",
-            "--sms-from-phone-number", "1234567890",
-            "--sms-to-phone-number", "0987654321"
-        ]).unwrap();
-        let time_open = Duration::from_secs(900); // 15 minutes
-        let timestamp = "2025-06-28 14:30:15 UTC";
+        use std::sync::Mutex;
 
-        // Second SMS attempt - should send (past 5 minute interval)
-        monitor.handle_sms_with_backoff(&args, time_open, timestamp).await;
+        #[derive(Default)]
+        struct Recording(Mutex<Vec<String>>);
 
-        // Should advance to next backoff level
-        assert!(monitor.state.sms_sent);
-        assert_eq!(monitor.state.sms_backoff_index, 1);
-        assert!(monitor.state.last_sms_time.is_some());
-    }
+        #[async_trait]
+        impl Notifier for Recording {
+            fn name(&self) -> &str {
+                "Recording"
+            }
 
-    #[tokio::test]
-    async fn test_handle_sms_with_backoff_beyond_intervals() {
-        use crate::config::Args;
-        use clap::Parser;
-        
-        let mut monitor = DoorMonitor::new();
-        // Set up state as if we're past all defined intervals
-        monitor.state.sms_sent = true;
-        monitor.state.sms_backoff_index = 5; // Beyond the 4 defined intervals
-        monitor.state.last_sms_time = Some(Instant::now() - Duration::from_secs(3700)); // 61+ minutes ago
-        
-        let args = Args::try_parse_from(&[
-            "test", 
-            "--api-url", "http://test.com",
-            "--sms-api-username", "test_user",
-            "--sms-api-password", "test_pass",
-            "--sms-from-phone-number", "1234567890",
-            "--sms-to-phone-number", "0987654321"
-        ]).unwrap();
-        let time_open = Duration::from_secs(7200); // 2 hours
-        let timestamp = "2025-06-28 14:30:15 UTC";
+            async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+                self.0.lock().unwrap().push(event.message(door));
+                Ok(())
+            }
+        }
 
-        // Should send with 60-minute default interval
-        monitor.handle_sms_with_backoff(&args, time_open, timestamp).await;
+        let recording = Arc::new(Recording::default());
+        let mut monitor = DoorMonitor::new().with_notifiers(vec![recording.clone()]);
+        // The configured notifiers replace the SMS and Telegram channels from Args
+        let args = Args::try_parse_from(["test"]).unwrap();
 
-        // Should advance backoff index
-        assert!(monitor.state.sms_sent);
-        assert_eq!(monitor.state.sms_backoff_index, 6);
-        assert!(monitor.state.last_sms_time.is_some());
-    }
+        monitor.handle_reading(DoorState::Closed, &args).await;
+        monitor.handle_reading(DoorState::Open, &args).await;
 
-    #[tokio::test]
-    async fn test_handle_single_sms_first_time() {
-        use crate::config::Args;
-        use clap::Parser;
-        
-        let mut monitor = DoorMonitor::new();
-        let args = Args::try_parse_from(&[
-            "test", 
-            "--api-url", "http://test.com",
-            "--sms-api-username", "test_user",
-            "--sms-api-password", "test_pass",
-            "--sms-from-phone-number", "1234567890",
-            "--sms-to-phone-number", "0987654321"
-        ]).unwrap();
-        let time_open = Duration::from_secs(900); // 15 minutes
-        let timestamp = "2025-06-28 14:30:15 UTC";
+        assert_eq!(*recording.0.lock().unwrap(), vec!["Door has been opened"]);
 
-        // First single SMS - should send
-        monitor.handle_single_sms(&args, time_open, timestamp).await;
+        // Muted events are still tracked, just not sent
+        monitor.handle().machine.lock().unwrap().mute(Instant::now() + Duration::from_secs(60));
+        monitor.handle_reading(DoorState::Closed, &args).await;
+        assert_eq!(recording.0.lock().unwrap().len(), 1);
+        assert!(!monitor.machine().is_open());
 
-        assert!(monitor.state.sms_sent);
-        // Single SMS doesn't use backoff tracking
-        assert_eq!(monitor.state.sms_backoff_index, 0);
-        assert!(monitor.state.last_sms_time.is_none());
+        monitor.handle().machine.lock().unwrap().unmute();
+        // Opened a millisecond later, or it's the same event to the outbox
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        monitor.handle_reading(DoorState::Open, &args).await;
+        assert_eq!(recording.0.lock().unwrap().len(), 2);
     }
 
     #[tokio::test]
-    async fn test_handle_single_sms_already_sent() {
-        use crate::config::Args;
+    async fn test_failing_channel_is_reported_through_the_others() {
+        use async_trait::async_trait;
         use clap::Parser;
-        
-        let mut monitor = DoorMonitor::new();
-        // Set up state as if SMS was already sent
-        monitor.state.sms_sent = true;
-        
-        let args = Args::try_parse_from(&[
-            "test", 
-            "--api-url", "http://test.com",
-            "--sms-api-username", "test_user",
-            "--sms-api-password", "test_pass",
-            "--sms-from-phone-number", "1234567890",
-            "--sms-to-phone-number", "0987654321"
-        ]).unwrap();
-        let time_open = Duration::from_secs(900); // 15 minutes
-        let timestamp = "2025-06-28 14:30:15 UTC";
-
-        // Second single SMS attempt - should not send
-        monitor.handle_single_sms(&args, time_open, timestamp).await;
+        use std::sync::atomic::{AtomicBool, Ordering};
 
-        // State should remain unchanged
-        assert!(monitor.state.sms_sent);
-        assert_eq!(monitor.state.sms_backoff_index, 0);
-        assert!(monitor.state.last_sms_time.is_none());
-    }
+        #[derive(Default)]
+        struct Recording(Mutex<Vec<String>>);
 
-    #[tokio::test]
-    async fn test_door_state_change_from_closed_to_open() {
-        use crate::config::Args;
-        use clap::Parser;
-        use crate::door::DoorStatus;
-        
-        let mut monitor = DoorMonitor::new();
-        // Set initial state as closed
-        monitor.state.door_closed_time = Some(Instant::now() - Duration::from_secs(300));
-        monitor.state.last_door_state = Some(true); // Door was closed
-        
-        let args = Args::try_parse_from(&[
-            "test", 
-            "--api-url", "http://test.com",
-            "--sms-api-username", "test_user",
-            "--sms-api-password", "test_pass",
-            "--sms-from-phone-number", "1234567890",
-            "--sms-to-phone-number", "0987654321"
-        ]).unwrap();
-        let door_status = DoorStatus { id: 1, state: false }; // Door is now open
-        let warning_threshold = Duration::from_secs(60);
+        #[async_trait]
+        impl Notifier for Recording {
+            fn name(&self) -> &str {
+                "Telegram"
+            }
 
-        // This should detect state change and send SMS
-        monitor.handle_door_status(&door_status, &args, warning_threshold).await;
+            async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+                self.0.lock().unwrap().push(event.message(door));
+                Ok(())
+            }
+        }
 
-        // Should transition to open state and send SMS
-        assert!(monitor.state.door_opened_time.is_some());
-        assert!(monitor.state.door_closed_time.is_none());
-        assert_eq!(monitor.state.last_door_state, Some(false));
-    }
+        struct BrokenSms(AtomicBool);
 
-    #[tokio::test]
-    async fn test_door_state_change_from_open_to_closed() {
-        use crate::config::Args;
-        use clap::Parser;
-        use crate::door::DoorStatus;
-        
-        let mut monitor = DoorMonitor::new();
-        // Set initial state as open
-        monitor.state.door_opened_time = Some(Instant::now() - Duration::from_secs(300));
-        monitor.state.last_door_state = Some(false); // Door was open
-        
-        let args = Args::try_parse_from(&[
-            "test", 
-            "--api-url", "http://test.com",
-            "--sms-api-username", "test_user",
-            "--sms-api-password", "test_pass",
-            "--sms-from-phone-number", "1234567890",
-            "--sms-to-phone-number", "0987654321"
-        ]).unwrap();
-        let door_status = DoorStatus { id: 1, state: true }; // Door is now closed
-        let warning_threshold = Duration::from_secs(60);
+        #[async_trait]
+        impl Notifier for BrokenSms {
+            fn name(&self) -> &str {
+                "SMS"
+            }
 
-        // This should detect state change and send SMS
-        monitor.handle_door_status(&door_status, &args, warning_threshold).await;
+            async fn send(&self, _door: &str, _event: &Event) -> Result<(), Error> {
+                if self.0.load(Ordering::SeqCst) {
+                    return Ok(());
+                }
+                Err(crate::sms::check_status("invalid_credentials").unwrap_err())
+            }
+        }
 
-        // Should transition to closed state and send SMS
-        assert!(monitor.state.door_opened_time.is_none());
-        assert!(monitor.state.door_closed_time.is_some());
-        assert_eq!(monitor.state.last_door_state, Some(true));
-        // SMS state should be reset after closing
-        assert!(!monitor.state.sms_sent);
-        assert_eq!(monitor.state.sms_backoff_index, 0);
+        let telegram = Arc::new(Recording::default());
+        let sms = Arc::new(BrokenSms(AtomicBool::new(false)));
+        let mut monitor = DoorMonitor::new().with_notifiers(vec![sms.clone(), telegram.clone()]);
+        let args = Args::try_parse_from(["test"]).unwrap();
+        let warning = "WARNING: SMS notifications for Door are failing: \
+            voip.ms refused the message: invalid_credentials (the API username or password is wrong)";
+
+        monitor.handle_reading(DoorState::Closed, &args).await;
+        monitor.handle_reading(DoorState::Open, &args).await;
+        assert_eq!(*telegram.0.lock().unwrap(), vec!["Door has been opened", warning]);
+
+        // Reported again only after SMS has worked in between
+        sms.0.store(true, Ordering::SeqCst);
+        monitor.handle_reading(DoorState::Closed, &args).await;
+        sms.0.store(false, Ordering::SeqCst);
+        // Opened a millisecond later, or it's the same event to the outbox
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        monitor.handle_reading(DoorState::Open, &args).await;
+        assert_eq!(telegram.0.lock().unwrap().iter().filter(|message| *message == warning).count(), 2);
     }
 
     #[tokio::test]
-    async fn test_handle_sms_with_backoff_no_last_sms_time() {
-        use crate::config::Args;
+    async fn test_telegram_only_setup_is_not_told_about_sms() {
         use clap::Parser;
-        
-        let mut monitor = DoorMonitor::new();
-        // Set up inconsistent state - sms_sent but no last_sms_time
-        monitor.state.sms_sent = true;
-        monitor.state.sms_backoff_index = 1;
-        monitor.state.last_sms_time = None; // This should not happen in normal operation
-        
-        let args = Args::try_parse_from(&[
-            "test", 
-            "--api-url", "http://test.com",
-            "--sms-api-username", "test_user",
-            "--sms-api-password", "test_pass",
-            "--sms-from-phone-number", "1234567890",
-            "--sms-to-phone-number", "0987654321"
+        use mockito::Server;
+
+        let mut server = Server::new_async().await;
+        // The door opening, and no warning that SMS is failing
+        let telegram = server.mock("POST", "/bot123:abc/sendMessage")
+            .with_status(200)
+            .with_body(r#"{"ok":true,"result":{"message_id":1}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let args = Args::try_parse_from([
+            "test", "--telegram-token", "123:abc", "--telegram-conversation-id", "42",
+            "--telegram-api-url", &server.url(),
         ]).unwrap();
-        let time_open = Duration::from_secs(900); // 15 minutes
-        let timestamp = "2025-06-28 14:30:15 UTC";
+        let mut monitor = DoorMonitor::new();
 
-        // Should handle gracefully and not send SMS
-        monitor.handle_sms_with_backoff(&args, time_open, timestamp).await;
+        monitor.handle_reading(DoorState::Closed, &args).await;
+        monitor.handle_reading(DoorState::Open, &args).await;
 
-        // Should remain unchanged
-        assert!(monitor.state.sms_sent);
-        assert_eq!(monitor.state.sms_backoff_index, 1);
-        assert!(monitor.state.last_sms_time.is_none());
+        telegram.assert_async().await;
+        assert!(monitor.failing_channels.is_empty());
     }
 
     #[tokio::test]
-    async fn test_handle_door_status_with_warning_threshold_trigger() {
-        use crate::config::Args;
+    async fn test_notifications_are_retried_through_the_outbox() {
+        use async_trait::async_trait;
         use clap::Parser;
-        use crate::door::DoorStatus;
-        
-        let mut monitor = DoorMonitor::new();
-        // Set door as open for longer than threshold
-        monitor.state.door_opened_time = Some(Instant::now() - Duration::from_secs(120)); // 2 minutes ago
-        monitor.state.last_door_state = Some(false); // Door was already open
-        
-        let args = Args::try_parse_from(&[
-            "test", 
-            "--api-url", "http://test.com",
-            "--sms-api-username", "test_user",
-            "--sms-api-password", "test_pass",
-            "--sms-from-phone-number", "1234567890",
-            "--sms-to-phone-number", "0987654321"
-        ]).unwrap();
-        let door_status = DoorStatus { id: 1, state: false }; // Door is still open
-        let warning_threshold = Duration::from_secs(60); // 1 minute threshold
+        use reqwest::StatusCode;
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        #[derive(Default)]
+        struct Uplink {
+            up: AtomicBool,
+            sent: Mutex<Vec<String>>,
+        }
 
-        // This should trigger warning logic since door has been open > threshold
-        monitor.handle_door_status(&door_status, &args, warning_threshold).await;
+        #[async_trait]
+        impl Notifier for Uplink {
+            fn name(&self) -> &str {
+                "SMS"
+            }
+
+            async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+                if !self.up.load(Ordering::SeqCst) {
+                    return Err(Error::NotifierRejected {
+                        provider: "voip.ms".to_string(),
+                        status: StatusCode::SERVICE_UNAVAILABLE,
+                        body: String::new(),
+                    });
+                }
+                self.sent.lock().unwrap().push(event.message(door));
+                Ok(())
+            }
+        }
 
-        // Warning should have triggered first SMS
-        assert!(monitor.state.sms_sent);
-        assert_eq!(monitor.state.sms_backoff_index, 1);
-        assert!(monitor.state.last_sms_time.is_some());
+        let sms = Arc::new(Uplink::default());
+        let outbox = Arc::new(Outbox::in_memory());
+        let mut monitor = DoorMonitor::new().with_notifiers(vec![sms.clone()]).with_outbox(outbox.clone());
+        let args = Args::try_parse_from(["test"]).unwrap();
+
+        monitor.handle_reading(DoorState::Closed, &args).await;
+        monitor.handle_reading(DoorState::Open, &args).await;
+        assert_eq!(outbox.len(), 1);
+
+        // Once the uplink is back, the door opening and closing go out together
+        sms.up.store(true, Ordering::SeqCst);
+        monitor.handle_reading(DoorState::Closed, &args).await;
+        assert!(outbox.is_empty());
+        let sent = sms.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].starts_with("2 notifications about Door couldn't be sent at the time:"));
+        assert!(sent[0].ends_with("Door is now closed after being open for 00:00:00"));
     }
 
     #[test]
@@ -999,17 +1091,73 @@ mod tests {
         // but we can test that it compiles and starts
         let args = crate::config::Args {
             api_url: Some("http://test.com".to_string()),
+            doors: Vec::new(),
             check_interval_seconds: 1,
+            listen: None,
+            listen_token: None,
+            reconcile_interval_seconds: 300,
+            debounce_readings: 1,
+            debounce_seconds: 0,
+            flap_threshold: 0,
+            flap_window_seconds: 60,
+            sensor_offline_seconds: 300,
+            connect_timeout_seconds: 5,
+            request_timeout_seconds: 10,
+            sensor_retries: 2,
+            sensor_retry_delay_ms: 500,
+            sensor_username: "admin".to_string(),
+            sensor_password: None,
             open_too_long_seconds: 5,
             sms_off: false,
             sms_api_username: None,
             sms_api_password: None,
             sms_from_phone_number: None,
-            sms_to_phone_number: None,
+            sms_to_phone_numbers: Vec::new(),
+            sms_api_url: "https://voip.ms/api/v1/rest.php".to_string(),
+            sms_replies: false,
+            acknowledge_snooze_minutes: None,
             no_sms_backoff: false,
             telegram_off: false,
             telegram_token: None,
-            telegram_conversation_id: None,
+            telegram_conversation_ids: Vec::new(),
+            telegram_api_url: "https://api.telegram.org".to_string(),
+            telegram_live_message: false,
+            telegram_commands: false,
+            telegram_allowed_ids: Vec::new(),
+            webhook_url: None,
+            webhook_headers: Vec::new(),
+            webhook_template: None,
+            email_smtp_host: None,
+            email_smtp_port: None,
+            email_smtp_security: crate::email::SmtpSecurity::Starttls,
+            email_smtp_username: None,
+            email_smtp_password: None,
+            email_from: None,
+            email_to: Vec::new(),
+            ntfy_topic: None,
+            ntfy_url: "https://ntfy.sh".to_string(),
+            ntfy_token: None,
+            ntfy_tags: Vec::new(),
+            gotify_url: None,
+            gotify_token: None,
+            pushover_app_token: None,
+            pushover_user_key: None,
+            pushover_emergency: false,
+            pushover_retry_seconds: 60,
+            pushover_expire_seconds: 3600,
+            slack_webhook_url: None,
+            discord_webhook_url: None,
+            matrix_homeserver: None,
+            matrix_room_id: None,
+            matrix_access_token: None,
+            outbox_file: None,
+            rate_limits: Vec::new(),
+            daily_quotas: Vec::new(),
+            monthly_quotas: Vec::new(),
+            overflow_channel: None,
+            usage_file: None,
+            templates: Vec::new(),
+            command: None,
             telegram_test: false,
             test_message: None,
         };
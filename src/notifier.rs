@@ -0,0 +1,321 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
+
+use crate::chat::{DiscordNotifier, MatrixNotifier, SlackNotifier};
+use crate::config::Args;
+use crate::email::EmailNotifier;
+use crate::push::{GotifyNotifier, NtfyNotifier, PushoverNotifier};
+use crate::error::Error;
+use crate::sms::SmsNotifier;
+use crate::state_machine::Event;
+use crate::telegram::TelegramNotifier;
+use crate::webhook_notifier::WebhookNotifier;
+
+/// A channel that door events can be sent through, e.g. SMS or Telegram.
+///
+/// Implement this for a new service and add it to `notifiers_from_args` to
+/// have every event sent there without touching `monitor.rs`.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// The channel name used in logs, e.g. "SMS".
+    fn name(&self) -> &str;
+
+    /// Sends `event` about `door`.
+    async fn send(&self, door: &str, event: &Event) -> Result<(), Error>;
+
+    /// Sends `event` about `door`, which happened at `at`, e.g. when it is
+    /// sent again from the outbox. Only notifiers that put the time in the
+    /// message need to override this.
+    async fn send_at(&self, door: &str, event: &Event, at: DateTime<Utc>) -> Result<(), Error> {
+        let _ = at;
+        self.send(door, event).await
+    }
+
+    /// Who each message goes to, for channels that send to several, e.g.
+    /// the SMS numbers. The outbox sends to and retries each of them on its
+    /// own, so a retry doesn't reach those who already got the message.
+    fn recipients(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Sends `event` about `door`, which happened at `at`, to `recipient`
+    /// only, one of `recipients`.
+    async fn send_to(&self, recipient: &str, door: &str, event: &Event, at: DateTime<Utc>) -> Result<(), Error> {
+        let _ = recipient;
+        self.send_at(door, event, at).await
+    }
+}
+
+/// The outcome of sending one event through one channel.
+#[derive(Debug)]
+pub struct Delivery {
+    pub channel: String,
+    pub result: Result<(), Error>,
+}
+
+/// Turns a response from `provider` into `Error::NotifierRejected` unless it is a 2xx.
+pub async fn check_response(provider: &str, response: reqwest::Response) -> Result<(), Error> {
+    let status = response.status();
+    if status.is_success() {
+        Ok(())
+    } else {
+        let body = response.text().await.unwrap_or_default();
+        Err(Error::NotifierRejected {
+            provider: provider.to_string(),
+            status,
+            body,
+        })
+    }
+}
+
+/// Combines the results of sending to each of a channel's recipients, logging
+/// each failure. Succeeds only if every recipient got the message. With a
+/// single recipient its error is returned as is.
+pub fn check_recipients(provider: &str, results: Vec<(String, Result<(), Error>)>) -> Result<(), Error> {
+    let recipients = results.len();
+    let mut failures: Vec<(String, Error)> = results
+        .into_iter()
+        .filter_map(|(recipient, result)| result.err().map(|e| (recipient, e)))
+        .collect();
+    if failures.is_empty() {
+        return Ok(());
+    }
+    if recipients == 1 {
+        return Err(failures.remove(0).1);
+    }
+
+    let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+    for (recipient, e) in &failures {
+        eprintln!("[{}] Failed to send {} to {}: {}", timestamp, provider, recipient, e);
+    }
+    Err(Error::RecipientsFailed {
+        provider: provider.to_string(),
+        recipients,
+        failures,
+    })
+}
+
+/// The notifiers enabled on the command line.
+///
+/// SMS and Telegram are on unless turned off, but are left out while their
+/// credentials and recipients aren't all given, so a setup that only uses
+/// one of them isn't told on every event that the other one is failing.
+pub fn notifiers_from_args(client: &reqwest::Client, args: &Args) -> Vec<Arc<dyn Notifier>> {
+    let mut notifiers: Vec<Arc<dyn Notifier>> = Vec::new();
+    let sms_configured = args.sms_api_username.is_some()
+        && args.sms_api_password.is_some()
+        && args.sms_from_phone_number.is_some()
+        && !args.sms_to_phone_numbers.is_empty();
+    if !args.sms_off && sms_configured {
+        notifiers.push(Arc::new(SmsNotifier::new(client.clone(), args.clone())));
+    }
+    if !args.telegram_off && args.telegram_token.is_some() && !args.telegram_conversation_ids.is_empty() {
+        notifiers.push(Arc::new(TelegramNotifier::new(client.clone(), args.clone())));
+    }
+    if let Some(url) = &args.webhook_url {
+        let webhook = WebhookNotifier::new(client.clone(), url)
+            .with_headers(args.webhook_headers.clone())
+            .with_template(args.webhook_template.clone());
+        notifiers.push(Arc::new(webhook));
+    }
+    if let Some(topic) = &args.ntfy_topic {
+        let ntfy = NtfyNotifier::new(client.clone(), &args.ntfy_url, topic)
+            .with_token(args.ntfy_token.clone())
+            .with_tags(args.ntfy_tags.clone());
+        notifiers.push(Arc::new(ntfy));
+    }
+    if let (Some(url), Some(token)) = (&args.gotify_url, &args.gotify_token) {
+        notifiers.push(Arc::new(GotifyNotifier::new(client.clone(), url, token)));
+    }
+    if let (Some(app_token), Some(user_key)) = (&args.pushover_app_token, &args.pushover_user_key) {
+        let mut pushover = PushoverNotifier::new(client.clone(), app_token, user_key);
+        if args.pushover_emergency {
+            pushover = pushover.with_emergency(
+                Duration::from_secs(args.pushover_retry_seconds),
+                Duration::from_secs(args.pushover_expire_seconds),
+            );
+        }
+        notifiers.push(Arc::new(pushover));
+    }
+    if let Some(url) = &args.slack_webhook_url {
+        notifiers.push(Arc::new(SlackNotifier::new(client.clone(), url)));
+    }
+    if let Some(url) = &args.discord_webhook_url {
+        notifiers.push(Arc::new(DiscordNotifier::new(client.clone(), url)));
+    }
+    if let (Some(homeserver), Some(room_id), Some(access_token)) =
+        (&args.matrix_homeserver, &args.matrix_room_id, &args.matrix_access_token)
+    {
+        notifiers.push(Arc::new(MatrixNotifier::new(client.clone(), homeserver, room_id, access_token)));
+    }
+    match EmailNotifier::from_args(args) {
+        Ok(Some(email)) => notifiers.push(Arc::new(email)),
+        Ok(None) => {}
+        Err(e) => {
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            eprintln!("[{}] Email notifications are disabled: {}", timestamp, e);
+        }
+    }
+    notifiers
+}
+
+/// Sends `event` through every notifier at once, so a slow channel doesn't
+/// hold up the others. Returns one `Delivery` per notifier, in order.
+pub async fn notify_all(notifiers: &[Arc<dyn Notifier>], door: &str, event: &Event) -> Vec<Delivery> {
+    let sends = notifiers.iter().map(|notifier| async move {
+        Delivery {
+            channel: notifier.name().to_string(),
+            result: send_logged(notifier.as_ref(), None, door, event, Utc::now()).await,
+        }
+    });
+    join_all(sends).await
+}
+
+/// Sends `event`, which happened at `at`, through `notifier`, to only
+/// `recipient` if given, logging the attempt and any failure.
+pub async fn send_logged(
+    notifier: &dyn Notifier,
+    recipient: Option<&str>,
+    door: &str,
+    event: &Event,
+    at: DateTime<Utc>,
+) -> Result<(), Error> {
+    let label = event.label();
+    let to = recipient.map(|recipient| format!(" to {}", recipient)).unwrap_or_default();
+    let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+    println!("[{}] Sending {} {}{}...", timestamp, label, notifier.name(), to);
+    let result = match recipient {
+        Some(recipient) => notifier.send_to(recipient, door, event, at).await,
+        None => notifier.send_at(door, event, at).await,
+    };
+    if let Err(e) = &result {
+        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+        eprintln!("[{}] Failed to send {} {}{}: {}", timestamp, label, notifier.name(), to, e);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::sync::Mutex;
+
+    struct Recording {
+        name: &'static str,
+        sent: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Notifier for Recording {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+            self.sent.lock().unwrap().push(event.message(door));
+            Ok(())
+        }
+    }
+
+    struct Failing;
+
+    #[async_trait]
+    impl Notifier for Failing {
+        fn name(&self) -> &str {
+            "Pager"
+        }
+
+        async fn send(&self, _door: &str, _event: &Event) -> Result<(), Error> {
+            Err(Error::MissingCredentials { provider: "Pager".to_string() })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_notify_all_collects_results_per_channel() {
+        let recording = Arc::new(Recording {
+            name: "Log",
+            sent: Mutex::new(Vec::new()),
+        });
+        let notifiers: Vec<Arc<dyn Notifier>> = vec![recording.clone(), Arc::new(Failing)];
+
+        let deliveries = notify_all(&notifiers, "Garage door", &Event::Opened).await;
+
+        assert_eq!(deliveries.len(), 2);
+        assert_eq!(deliveries[0].channel, "Log");
+        assert!(deliveries[0].result.is_ok());
+        assert_eq!(deliveries[1].channel, "Pager");
+        assert!(matches!(deliveries[1].result, Err(Error::MissingCredentials { .. })));
+        // A failing channel doesn't stop the others
+        assert_eq!(*recording.sent.lock().unwrap(), vec!["Garage door has been opened"]);
+    }
+
+    #[test]
+    fn test_notifiers_from_args() {
+        let client = reqwest::Client::new();
+
+        let sms = [
+            "--sms-api-username", "user", "--sms-api-password", "secret",
+            "--sms-from-phone-number", "5551234567", "--sms-to-phone-number", "5559876543",
+        ];
+        let telegram = ["--telegram-token", "123:abc", "--telegram-conversation-id", "-100123"];
+        let parse = |flags: &[&[&str]]| Args::try_parse_from(std::iter::once(&"test").chain(flags.concat().iter())).unwrap();
+
+        let args = parse(&[&sms, &telegram]);
+        let names: Vec<String> = notifiers_from_args(&client, &args).iter().map(|n| n.name().to_string()).collect();
+        assert_eq!(names, vec!["SMS", "Telegram"]);
+
+        let args = parse(&[&sms, &telegram, &["--sms-off"]]);
+        let names: Vec<String> = notifiers_from_args(&client, &args).iter().map(|n| n.name().to_string()).collect();
+        assert_eq!(names, vec!["Telegram"]);
+
+        // SMS isn't turned off, but without credentials it is left out
+        let args = parse(&[&telegram]);
+        let names: Vec<String> = notifiers_from_args(&client, &args).iter().map(|n| n.name().to_string()).collect();
+        assert_eq!(names, vec!["Telegram"]);
+        let args = parse(&[&telegram, &["--sms-api-username", "user"]]);
+        assert_eq!(notifiers_from_args(&client, &args).len(), 1);
+
+        let args = Args::try_parse_from(["test"]).unwrap();
+        assert!(notifiers_from_args(&client, &args).is_empty());
+
+        let args = parse(&[&sms, &telegram, &["--sms-off", "--telegram-off"]]);
+        assert!(notifiers_from_args(&client, &args).is_empty());
+
+        let args = parse(&[&telegram, &["--sms-off", "--webhook-url", "http://hooks.test"]]);
+        let names: Vec<String> = notifiers_from_args(&client, &args).iter().map(|n| n.name().to_string()).collect();
+        assert_eq!(names, vec!["Telegram", "Webhook"]);
+
+        let args = Args::try_parse_from([
+            "test", "--sms-off", "--telegram-off", "--ntfy-topic", "garage",
+            "--gotify-url", "http://gotify.lan", "--gotify-token", "AbCdEf",
+            "--pushover-app-token", "app", "--pushover-user-key", "user",
+        ]).unwrap();
+        let names: Vec<String> = notifiers_from_args(&client, &args).iter().map(|n| n.name().to_string()).collect();
+        assert_eq!(names, vec!["ntfy", "Gotify", "Pushover"]);
+
+        let args = Args::try_parse_from([
+            "test", "--sms-off", "--telegram-off",
+            "--slack-webhook-url", "https://hooks.slack.com/services/T000/B000/XXXX",
+            "--discord-webhook-url", "https://discord.com/api/webhooks/123/abc",
+            "--matrix-homeserver", "https://matrix.example.org", "--matrix-room-id", "!room:example.org",
+            "--matrix-access-token", "syt_abc",
+        ]).unwrap();
+        let names: Vec<String> = notifiers_from_args(&client, &args).iter().map(|n| n.name().to_string()).collect();
+        assert_eq!(names, vec!["Slack", "Discord", "Matrix"]);
+    }
+
+    #[test]
+    fn test_partial_notifier_config_is_rejected() {
+        let missing = |args: &[&str]| Args::try_parse_from(args).unwrap_err().to_string();
+
+        assert!(missing(&["test", "--gotify-url", "http://gotify.lan"]).contains("--gotify-token"));
+        assert!(missing(&["test", "--pushover-user-key", "user"]).contains("--pushover-app-token"));
+        let error = missing(&["test", "--matrix-homeserver", "https://matrix.example.org", "--matrix-room-id", "!room:example.org"]);
+        assert!(error.contains("--matrix-access-token"));
+    }
+}
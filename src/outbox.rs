@@ -0,0 +1,860 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Args;
+use crate::error::Error;
+use crate::notifier::{Delivery, Notifier, check_recipients, send_logged};
+use crate::retry::RetryPolicy;
+use crate::state_machine::Event;
+use crate::utils::format_duration;
+
+/// How long to wait before resending a notification the first time. The
+/// wait doubles with every failure.
+pub const OUTBOX_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// The longest wait between attempts to resend a notification.
+pub const OUTBOX_MAX_RETRY_DELAY: Duration = Duration::from_secs(15 * 60);
+
+/// How many delivered or dropped records the file may collect before it is
+/// rewritten, even though something is still pending.
+const OUTBOX_COMPACT_AFTER: usize = 100;
+
+/// An event to be sent through some channels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Intent {
+    /// Made from the door, the kind of event and the episode it belongs to,
+    /// e.g. when the door was opened, so the same event is only queued and
+    /// sent once
+    pub id: String,
+    pub door: String,
+    pub event: Event,
+    pub created_at: DateTime<Utc>,
+    pub channels: Vec<Channel>,
+}
+
+/// A channel to send an event through, and who to send it to if the
+/// channel sends to several, see `Notifier::recipients`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Channel {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recipients: Vec<String>,
+}
+
+impl Channel {
+    /// The channel `notifier` sends through, with its recipients.
+    pub fn of(notifier: &dyn Notifier) -> Self {
+        Self {
+            name: notifier.name().to_string(),
+            recipients: notifier.recipients(),
+        }
+    }
+}
+
+impl From<&str> for Channel {
+    fn from(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            recipients: Vec::new(),
+        }
+    }
+}
+
+/// A line of the outbox file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Record {
+    Queued(Intent),
+    Delivered {
+        id: String,
+        channel: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        recipient: Option<String>,
+    },
+    /// Sending failed in a way that retrying won't fix
+    Dropped {
+        id: String,
+        channel: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        recipient: Option<String>,
+    },
+}
+
+/// An event still waiting to go through one channel, to one of its
+/// recipients if it sends to several.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pending {
+    pub id: String,
+    pub door: String,
+    pub event: Event,
+    pub created_at: DateTime<Utc>,
+    pub channel: String,
+    pub recipient: Option<String>,
+    /// Failed attempts so far
+    pub attempts: u32,
+    pub next_attempt: DateTime<Utc>,
+}
+
+impl Pending {
+    /// Whether this is waiting for `channel` and `recipient`.
+    fn is_for(&self, channel: &str, recipient: Option<&str>) -> bool {
+        self.channel == channel && self.recipient.as_deref() == recipient
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    pending: Vec<Pending>,
+    /// Ids of every intent queued since the outbox was opened
+    seen: HashSet<String>,
+    /// Delivered and dropped records in the file since it was last rewritten
+    finished: usize,
+}
+
+impl Queue {
+    fn apply(&mut self, record: Record) {
+        match record {
+            Record::Queued(intent) => {
+                if !self.seen.insert(intent.id.clone()) {
+                    return;
+                }
+                for channel in intent.channels {
+                    let recipients: Vec<Option<String>> = if channel.recipients.is_empty() {
+                        vec![None]
+                    } else {
+                        channel.recipients.into_iter().map(Some).collect()
+                    };
+                    for recipient in recipients {
+                        self.pending.push(Pending {
+                            id: intent.id.clone(),
+                            door: intent.door.clone(),
+                            event: intent.event.clone(),
+                            created_at: intent.created_at,
+                            channel: channel.name.clone(),
+                            recipient,
+                            attempts: 0,
+                            next_attempt: intent.created_at,
+                        });
+                    }
+                }
+            }
+            Record::Delivered { id, channel, recipient } | Record::Dropped { id, channel, recipient } => {
+                self.pending.retain(|pending| pending.id != id || !pending.is_for(&channel, recipient.as_deref()));
+                self.finished += 1;
+            }
+        }
+    }
+
+    /// The pending intents as `Queued` records, one per intent.
+    fn records(&self) -> Vec<Record> {
+        let mut intents: Vec<Intent> = Vec::new();
+        for pending in &self.pending {
+            let intent = match intents.iter().position(|intent| intent.id == pending.id) {
+                Some(index) => &mut intents[index],
+                None => {
+                    intents.push(Intent {
+                        id: pending.id.clone(),
+                        door: pending.door.clone(),
+                        event: pending.event.clone(),
+                        created_at: pending.created_at,
+                        channels: Vec::new(),
+                    });
+                    intents.last_mut().unwrap()
+                }
+            };
+            let channel = match intent.channels.iter().position(|channel| channel.name == pending.channel) {
+                Some(index) => &mut intent.channels[index],
+                None => {
+                    intent.channels.push(Channel::from(pending.channel.as_str()));
+                    intent.channels.last_mut().unwrap()
+                }
+            };
+            channel.recipients.extend(pending.recipient.clone());
+        }
+        intents.into_iter().map(Record::Queued).collect()
+    }
+}
+
+/// Notifications waiting to be sent, so that an alert raised while the
+/// network is down isn't lost.
+///
+/// Every event is queued for each channel before it is sent and only taken
+/// off once that channel has sent it. Failures that retrying may fix are
+/// retried with an exponentially growing delay, separately for each channel,
+/// and for each recipient of channels that send to several.
+/// Failures that retrying won't fix, like wrong credentials, are given up on.
+///
+/// With a file, every change is appended to it as a line of JSON, so
+/// whatever wasn't sent is picked up again after a restart. The file is
+/// rewritten to hold only what is still pending when it is opened, whenever
+/// everything has been sent, and once `OUTBOX_COMPACT_AFTER` notifications
+/// have been sent since it was last rewritten.
+pub struct Outbox {
+    path: Option<PathBuf>,
+    retry: RetryPolicy,
+    queue: Mutex<Queue>,
+    /// Held while writing the file, so the queue isn't locked during file
+    /// I/O but records are still written in the order they were applied
+    file: Mutex<()>,
+}
+
+impl Outbox {
+    /// An outbox that only lives in memory, so it doesn't survive a restart.
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            retry: RetryPolicy {
+                max_retries: u32::MAX,
+                base_delay: OUTBOX_RETRY_DELAY,
+                max_delay: OUTBOX_MAX_RETRY_DELAY,
+            },
+            queue: Mutex::new(Queue::default()),
+            file: Mutex::new(()),
+        }
+    }
+
+    /// Opens the outbox file at `path`, creating it if needed, and picks up
+    /// whatever a previous run didn't get to send. A line that can't be read,
+    /// e.g. one cut short by a power cut, is skipped.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let mut queue = Queue::default();
+        match File::open(&path) {
+            Ok(file) => {
+                for (number, line) in BufReader::new(file).lines().enumerate() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match serde_json::from_str(&line) {
+                        Ok(record) => queue.apply(record),
+                        Err(e) => {
+                            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                            eprintln!("[{}] Skipping line {} of {}: {}", timestamp, number + 1, path.display(), e);
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        // Whatever is left over was tried before the restart, so it gets
+        // summarised along with what comes next
+        for pending in &mut queue.pending {
+            pending.attempts = 1;
+        }
+        queue.finished = 0;
+        rewrite(&path, &queue.records())?;
+
+        Ok(Self {
+            path: Some(path),
+            queue: Mutex::new(queue),
+            ..Self::in_memory()
+        })
+    }
+
+    /// The outbox file from `--outbox-file`, or an in-memory outbox without one.
+    pub fn from_args(args: &Args) -> Result<Self, Error> {
+        match &args.outbox_file {
+            Some(path) => Self::open(path),
+            None => Ok(Self::in_memory()),
+        }
+    }
+
+    /// Waits according to `retry` between attempts instead of the defaults.
+    /// Its `max_retries` is ignored, notifications are retried until sent.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Number of notifications still to be sent, counting each channel and
+    /// recipient.
+    pub fn len(&self) -> usize {
+        self.queue.lock().unwrap().pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Queues `event` about `door`, which happened at `now`, for each of
+    /// `channels` and their recipients, unless the same event was queued
+    /// before.
+    pub fn enqueue(&self, door: &str, event: &Event, channels: &[Channel], now: DateTime<Utc>) -> Intent {
+        let intent = Intent {
+            id: intent_id(door, event, now),
+            door: door.to_string(),
+            event: event.clone(),
+            created_at: now,
+            channels: channels.to_vec(),
+        };
+        if !self.add(intent.clone()) {
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            println!("[{}] {} {} notification is already queued", timestamp, door, event.label());
+        }
+        intent
+    }
+
+    /// Queues `intent` unless an intent with the same id was queued before.
+    pub fn add(&self, intent: Intent) -> bool {
+        let queue = self.queue.lock().unwrap();
+        if queue.seen.contains(&intent.id) {
+            return false;
+        }
+        self.commit(queue, vec![Record::Queued(intent)]);
+        true
+    }
+
+    /// Whether anything about `door` is due to be sent at `now`.
+    pub fn is_due(&self, door: &str, now: DateTime<Utc>) -> bool {
+        self.queue
+            .lock()
+            .unwrap()
+            .pending
+            .iter()
+            .any(|pending| pending.door == door && pending.next_attempt <= now)
+    }
+
+    /// Everything waiting to be sent about `door`, by channel and recipient,
+    /// for those where something is due at `now`. Oldest first.
+    pub fn due(&self, door: &str, now: DateTime<Utc>) -> Vec<Vec<Pending>> {
+        let queue = self.queue.lock().unwrap();
+        let mut lanes: Vec<Vec<Pending>> = Vec::new();
+        for pending in queue.pending.iter().filter(|pending| pending.door == door) {
+            match lanes.iter_mut().find(|waiting| waiting[0].is_for(&pending.channel, pending.recipient.as_deref())) {
+                Some(waiting) => waiting.push(pending.clone()),
+                None => lanes.push(vec![pending.clone()]),
+            }
+        }
+        lanes.retain(|waiting| waiting.iter().any(|pending| pending.next_attempt <= now));
+        lanes
+    }
+
+    /// Takes the intents in `ids` off the queue for `channel` and `recipient`.
+    pub fn delivered(&self, channel: &str, recipient: Option<&str>, ids: &[String]) {
+        self.finish(channel, recipient, ids, |id, channel, recipient| Record::Delivered { id, channel, recipient });
+    }
+
+    /// Records that sending the intents in `ids` through `channel` to
+    /// `recipient` failed. If retrying may help, returns how long until the
+    /// next attempt. Otherwise they are taken off the queue.
+    pub fn failed(
+        &self,
+        channel: &str,
+        recipient: Option<&str>,
+        ids: &[String],
+        error: &Error,
+        now: DateTime<Utc>,
+    ) -> Option<Duration> {
+        if !error.is_transient() {
+            self.finish(channel, recipient, ids, |id, channel, recipient| Record::Dropped { id, channel, recipient });
+            return None;
+        }
+
+        let mut queue = self.queue.lock().unwrap();
+        let attempts = queue
+            .pending
+            .iter()
+            .filter(|pending| pending.is_for(channel, recipient) && ids.contains(&pending.id))
+            .map(|pending| pending.attempts)
+            .max()
+            .unwrap_or(0);
+        let delay = self.retry.delay(attempts);
+        let next_attempt = now + chrono::Duration::from_std(delay).unwrap_or_default();
+        for pending in queue.pending.iter_mut() {
+            if pending.is_for(channel, recipient) && ids.contains(&pending.id) {
+                pending.attempts = attempts + 1;
+                pending.next_attempt = next_attempt;
+            }
+        }
+        Some(delay)
+    }
+
+    /// Sends whatever is due about `door` through `notifiers`, all channels
+    /// and recipients at once and in order for each. Returns one `Delivery`
+    /// per channel that was tried, which fails if any recipient failed.
+    ///
+    /// When a channel has more than one notification waiting and it failed
+    /// to send some of them before, they are sent as a single
+    /// `Event::Missed` summary instead, so nobody gets a late "door opened"
+    /// for a door that has been closed since.
+    pub async fn deliver(&self, notifiers: &[Arc<dyn Notifier>], door: &str, now: DateTime<Utc>) -> Vec<Delivery> {
+        let mut sends = Vec::new();
+        for waiting in self.due(door, now) {
+            let channel = waiting[0].channel.clone();
+            let recipient = waiting[0].recipient.clone();
+            let ids: Vec<String> = waiting.iter().map(|pending| pending.id.clone()).collect();
+            let Some(notifier) = notifiers.iter().find(|notifier| notifier.name() == channel).cloned() else {
+                let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                eprintln!("[{}] Dropping {} queued {} notifications, {} is no longer enabled",
+                         timestamp, ids.len(), channel, channel);
+                self.finish(&channel, recipient.as_deref(), &ids, |id, channel, recipient| {
+                    Record::Dropped { id, channel, recipient }
+                });
+                continue;
+            };
+            if let Some(recipient) = &recipient
+                && !notifier.recipients().contains(recipient)
+            {
+                let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                eprintln!("[{}] Dropping {} queued {} notifications to {}, it is no longer a recipient",
+                         timestamp, ids.len(), channel, recipient);
+                self.finish(&channel, Some(recipient), &ids, |id, channel, recipient| {
+                    Record::Dropped { id, channel, recipient }
+                });
+                continue;
+            }
+            sends.push(async move {
+                let (sent, failure) = send_waiting(notifier.as_ref(), recipient.as_deref(), door, &waiting).await;
+                (channel, recipient, ids, sent, failure)
+            });
+        }
+
+        let mut results: Vec<(String, Vec<_>)> = Vec::new();
+        for (channel, recipient, ids, sent, failure) in join_all(sends).await {
+            self.delivered(&channel, recipient.as_deref(), &ids[..sent]);
+            let result = match failure {
+                None => Ok(()),
+                Some(error) => {
+                    let unsent = &ids[sent..];
+                    let to = recipient.as_ref().map(|recipient| format!(" to {}", recipient)).unwrap_or_default();
+                    let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                    match self.failed(&channel, recipient.as_deref(), unsent, &error, now) {
+                        Some(delay) => println!("[{}] Will try the {} unsent {} notifications{} again in {}",
+                                               timestamp, unsent.len(), channel, to, format_duration(delay)),
+                        None => eprintln!("[{}] Giving up on {} {} notifications{}, retrying won't help",
+                                         timestamp, unsent.len(), channel, to),
+                    }
+                    Err(error)
+                }
+            };
+            let recipient = recipient.unwrap_or_default();
+            match results.iter_mut().find(|(name, _)| *name == channel) {
+                Some((_, lanes)) => lanes.push((recipient, result)),
+                None => results.push((channel, vec![(recipient, result)])),
+            }
+        }
+        results
+            .into_iter()
+            .map(|(channel, lanes)| Delivery {
+                result: check_recipients(&channel, lanes),
+                channel,
+            })
+            .collect()
+    }
+
+    /// Takes the intents in `ids` off the queue for `channel` and
+    /// `recipient`, recording why with `record`.
+    fn finish(
+        &self,
+        channel: &str,
+        recipient: Option<&str>,
+        ids: &[String],
+        record: impl Fn(String, String, Option<String>) -> Record,
+    ) {
+        if ids.is_empty() {
+            return;
+        }
+        let records = ids
+            .iter()
+            .map(|id| record(id.clone(), channel.to_string(), recipient.map(str::to_string)))
+            .collect();
+        self.commit(self.queue.lock().unwrap(), records);
+    }
+
+    /// Applies `records` to `queue` and writes them to the file, or rewrites
+    /// the file if it is due to be compacted. The queue is unlocked before
+    /// the file is written. A failed write is logged, the notifications are
+    /// still sent from memory.
+    fn commit(&self, mut queue: MutexGuard<'_, Queue>, records: Vec<Record>) {
+        let Some(path) = &self.path else {
+            for record in records {
+                queue.apply(record);
+            }
+            return;
+        };
+        let mut lines = String::new();
+        for record in records {
+            match serde_json::to_string(&record) {
+                Ok(line) => {
+                    lines.push_str(&line);
+                    lines.push('\n');
+                }
+                Err(e) => {
+                    let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                    eprintln!("[{}] Failed to write to the outbox {}: {}", timestamp, path.display(), e);
+                }
+            }
+            queue.apply(record);
+        }
+        let compacted = (queue.pending.is_empty() || queue.finished >= OUTBOX_COMPACT_AFTER).then(|| {
+            queue.finished = 0;
+            queue.records()
+        });
+
+        let _file = self.file.lock().unwrap();
+        drop(queue);
+        let result = match compacted {
+            Some(records) => rewrite(path, &records),
+            None => append(path, &lines),
+        };
+        if let Err(e) = result {
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            eprintln!("[{}] Failed to write to the outbox {}: {}", timestamp, path.display(), e);
+        }
+    }
+}
+
+/// The id of `event` about `door`, which happened at `now`. Events about an
+/// open door or an offline sensor are told apart by when that started, and
+/// by their reminder number, others by when they happened.
+fn intent_id(door: &str, event: &Event, now: DateTime<Utc>) -> String {
+    let since = event
+        .open_for()
+        .or(event.offline_for())
+        .and_then(|duration| chrono::Duration::from_std(duration).ok())
+        .map_or(now, |duration| now - duration);
+    match event.reminder() {
+        Some(reminder) => format!("{}/{}/{}-{}", door, event.kind(), since.timestamp_millis(), reminder),
+        None => format!("{}/{}/{}", door, event.kind(), since.timestamp_millis()),
+    }
+}
+
+/// Adds `lines` to the end of the file at `path`.
+fn append(path: &Path, lines: &str) -> Result<(), Error> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(lines.as_bytes())?;
+    Ok(())
+}
+
+/// Replaces the file at `path` with one that only holds `records`.
+fn rewrite(path: &Path, records: &[Record]) -> Result<(), Error> {
+    let mut contents = String::new();
+    for record in records {
+        contents.push_str(&serde_json::to_string(record)?);
+        contents.push('\n');
+    }
+    let mut temporary = path.to_path_buf().into_os_string();
+    temporary.push(".tmp");
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+/// Sends `waiting` through `notifier`, to only `recipient` if given, as a
+/// summary if it is a backlog. Returns how many were sent and, if sending
+/// stopped early, why.
+async fn send_waiting(
+    notifier: &dyn Notifier,
+    recipient: Option<&str>,
+    door: &str,
+    waiting: &[Pending],
+) -> (usize, Option<Error>) {
+    if waiting.len() > 1 && waiting.iter().any(|pending| pending.attempts > 0) {
+        let summary = Event::Missed {
+            notifications: waiting
+                .iter()
+                .map(|pending| format!("{} {}", pending.created_at.format("%H:%M:%S UTC"), pending.event.message(door)))
+                .collect(),
+        };
+        return match send_logged(notifier, recipient, door, &summary, Utc::now()).await {
+            Ok(()) => (waiting.len(), None),
+            Err(e) => (0, Some(e)),
+        };
+    }
+
+    for (sent, pending) in waiting.iter().enumerate() {
+        if let Err(e) = send_logged(notifier, recipient, door, &pending.event, pending.created_at).await {
+            return (sent, Some(e));
+        }
+    }
+    (waiting.len(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use chrono::TimeZone;
+    use reqwest::StatusCode;
+
+    /// Fails with `error` while it is set, recording what it sends otherwise.
+    struct Flaky {
+        error: Mutex<Option<Error>>,
+        sent: Mutex<Vec<String>>,
+    }
+
+    impl Flaky {
+        fn new(error: Option<Error>) -> Arc<Self> {
+            Arc::new(Self {
+                error: Mutex::new(error),
+                sent: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Notifier for Flaky {
+        fn name(&self) -> &str {
+            "SMS"
+        }
+
+        async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+            if let Some(e) = self.error.lock().unwrap().take() {
+                return Err(e);
+            }
+            self.sent.lock().unwrap().push(event.message(door));
+            Ok(())
+        }
+    }
+
+    /// Sends to two numbers, failing once for `failing`.
+    struct Household {
+        failing: Mutex<Option<String>>,
+        sent: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Notifier for Household {
+        fn name(&self) -> &str {
+            "SMS"
+        }
+
+        async fn send(&self, _door: &str, _event: &Event) -> Result<(), Error> {
+            unreachable!("the outbox sends to each recipient")
+        }
+
+        fn recipients(&self) -> Vec<String> {
+            vec!["5550000001".to_string(), "5550000002".to_string()]
+        }
+
+        async fn send_to(&self, recipient: &str, door: &str, event: &Event, _at: DateTime<Utc>) -> Result<(), Error> {
+            let mut failing = self.failing.lock().unwrap();
+            if failing.as_deref() == Some(recipient) {
+                *failing = None;
+                return Err(unreachable());
+            }
+            self.sent.lock().unwrap().push(format!("{}: {}", recipient, event.message(door)));
+            Ok(())
+        }
+    }
+
+    fn unreachable() -> Error {
+        Error::NotifierRejected {
+            provider: "voip.ms".to_string(),
+            status: StatusCode::BAD_GATEWAY,
+            body: String::new(),
+        }
+    }
+
+    fn outbox_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("door-monitor-{}-{}.jsonl", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_add_deduplicates_by_id() {
+        let outbox = Outbox::in_memory();
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 10, 15, 0).unwrap();
+        let channels = vec![Channel::from("SMS"), Channel::from("Telegram")];
+
+        let intent = outbox.enqueue("Garage", &Event::Opened, &channels, now);
+        assert_eq!(intent.id, "Garage/door_opened/1792232100000");
+        assert!(!outbox.add(intent));
+        outbox.enqueue("Garage", &Event::Opened, &channels, now);
+        outbox.enqueue("Shed", &Event::Opened, &channels, now);
+        assert_eq!(outbox.len(), 4);
+
+        // Alerts belong to the episode that started when the door was opened
+        let later = now + chrono::Duration::minutes(5);
+        let alert = outbox.enqueue("Garage", &Event::OpenTooLong { open_for: Duration::from_secs(300), reminder: 0 }, &channels, later);
+        assert_eq!(alert.id, "Garage/open_too_long/1792232100000-0");
+        outbox.enqueue("Garage", &Event::OpenTooLong { open_for: Duration::from_secs(300), reminder: 0 }, &channels, later);
+        outbox.enqueue("Garage", &Event::OpenTooLong { open_for: Duration::from_secs(600), reminder: 1 }, &channels, later);
+        assert_eq!(outbox.len(), 8);
+
+        let due = outbox.due("Garage", later);
+        assert_eq!(due.len(), 2);
+        assert_eq!(due[0][0].channel, "SMS");
+        assert_eq!(due[0].len(), 3);
+    }
+
+    #[test]
+    fn test_replay_deduplicates_by_id() {
+        let path = outbox_path("outbox-dedup");
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 10, 15, 0).unwrap();
+        let channels = vec![Channel::from("SMS")];
+
+        let outbox = Outbox::open(&path).unwrap();
+        let intent = outbox.enqueue("Garage", &Event::Opened, &channels, now);
+        drop(outbox);
+        // Queued twice in the file, e.g. by an older version
+        let line = serde_json::to_string(&Record::Queued(intent.clone())).unwrap();
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(format!("{}\n", line).as_bytes()).unwrap();
+
+        let outbox = Outbox::open(&path).unwrap();
+        assert_eq!(outbox.len(), 1);
+        // Already queued before the restart
+        assert!(!outbox.add(intent));
+        outbox.enqueue("Garage", &Event::Opened, &channels, now);
+        assert_eq!(outbox.len(), 1);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_failed_backs_off_per_channel() {
+        let outbox = Outbox::in_memory().with_retry_policy(RetryPolicy {
+            max_retries: 0,
+            base_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(60),
+        });
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 10, 15, 0).unwrap();
+        let intent = outbox.enqueue("Garage", &Event::Opened, &["SMS".into(), "Telegram".into()], now);
+        let ids = vec![intent.id];
+
+        for attempt in 0..5 {
+            let delay = outbox.failed("SMS", None, &ids, &unreachable(), now).unwrap();
+            let longest = Duration::from_secs(10 * 2u64.pow(attempt)).min(Duration::from_secs(60));
+            assert!(delay >= longest / 2 && delay <= longest);
+        }
+        let due: Vec<String> = outbox.due("Garage", now).into_iter().map(|waiting| waiting[0].channel.clone()).collect();
+        assert_eq!(due, vec!["Telegram"]);
+        assert!(!outbox.due("Garage", now + chrono::Duration::seconds(60)).is_empty());
+
+        // Retrying won't fix a wrong password
+        let error = Error::MissingCredentials { provider: "voip.ms".to_string() };
+        assert_eq!(outbox.failed("SMS", None, &ids, &error, now), None);
+        assert_eq!(outbox.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_backlog_is_sent_as_summary() {
+        let outbox = Outbox::in_memory();
+        let sms = Flaky::new(Some(unreachable()));
+        let notifiers: Vec<Arc<dyn Notifier>> = vec![sms.clone()];
+        let channels = vec![Channel::from("SMS")];
+        let opened_at = Utc.with_ymd_and_hms(2026, 10, 17, 10, 15, 0).unwrap();
+
+        outbox.enqueue("Garage", &Event::Opened, &channels, opened_at);
+        let deliveries = outbox.deliver(&notifiers, "Garage", opened_at).await;
+        assert!(deliveries[0].result.is_err());
+        assert_eq!(outbox.len(), 1);
+
+        let closed_at = opened_at + chrono::Duration::minutes(20);
+        outbox.enqueue("Garage", &Event::Closed { open_for: Duration::from_secs(1200) }, &channels, closed_at);
+        let deliveries = outbox.deliver(&notifiers, "Garage", closed_at).await;
+        assert!(deliveries[0].result.is_ok());
+        assert!(outbox.is_empty());
+        assert_eq!(*sms.sent.lock().unwrap(), vec![
+            "2 notifications about Garage couldn't be sent at the time:\n\
+             10:15:00 UTC Garage has been opened\n\
+             10:35:00 UTC Garage is now closed after being open for 00:20:00"
+        ]);
+
+        // Without a backlog each event is sent as it is
+        outbox.enqueue("Garage", &Event::Opened, &channels, closed_at + chrono::Duration::minutes(1));
+        outbox.deliver(&notifiers, "Garage", closed_at + chrono::Duration::minutes(1)).await;
+        assert_eq!(sms.sent.lock().unwrap().last().unwrap(), "Garage has been opened");
+    }
+
+    #[tokio::test]
+    async fn test_outbox_survives_restart() {
+        let path = outbox_path("outbox-restart");
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 10, 15, 0).unwrap();
+        let channels = vec![Channel::from("SMS"), Channel::from("Telegram")];
+
+        let outbox = Outbox::open(&path).unwrap();
+        let opened = outbox.enqueue("Garage", &Event::Opened, &channels, now);
+        outbox.enqueue("Garage", &Event::OpenTooLong { open_for: Duration::from_secs(300), reminder: 0 }, &channels, now);
+        outbox.delivered("Telegram", None, std::slice::from_ref(&opened.id));
+        drop(outbox);
+        // A line cut short by a power cut
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"delivered\":{\"id\":").unwrap();
+
+        let outbox = Outbox::open(&path).unwrap();
+        assert_eq!(outbox.len(), 3);
+        // The file was compacted to what is still pending
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        let sms = Flaky::new(None);
+        let notifiers: Vec<Arc<dyn Notifier>> = vec![sms.clone()];
+        outbox.deliver(&notifiers, "Garage", now).await;
+        assert_eq!(sms.sent.lock().unwrap().len(), 1);
+        assert!(sms.sent.lock().unwrap()[0].starts_with("2 notifications about Garage"));
+        // Telegram is no longer enabled, so its notification was dropped
+        assert!(outbox.is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+
+        drop(outbox);
+        assert!(Outbox::open(&path).unwrap().is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retry_only_goes_to_failed_recipients() {
+        let path = outbox_path("outbox-recipients");
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 10, 15, 0).unwrap();
+        let sms = Arc::new(Household {
+            failing: Mutex::new(Some("5550000002".to_string())),
+            sent: Mutex::new(Vec::new()),
+        });
+        let notifiers: Vec<Arc<dyn Notifier>> = vec![sms.clone()];
+
+        let outbox = Outbox::open(&path).unwrap();
+        outbox.enqueue("Garage", &Event::Opened, &[Channel::of(sms.as_ref())], now);
+        assert_eq!(outbox.len(), 2);
+        let deliveries = outbox.deliver(&notifiers, "Garage", now).await;
+        assert!(matches!(
+            &deliveries[0].result,
+            Err(Error::RecipientsFailed { recipients: 2, failures, .. }) if failures.len() == 1
+        ));
+        assert_eq!(outbox.len(), 1);
+
+        // Only the number that didn't get it is tried again, also after a restart
+        drop(outbox);
+        let outbox = Outbox::open(&path).unwrap();
+        assert_eq!(outbox.len(), 1);
+        let deliveries = outbox.deliver(&notifiers, "Garage", now + chrono::Duration::minutes(1)).await;
+        assert!(deliveries[0].result.is_ok());
+        assert!(outbox.is_empty());
+        assert_eq!(*sms.sent.lock().unwrap(), vec![
+            "5550000001: Garage has been opened",
+            "5550000002: Garage has been opened",
+        ]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_is_compacted_while_busy() {
+        let path = outbox_path("outbox-compact");
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 10, 15, 0).unwrap();
+        let channels = vec![Channel::from("SMS")];
+
+        let outbox = Outbox::open(&path).unwrap();
+        // Never sent, so the queue is never empty
+        outbox.enqueue("Garage", &Event::Opened, &channels, now);
+        for second in 0..OUTBOX_COMPACT_AFTER as i64 * 3 {
+            let intent = outbox.enqueue("Shed", &Event::Opened, &channels, now + chrono::Duration::seconds(second));
+            outbox.delivered("SMS", None, &[intent.id]);
+        }
+
+        assert_eq!(outbox.len(), 1);
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        assert!(lines <= OUTBOX_COMPACT_AFTER * 2 + 1, "{} lines", lines);
+        drop(outbox);
+        assert_eq!(Outbox::open(&path).unwrap().len(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+}
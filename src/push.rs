@@ -0,0 +1,359 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use crate::error::Error;
+use crate::notifier::{Notifier, check_response};
+use crate::state_machine::Event;
+
+/// How urgent an event is, mapped onto each push service's own scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+impl Priority {
+    /// Alerts that need someone to act are high, routine open/close messages
+    /// normal, and all-clear messages low.
+    pub fn for_event(event: &Event) -> Self {
+        match event {
+            Event::OpenTooLong { .. }
+            | Event::SensorFailing { .. }
+            | Event::SensorOffline { .. }
+            | Event::ChannelFailing { .. } => Priority::High,
+            Event::Opened
+            | Event::Closed { .. }
+            | Event::FlappingStarted { .. }
+            | Event::Acknowledged { .. }
+            | Event::Missed { .. }
+            | Event::LimitReached { .. } => Priority::Normal,
+            Event::Templated { event, .. } => Priority::for_event(event),
+            Event::Started { .. } | Event::FlappingSettled { .. } | Event::SensorOnline { .. } => Priority::Low,
+        }
+    }
+}
+
+/// Sends events to a topic on an ntfy server.
+pub struct NtfyNotifier {
+    client: reqwest::Client,
+    server: String,
+    topic: String,
+    token: Option<String>,
+    tags: Vec<String>,
+}
+
+impl NtfyNotifier {
+    pub fn new(client: reqwest::Client, server: impl Into<String>, topic: impl Into<String>) -> Self {
+        Self {
+            client,
+            server: server.into(),
+            topic: topic.into(),
+            token: None,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Access token for protected topics.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// Tags added to every message, after the tag for the event.
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// ntfy priorities run from 1 (min) to 5 (max).
+    fn priority(event: &Event) -> u8 {
+        match Priority::for_event(event) {
+            Priority::Low => 2,
+            Priority::Normal => 3,
+            Priority::High => 4,
+        }
+    }
+
+    /// ntfy shows tags that match an emoji short code as that emoji.
+    fn event_tag(event: &Event) -> &'static str {
+        match Priority::for_event(event) {
+            Priority::Low => "white_check_mark",
+            Priority::Normal => "door",
+            Priority::High => "warning",
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for NtfyNotifier {
+    fn name(&self) -> &str {
+        "ntfy"
+    }
+
+    async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+        let url = format!("{}/{}", self.server.trim_end_matches('/'), self.topic);
+        let mut tags = vec![Self::event_tag(event).to_string()];
+        tags.extend(self.tags.iter().cloned());
+
+        let mut request = self
+            .client
+            .post(&url)
+            .header("Title", event.title(door))
+            .header("Priority", Self::priority(event).to_string())
+            .header("Tags", tags.join(","))
+            .body(event.message(door));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await.map_err(|source| Error::NotifierHttp {
+            provider: "ntfy".to_string(),
+            source,
+        })?;
+        check_response("ntfy", response).await
+    }
+}
+
+/// Sends events to a Gotify server as an application.
+pub struct GotifyNotifier {
+    client: reqwest::Client,
+    server: String,
+    app_token: String,
+}
+
+impl GotifyNotifier {
+    pub fn new(client: reqwest::Client, server: impl Into<String>, app_token: impl Into<String>) -> Self {
+        Self {
+            client,
+            server: server.into(),
+            app_token: app_token.into(),
+        }
+    }
+
+    /// Gotify priorities run from 0 to 10, the Android app makes a sound from 4
+    /// and shows a heads-up notification from 8.
+    fn priority(event: &Event) -> u8 {
+        match Priority::for_event(event) {
+            Priority::Low => 2,
+            Priority::Normal => 5,
+            Priority::High => 8,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for GotifyNotifier {
+    fn name(&self) -> &str {
+        "Gotify"
+    }
+
+    async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+        let url = format!("{}/message", self.server.trim_end_matches('/'));
+        let response = self
+            .client
+            .post(&url)
+            .header("X-Gotify-Key", &self.app_token)
+            .json(&json!({
+                "title": event.title(door),
+                "message": event.message(door),
+                "priority": Self::priority(event),
+            }))
+            .send()
+            .await
+            .map_err(|source| Error::NotifierHttp {
+                provider: "Gotify".to_string(),
+                source,
+            })?;
+        check_response("Gotify", response).await
+    }
+}
+
+const PUSHOVER_API_URL: &str = "https://api.pushover.net/1/messages.json";
+
+/// Sends events through Pushover.
+///
+/// High priority events can be sent as emergencies, which Pushover repeats
+/// every `retry` until someone acknowledges them or `expire` has passed.
+pub struct PushoverNotifier {
+    client: reqwest::Client,
+    api_url: String,
+    app_token: String,
+    user_key: String,
+    /// Retry and expire for emergency priority, `None` sends high priority instead
+    emergency: Option<(Duration, Duration)>,
+}
+
+impl PushoverNotifier {
+    pub fn new(client: reqwest::Client, app_token: impl Into<String>, user_key: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_url: PUSHOVER_API_URL.to_string(),
+            app_token: app_token.into(),
+            user_key: user_key.into(),
+            emergency: None,
+        }
+    }
+
+    /// Sends high priority events as emergencies, repeated every `retry` (at
+    /// least 30 seconds) until acknowledged or `expire` (at most 3 hours) has passed.
+    pub fn with_emergency(mut self, retry: Duration, expire: Duration) -> Self {
+        self.emergency = Some((retry.max(Duration::from_secs(30)), expire.min(Duration::from_secs(3 * 60 * 60))));
+        self
+    }
+
+    /// Sends to a different API endpoint, for testing.
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into();
+        self
+    }
+
+    /// Pushover priorities run from -2 to 2, 2 being an emergency.
+    fn priority(&self, event: &Event) -> i8 {
+        match Priority::for_event(event) {
+            Priority::Low => -1,
+            Priority::Normal => 0,
+            Priority::High if self.emergency.is_some() => 2,
+            Priority::High => 1,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for PushoverNotifier {
+    fn name(&self) -> &str {
+        "Pushover"
+    }
+
+    async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+        let priority = self.priority(event);
+        let mut params = vec![
+            ("token", self.app_token.clone()),
+            ("user", self.user_key.clone()),
+            ("title", event.title(door)),
+            ("message", event.message(door)),
+            ("priority", priority.to_string()),
+        ];
+        if let Some((retry, expire)) = self.emergency.filter(|_| priority == 2) {
+            params.push(("retry", retry.as_secs().to_string()));
+            params.push(("expire", expire.as_secs().to_string()));
+        }
+
+        let response = self
+            .client
+            .post(&self.api_url)
+            .form(&params)
+            .send()
+            .await
+            .map_err(|source| Error::NotifierHttp {
+                provider: "Pushover".to_string(),
+                source,
+            })?;
+        check_response("Pushover", response).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+
+    fn open_too_long() -> Event {
+        Event::OpenTooLong { open_for: Duration::from_secs(300), reminder: 0 }
+    }
+
+    #[test]
+    fn test_priority_for_event() {
+        assert_eq!(Priority::for_event(&open_too_long()), Priority::High);
+        assert_eq!(Priority::for_event(&Event::Opened), Priority::Normal);
+        assert_eq!(
+            Priority::for_event(&Event::SensorOnline { offline_for: Duration::from_secs(60), state: crate::door::DoorState::Closed }),
+            Priority::Low
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ntfy() {
+        let mut server = Server::new_async().await;
+        let mock = server.mock("POST", "/garage")
+            .match_header("title", "ALERT: Garage door open too long")
+            .match_header("priority", "4")
+            .match_header("tags", "warning,house")
+            .match_header("authorization", "Bearer tk_secret")
+            .match_body("ALERT: Garage door has been open for 00:05:00")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let notifier = NtfyNotifier::new(reqwest::Client::new(), format!("{}/", server.url()), "garage")
+            .with_token(Some("tk_secret".to_string()))
+            .with_tags(vec!["house".to_string()]);
+        notifier.send("Garage door", &open_too_long()).await.unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_gotify() {
+        let mut server = Server::new_async().await;
+        let mock = server.mock("POST", "/message")
+            .match_header("x-gotify-key", "AbCdEf")
+            .match_body(Matcher::Json(json!({
+                "title": "Garage door opened",
+                "message": "Garage door has been opened",
+                "priority": 5,
+            })))
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let notifier = GotifyNotifier::new(reqwest::Client::new(), server.url(), "AbCdEf");
+        notifier.send("Garage door", &Event::Opened).await.unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_pushover_emergency() {
+        let mut server = Server::new_async().await;
+        let mock = server.mock("POST", "/1/messages.json")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("token".into(), "app".into()),
+                Matcher::UrlEncoded("user".into(), "user".into()),
+                Matcher::UrlEncoded("priority".into(), "2".into()),
+                Matcher::UrlEncoded("retry".into(), "30".into()),
+                Matcher::UrlEncoded("expire".into(), "3600".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"status":1,"request":"abc"}"#)
+            .create_async()
+            .await;
+
+        let notifier = PushoverNotifier::new(reqwest::Client::new(), "app", "user")
+            .with_api_url(format!("{}/1/messages.json", server.url()))
+            .with_emergency(Duration::from_secs(10), Duration::from_secs(3600));
+        notifier.send("Garage door", &open_too_long()).await.unwrap();
+        mock.assert_async().await;
+
+        // Routine events are never emergencies
+        assert_eq!(notifier.priority(&Event::Opened), 0);
+    }
+
+    #[tokio::test]
+    async fn test_pushover_rejected() {
+        let mut server = Server::new_async().await;
+        let mock = server.mock("POST", "/")
+            .match_body(Matcher::UrlEncoded("priority".into(), "1".into()))
+            .match_request(|request| !String::from_utf8_lossy(request.body().unwrap()).contains("retry="))
+            .with_status(400)
+            .with_body(r#"{"user":"invalid","errors":["user identifier is invalid"],"status":0}"#)
+            .create_async()
+            .await;
+
+        let notifier = PushoverNotifier::new(reqwest::Client::new(), "app", "bad").with_api_url(server.url());
+        let error = notifier.send("Garage door", &open_too_long()).await.unwrap_err();
+
+        mock.assert_async().await;
+        assert!(matches!(error, Error::NotifierRejected { provider, .. } if provider == "Pushover"));
+    }
+}
@@ -0,0 +1,133 @@
+use std::future::Future;
+use std::time::Duration;
+
+use chrono::Utc;
+use rand::Rng;
+use tokio::time::sleep;
+
+use crate::error::Error;
+
+/// Retries transient sensor errors a bounded number of times, waiting an
+/// exponentially growing, jittered delay between attempts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt, 0 disables retrying
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32, base_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            base_delay,
+            max_delay: Duration::from_secs(30),
+        }
+    }
+
+    /// The delay before retry number `retry` (starting at 0): somewhere
+    /// between half and all of `base_delay * 2^retry`, capped at `max_delay`.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        let half = backoff / 2;
+        let jitter_ms = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+        half + Duration::from_millis(jitter_ms)
+    }
+
+    /// Runs `operation`, retrying transient failures. `what` names the
+    /// operation in the log, e.g. "Garage door status".
+    pub async fn run<T, F, Fut>(&self, what: &str, mut operation: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut retry = 0;
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) if retry < self.max_retries && e.is_transient() => {
+                    let delay = self.delay(retry);
+                    let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                    eprintln!("[{}] Error checking {} (attempt {} of {}): {}, retrying in {:?}",
+                             timestamp, what, retry + 1, self.max_retries + 1, e, delay);
+                    sleep(delay).await;
+                    retry += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(2, Duration::from_millis(500))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_delay_has_jitter_within_bounds() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100));
+        for _ in 0..20 {
+            let delay = policy.delay(2);
+            assert!(delay >= Duration::from_millis(200));
+            assert!(delay <= Duration::from_millis(400));
+        }
+        assert!(policy.delay(20) <= policy.max_delay);
+    }
+
+    #[tokio::test]
+    async fn test_run_retries_transient_errors() {
+        let policy = RetryPolicy::new(2, Duration::from_millis(1));
+        let attempts = AtomicU32::new(0);
+
+        let result = policy.run("test door status", || async {
+            if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(Error::SensorStatus(StatusCode::BAD_GATEWAY))
+            } else {
+                Ok(42)
+            }
+        }).await;
+
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_run_gives_up_after_max_retries() {
+        let policy = RetryPolicy::new(2, Duration::from_millis(1));
+        let attempts = AtomicU32::new(0);
+
+        let result: Result<(), _> = policy.run("test door status", || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(Error::SensorStatus(StatusCode::SERVICE_UNAVAILABLE))
+        }).await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_run_does_not_retry_permanent_errors() {
+        let policy = RetryPolicy::new(2, Duration::from_millis(1));
+        let attempts = AtomicU32::new(0);
+
+        let result: Result<(), _> = policy.run("test door status", || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(Error::SensorStatus(StatusCode::NOT_FOUND))
+        }).await;
+
+        assert_eq!(result.unwrap_err().to_string(), "HTTP error: 404 Not Found");
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
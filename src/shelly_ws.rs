@@ -0,0 +1,457 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use futures_util::{SinkExt, StreamExt};
+use rand::Rng;
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::door::{DoorSensor, DoorState, SensorCredentials, check_door_status_with_auth, sha256_hex, split_credentials};
+use crate::error::Error;
+
+/// Identifies this client to the Shelly, which only sends notifications to
+/// connections that have made a request with a `src`.
+const RPC_SOURCE: &str = "door-monitor";
+
+/// Subscribes to a Shelly Gen2 device's JSON-RPC WebSocket, e.g. `ws://192.168.1.226/rpc?id=0`.
+///
+/// The device pushes `NotifyStatus` and `NotifyEvent` frames as soon as the
+/// input changes, which `watch` forwards to the monitor. The connection is
+/// re-opened with exponential backoff whenever it drops. `read` polls
+/// `/rpc/Input.GetStatus` over HTTP for the initial state and reconciliation.
+///
+/// A device with a password answers the first request on the socket with a
+/// digest challenge, which is answered with the sensor credentials.
+pub struct ShellyWebSocketSensor {
+    client: reqwest::Client,
+    ws_url: String,
+    status_url: String,
+    input_id: u8,
+    credentials: Option<SensorCredentials>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl ShellyWebSocketSensor {
+    /// Creates a sensor from a `ws://host/rpc` URL. An optional `?id=N` query
+    /// selects the input, which defaults to `input:0`.
+    pub fn new(client: reqwest::Client, url: &str) -> Self {
+        let (url, credentials) = split_credentials(url).unwrap_or_else(|_| (url.to_string(), None));
+        let (ws_url, query) = url.split_once('?').unwrap_or((&url, ""));
+        let input_id = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == "id")
+            .and_then(|(_, value)| value.parse().ok())
+            .unwrap_or(0);
+
+        let http_base = ws_url
+            .replacen("wss://", "https://", 1)
+            .replacen("ws://", "http://", 1);
+        let http_base = http_base.trim_end_matches('/').trim_end_matches("/rpc");
+        let status_url = format!("{}/rpc/Input.GetStatus?id={}", http_base, input_id);
+
+        Self {
+            client,
+            ws_url: ws_url.to_string(),
+            status_url,
+            input_id,
+            credentials,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+
+    /// Sets how long to wait before reconnecting, doubling from `initial` up to `max`.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Sets the credentials for HTTP status reads and the WebSocket, unless
+    /// the URL already carried its own.
+    pub fn with_credentials(mut self, credentials: Option<SensorCredentials>) -> Self {
+        if self.credentials.is_none() {
+            self.credentials = credentials;
+        }
+        self
+    }
+}
+
+#[async_trait]
+impl DoorSensor for ShellyWebSocketSensor {
+    async fn read(&self) -> Result<DoorState, Error> {
+        let status = check_door_status_with_auth(&self.client, &self.status_url, self.credentials.as_ref()).await?;
+        Ok(DoorState::from(&status))
+    }
+
+    fn watch(&self, events: mpsc::Sender<DoorState>) -> bool {
+        tokio::spawn(watch_socket(
+            self.ws_url.clone(),
+            self.input_id,
+            self.credentials.clone(),
+            events,
+            self.initial_backoff,
+            self.max_backoff,
+        ));
+        true
+    }
+}
+
+/// Keeps a WebSocket open to the device and forwards every input change to
+/// `events` until the receiving monitor goes away.
+async fn watch_socket(
+    ws_url: String,
+    input_id: u8,
+    credentials: Option<SensorCredentials>,
+    events: mpsc::Sender<DoorState>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+) {
+    let mut backoff = initial_backoff;
+    let request = |auth: Option<Value>| {
+        let mut request = json!({
+            "id": 1,
+            "src": RPC_SOURCE,
+            "method": "Input.GetStatus",
+            "params": { "id": input_id },
+        });
+        if let Some(auth) = auth {
+            request["auth"] = auth;
+        }
+        Message::Text(request.to_string().into())
+    };
+
+    loop {
+        match connect_async(ws_url.as_str()).await {
+            Ok((mut socket, _)) => {
+                let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                println!("[{}] Connected to Shelly WebSocket {}", timestamp, ws_url);
+
+                if let Err(e) = socket.send(request(None)).await {
+                    eprintln!("[{}] Failed to subscribe to {}: {}", timestamp, ws_url, e);
+                }
+
+                let mut answered = false;
+                while let Some(message) = socket.next().await {
+                    match message {
+                        Ok(Message::Text(text)) => {
+                            if let Some(challenge) = auth_challenge(text.as_str()) {
+                                let auth = match (&credentials, answered) {
+                                    (Some(credentials), false) => {
+                                        rpc_auth(credentials, &challenge, rand::thread_rng().r#gen())
+                                    }
+                                    (Some(_), true) => Err(Error::SensorAuth("the password was rejected".to_string())),
+                                    (None, _) => Err(Error::SensorAuth(
+                                        "the device asks for a password, set --sensor-password".to_string(),
+                                    )),
+                                };
+                                let result = match auth {
+                                    Ok(auth) => socket.send(request(Some(auth))).await.map_err(|e| e.to_string()),
+                                    Err(e) => Err(e.to_string()),
+                                };
+                                if let Err(e) = result {
+                                    let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                                    eprintln!("[{}] Failed to authenticate to {}: {}", timestamp, ws_url, e);
+                                    break;
+                                }
+                                answered = true;
+                                continue;
+                            }
+                            let Some(door_state) = parse_frame(text.as_str(), input_id) else {
+                                continue;
+                            };
+                            // Only a device that reports its input counts as
+                            // connected, so one that keeps refusing us backs off
+                            backoff = initial_backoff;
+                            if events.send(door_state).await.is_err() {
+                                // The monitor has stopped, nobody is listening anymore
+                                return;
+                            }
+                        }
+                        Ok(Message::Close(_)) => break,
+                        Ok(_) => {}
+                        Err(e) => {
+                            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                            eprintln!("[{}] Shelly WebSocket {} error: {}", timestamp, ws_url, e);
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                eprintln!("[{}] Failed to connect to Shelly WebSocket {}: {}", timestamp, ws_url, e);
+            }
+        }
+
+        if events.is_closed() {
+            return;
+        }
+
+        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+        println!("[{}] Reconnecting to {} in {:?}", timestamp, ws_url, backoff);
+        sleep(backoff).await;
+        backoff = (backoff * 2).min(max_backoff);
+    }
+}
+
+/// The digest challenge in a Shelly's 401 error response, e.g.
+/// `{"id":1,"error":{"code":401,"message":"{\"auth_type\":\"digest\",\"nonce\":1625038762,\"nc\":1,\"realm\":\"shellyplus1-a8032ab12345\",\"algorithm\":\"SHA-256\"}"}}`.
+fn auth_challenge(text: &str) -> Option<Value> {
+    let frame: Value = serde_json::from_str(text).ok()?;
+    if frame["error"]["code"].as_i64() != Some(401) {
+        return None;
+    }
+    serde_json::from_str(frame["error"]["message"].as_str()?).ok()
+}
+
+/// The `auth` object answering a Shelly JSON-RPC digest `challenge`. Unlike
+/// HTTP digest auth, the second hash is over a fixed "dummy_method:dummy_uri".
+fn rpc_auth(credentials: &SensorCredentials, challenge: &Value, cnonce: u32) -> Result<Value, Error> {
+    if challenge["algorithm"].as_str().is_some_and(|algorithm| !algorithm.eq_ignore_ascii_case("SHA-256")) {
+        return Err(Error::SensorAuth(format!("unsupported algorithm {}", challenge["algorithm"])));
+    }
+    let realm = challenge["realm"]
+        .as_str()
+        .ok_or_else(|| Error::SensorAuth("digest challenge is missing the realm".to_string()))?;
+    let nonce = match &challenge["nonce"] {
+        Value::Number(nonce) => nonce.to_string(),
+        Value::String(nonce) => nonce.clone(),
+        _ => return Err(Error::SensorAuth("digest challenge is missing the nonce".to_string())),
+    };
+    let nc = challenge["nc"].as_u64().unwrap_or(1);
+
+    let ha1 = sha256_hex(&format!("{}:{}:{}", credentials.username, realm, credentials.password));
+    let ha2 = sha256_hex("dummy_method:dummy_uri");
+    let response = sha256_hex(&format!("{}:{}:{}:{}:auth:{}", ha1, nonce, nc, cnonce, ha2));
+    Ok(json!({
+        "realm": realm,
+        "username": credentials.username,
+        "nonce": challenge["nonce"],
+        "cnonce": cnonce,
+        "response": response,
+        "algorithm": "SHA-256",
+    }))
+}
+
+/// Extracts the state of `input:<input_id>` from a Shelly JSON-RPC frame.
+///
+/// Understands `NotifyStatus` and `NotifyEvent` notifications as well as the
+/// response to an `Input.GetStatus` request. Like the HTTP API, an input
+/// state of `true` means the door is closed.
+pub fn parse_frame(text: &str, input_id: u8) -> Option<DoorState> {
+    let frame: Value = serde_json::from_str(text).ok()?;
+    let component = format!("input:{}", input_id);
+
+    let state = match frame.get("method").and_then(Value::as_str) {
+        Some("NotifyStatus") => frame["params"][&component]["state"].as_bool(),
+        Some("NotifyEvent") => frame["params"]["events"]
+            .as_array()?
+            .iter()
+            .rev()
+            .filter(|event| event["component"].as_str() == Some(component.as_str()))
+            .find_map(|event| event["state"].as_bool()),
+        Some(_) => None,
+        None => {
+            let result = frame.get("result")?;
+            if result["id"].as_u64() == Some(u64::from(input_id)) {
+                result["state"].as_bool()
+            } else {
+                None
+            }
+        }
+    }?;
+
+    Some(DoorState::from_closed(state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    #[test]
+    fn test_parse_notify_status() {
+        let frame = r#"{"src":"shellyplus1-a8032ab12345","dst":"door-monitor","method":"NotifyStatus","params":{"ts":1719586215.12,"input:0":{"id":0,"state":false}}}"#;
+        assert_eq!(parse_frame(frame, 0), Some(DoorState::Open));
+
+        let frame = r#"{"method":"NotifyStatus","params":{"ts":1719586215.12,"input:0":{"id":0,"state":true}}}"#;
+        assert_eq!(parse_frame(frame, 0), Some(DoorState::Closed));
+    }
+
+    #[test]
+    fn test_parse_notify_status_other_component() {
+        let frame = r#"{"method":"NotifyStatus","params":{"ts":1719586215.12,"switch:0":{"id":0,"output":true}}}"#;
+        assert_eq!(parse_frame(frame, 0), None);
+
+        let frame = r#"{"method":"NotifyStatus","params":{"ts":1719586215.12,"input:1":{"id":1,"state":true}}}"#;
+        assert_eq!(parse_frame(frame, 0), None);
+        assert_eq!(parse_frame(frame, 1), Some(DoorState::Closed));
+    }
+
+    #[test]
+    fn test_parse_notify_event() {
+        let frame = r#"{"method":"NotifyEvent","params":{"ts":1719586215.12,"events":[{"component":"input:0","id":0,"event":"toggle","state":true,"ts":1719586215.12}]}}"#;
+        assert_eq!(parse_frame(frame, 0), Some(DoorState::Closed));
+
+        // Button events carry no state
+        let frame = r#"{"method":"NotifyEvent","params":{"ts":1719586215.12,"events":[{"component":"input:0","id":0,"event":"btn_down","ts":1719586215.12}]}}"#;
+        assert_eq!(parse_frame(frame, 0), None);
+    }
+
+    #[test]
+    fn test_parse_get_status_response() {
+        let frame = r#"{"id":1,"src":"shellyplus1-a8032ab12345","dst":"door-monitor","result":{"id":0,"state":false}}"#;
+        assert_eq!(parse_frame(frame, 0), Some(DoorState::Open));
+        assert_eq!(parse_frame(frame, 1), None);
+    }
+
+    #[test]
+    fn test_parse_invalid_frame() {
+        assert_eq!(parse_frame("not json", 0), None);
+        assert_eq!(parse_frame(r#"{"id":1,"error":{"code":-103}}"#, 0), None);
+    }
+
+    #[test]
+    fn test_new_derives_status_url() {
+        let sensor = ShellyWebSocketSensor::new(reqwest::Client::new(), "ws://192.168.1.226/rpc?id=1");
+        assert_eq!(sensor.ws_url, "ws://192.168.1.226/rpc");
+        assert_eq!(sensor.input_id, 1);
+        assert_eq!(sensor.status_url, "http://192.168.1.226/rpc/Input.GetStatus?id=1");
+
+        let sensor = ShellyWebSocketSensor::new(reqwest::Client::new(), "wss://door.example.com/rpc");
+        assert_eq!(sensor.input_id, 0);
+        assert_eq!(sensor.status_url, "https://door.example.com/rpc/Input.GetStatus?id=0");
+    }
+
+    #[tokio::test]
+    async fn test_read_polls_input_status_over_http() {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let mock = server.mock("GET", "/rpc/Input.GetStatus")
+            .match_query(Matcher::UrlEncoded("id".into(), "0".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"id":0,"state":true}"#)
+            .create_async()
+            .await;
+
+        let ws_url = format!("{}/rpc", server.url().replacen("http://", "ws://", 1));
+        let sensor = ShellyWebSocketSensor::new(reqwest::Client::new(), &ws_url);
+
+        assert_eq!(sensor.read().await.unwrap(), DoorState::Closed);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_watch_forwards_notifications() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}/rpc", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(stream).await.unwrap();
+
+            // The sensor subscribes by asking for the input status
+            let request = socket.next().await.unwrap().unwrap();
+            let request: Value = serde_json::from_str(request.to_text().unwrap()).unwrap();
+            assert_eq!(request["method"], "Input.GetStatus");
+            assert_eq!(request["src"], RPC_SOURCE);
+
+            socket.send(Message::Text(r#"{"id":1,"result":{"id":0,"state":true}}"#.into())).await.unwrap();
+            socket.send(Message::Text(r#"{"method":"NotifyStatus","params":{"input:0":{"id":0,"state":false}}}"#.into())).await.unwrap();
+            // Keep the connection open until the test is done
+            while socket.next().await.is_some() {}
+        });
+
+        let (sender, mut receiver) = mpsc::channel(8);
+        let sensor = ShellyWebSocketSensor::new(reqwest::Client::new(), &ws_url);
+        assert!(sensor.watch(sender));
+
+        assert_eq!(receiver.recv().await, Some(DoorState::Closed));
+        assert_eq!(receiver.recv().await, Some(DoorState::Open));
+    }
+
+    #[tokio::test]
+    async fn test_watch_reconnects_after_disconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}/rpc", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            // First connection drops straight away
+            let (stream, _) = listener.accept().await.unwrap();
+            let socket = accept_async(stream).await.unwrap();
+            drop(socket);
+
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(stream).await.unwrap();
+            socket.send(Message::Text(r#"{"method":"NotifyEvent","params":{"events":[{"component":"input:0","event":"toggle","state":false}]}}"#.into())).await.unwrap();
+            while socket.next().await.is_some() {}
+        });
+
+        let (sender, mut receiver) = mpsc::channel(8);
+        let sensor = ShellyWebSocketSensor::new(reqwest::Client::new(), &ws_url)
+            .with_backoff(Duration::from_millis(10), Duration::from_millis(50));
+        assert!(sensor.watch(sender));
+
+        let door_state = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap();
+        assert_eq!(door_state, Some(DoorState::Open));
+    }
+
+    #[tokio::test]
+    async fn test_watch_answers_digest_challenge() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://admin:s3cret@{}/rpc", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(stream).await.unwrap();
+
+            let request = socket.next().await.unwrap().unwrap();
+            assert!(!request.to_text().unwrap().contains("auth"));
+            let challenge = r#"{"auth_type":"digest","nonce":1625038762,"nc":1,"realm":"shellyplus1-a8032ab12345","algorithm":"SHA-256"}"#;
+            let error = json!({"id": 1, "error": {"code": 401, "message": challenge}});
+            socket.send(Message::Text(error.to_string().into())).await.unwrap();
+
+            let request = socket.next().await.unwrap().unwrap();
+            let request: Value = serde_json::from_str(request.to_text().unwrap()).unwrap();
+            let auth = &request["auth"];
+            assert_eq!(auth["username"], "admin");
+            assert_eq!(auth["nonce"], 1625038762);
+            let ha1 = sha256_hex("admin:shellyplus1-a8032ab12345:s3cret");
+            let ha2 = sha256_hex("dummy_method:dummy_uri");
+            let expected = sha256_hex(&format!("{}:1625038762:1:{}:auth:{}", ha1, auth["cnonce"], ha2));
+            assert_eq!(auth["response"], expected.as_str());
+
+            socket.send(Message::Text(r#"{"id":1,"result":{"id":0,"state":false}}"#.into())).await.unwrap();
+            while socket.next().await.is_some() {}
+        });
+
+        let (sender, mut receiver) = mpsc::channel(8);
+        let sensor = ShellyWebSocketSensor::new(reqwest::Client::new(), &ws_url);
+        assert!(sensor.watch(sender));
+
+        let door_state = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap();
+        assert_eq!(door_state, Some(DoorState::Open));
+    }
+
+    #[test]
+    fn test_auth_challenge() {
+        let frame = r#"{"id":1,"src":"shellyplus1-a8032ab12345","error":{"code":401,"message":"{\"auth_type\": \"digest\", \"nonce\": 1625038762, \"realm\": \"shellyplus1-a8032ab12345\", \"algorithm\": \"SHA-256\"}"}}"#;
+        assert_eq!(auth_challenge(frame).unwrap()["nonce"], 1625038762);
+        assert!(auth_challenge(r#"{"id":1,"error":{"code":-103,"message":"Invalid argument"}}"#).is_none());
+        assert!(auth_challenge(r#"{"id":1,"result":{"id":0,"state":true}}"#).is_none());
+
+        let credentials = SensorCredentials { username: "admin".to_string(), password: "s3cret".to_string() };
+        let challenge = json!({"nonce": 1625038762, "realm": "shellyplus1-a8032ab12345", "algorithm": "MD5"});
+        assert!(rpc_auth(&credentials, &challenge, 1).is_err());
+    }
+}
@@ -1,26 +1,120 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
+use serde::Deserialize;
+
 use crate::config::Args;
+use crate::error::Error;
+use crate::monitor::{DoorHandle, acknowledge_doors};
+use crate::notifier::{Notifier, check_recipients};
+use crate::state_machine::Event;
+
+const PROVIDER: &str = "voip.ms";
+
+/// How often `SmsReplies` checks for new replies.
+pub const SMS_REPLY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Turns the `status` in a voip.ms response into an error unless it is
+/// "success". voip.ms answers HTTP 200 whether or not the request worked.
+pub fn check_status(status: &str) -> Result<(), Error> {
+    let (description, transient) = match status {
+        "success" => return Ok(()),
+        "invalid_credentials" => ("the API username or password is wrong", false),
+        "missing_credentials" => ("no API username or password was sent", false),
+        "ip_not_enabled" => ("this IP address is not enabled for the API", false),
+        "api_not_enabled" => ("the API is not enabled on this account", false),
+        "invalid_did" | "missing_did" => ("the from number is not an SMS enabled DID on this account", false),
+        "invalid_dst" | "missing_dst" => ("the to number is not valid", false),
+        "missing_message" | "message_empty" => ("the message was empty", false),
+        "invalid_sms" | "sms_toolong" => ("the message is too long or not valid", false),
+        "limit_reached" => ("too many requests, try again later", true),
+        "sms_failed" => ("voip.ms couldn't deliver the message", true),
+        _ => ("unknown status", false),
+    };
+    Err(Error::NotifierStatus {
+        provider: PROVIDER.to_string(),
+        status: status.to_string(),
+        description: description.to_string(),
+        transient,
+    })
+}
+
+/// A reply starting with one of these acknowledges the open door alert.
+const ACKNOWLEDGE_WORDS: [&str; 4] = ["ack", "ok", "on it", "yes"];
+
+/// Sends events as text messages through voip.ms.
+pub struct SmsNotifier {
+    client: reqwest::Client,
+    args: Args,
+}
+
+impl SmsNotifier {
+    pub fn new(client: reqwest::Client, args: Args) -> Self {
+        Self { client, args }
+    }
+}
+
+#[async_trait]
+impl Notifier for SmsNotifier {
+    fn name(&self) -> &str {
+        "SMS"
+    }
+
+    async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+        send_sms(&self.client, &self.args, &event.message(door)).await
+    }
+
+    fn recipients(&self) -> Vec<String> {
+        self.args.sms_to_phone_numbers.clone()
+    }
 
+    async fn send_to(&self, recipient: &str, door: &str, event: &Event, _at: DateTime<Utc>) -> Result<(), Error> {
+        send_sms_to(&self.client, &self.args, recipient, &event.message(door)).await
+    }
+}
+
+/// Sends `message` to every configured number at once. Fails if any number
+/// didn't get it, with the error for each one that failed.
 pub async fn send_sms(
     client: &reqwest::Client,
     args: &Args,
     message: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), Error> {
     // Debug print all available arguments
     println!("SMS Function Args Debug:");
     println!("  sms_api_username: {:?}", args.sms_api_username);
     println!("  sms_api_password: {:?}", args.sms_api_password.as_ref().map(|_| "[REDACTED]"));
     println!("  sms_from_phone_number: {:?}", args.sms_from_phone_number);
-    println!("  sms_to_phone_number: {:?}", args.sms_to_phone_number);
+    println!("  sms_to_phone_numbers: {:?}", args.sms_to_phone_numbers);
     println!("  message: {:?}", message);
 
-    if let (Some(username), Some(password), Some(from), Some(to)) = (
+    if args.sms_to_phone_numbers.is_empty() {
+        println!("SMS args not supplied");
+        return Err(Error::MissingCredentials { provider: PROVIDER.to_string() });
+    }
+    let sends = args.sms_to_phone_numbers.iter().map(|to| async move {
+        (to.clone(), send_sms_to(client, args, to, message).await)
+    });
+    check_recipients(PROVIDER, join_all(sends).await)
+}
+
+/// Sends `message` to one number.
+pub async fn send_sms_to(
+    client: &reqwest::Client,
+    args: &Args,
+    to: &str,
+    message: &str,
+) -> Result<(), Error> {
+    if let (Some(username), Some(password), Some(from)) = (
         &args.sms_api_username,
         &args.sms_api_password,
         &args.sms_from_phone_number,
-        &args.sms_to_phone_number,
     ) {
         let uri = format!(
-            "https://voip.ms/api/v1/rest.php?api_username={}&api_password={}&method=sendSMS&did={}&dst={}&message={}",
+            "{}?api_username={}&api_password={}&method=sendSMS&did={}&dst={}&message={}",
+            args.sms_api_url,
             urlencoding::encode(username),
             urlencoding::encode(password),
             urlencoding::encode(from),
@@ -28,20 +122,332 @@ pub async fn send_sms(
             urlencoding::encode(message)
         );
 
-        println!("Voip URI: {}", uri);
-
-        let response = client.get(&uri).send().await?;
+        let response = client.get(&uri).send().await.map_err(|source| Error::NotifierHttp {
+            provider: PROVIDER.to_string(),
+            source,
+        })?;
 
         println!("SMS Response: {}", response.status().as_str());
         
-        if response.status().is_success() {
-            println!("SMS sent successfully: {}", message);
-        } else {
-            eprintln!("Failed to send SMS: HTTP {}", response.status());
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(Error::NotifierRejected {
+                provider: PROVIDER.to_string(),
+                status,
+                body,
+            });
         }
+
+        let Some(api_status) = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|response| response["status"].as_str().map(str::to_string))
+        else {
+            return Err(Error::NotifierStatus {
+                provider: PROVIDER.to_string(),
+                status: "invalid_response".to_string(),
+                description: format!("no status in response: {}", body),
+                transient: false,
+            });
+        };
+        check_status(&api_status)?;
+        println!("SMS sent successfully: {}", message);
+        Ok(())
     } else {
         println!("SMS args not supplied");
+        Err(Error::MissingCredentials { provider: PROVIDER.to_string() })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ReceivedMessages {
+    status: String,
+    #[serde(default)]
+    sms: Vec<ReceivedMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReceivedMessage {
+    id: String,
+    contact: String,
+    message: String,
+}
+
+/// Checks voip.ms for text messages sent back to the alert number, and
+/// acknowledges open door alerts when someone replies e.g. "ACK" or "on it".
+///
+/// A reply that names a door only acknowledges that door's alert. Messages
+/// that were already there when it started are ignored.
+pub struct SmsReplies {
+    client: reqwest::Client,
+    api_url: String,
+    username: String,
+    password: String,
+    /// Our number, the one alerts are sent from
+    did: String,
+    /// The numbers alerts are sent to, the only ones replies are taken from
+    contacts: Vec<String>,
+    doors: Vec<DoorHandle>,
+    /// The newest message seen so far, `None` until the first check
+    last_id: Option<u64>,
+}
+
+impl SmsReplies {
+    /// Checks for replies if `--sms-replies` is set, SMS is on and the voip.ms
+    /// credentials and numbers are all given.
+    pub fn from_args(client: reqwest::Client, args: &Args, doors: Vec<DoorHandle>) -> Option<Self> {
+        if !args.sms_replies || args.sms_off {
+            return None;
+        }
+        Some(Self {
+            client,
+            api_url: args.sms_api_url.clone(),
+            username: args.sms_api_username.clone()?,
+            password: args.sms_api_password.clone()?,
+            did: args.sms_from_phone_number.clone()?,
+            contacts: Some(args.sms_to_phone_numbers.clone()).filter(|numbers| !numbers.is_empty())?,
+            doors,
+            last_id: None,
+        })
+    }
+
+    /// Checks for replies every `SMS_REPLY_INTERVAL` until the process exits.
+    pub async fn run(mut self) {
+        loop {
+            if let Err(e) = self.poll_once().await {
+                let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                eprintln!("[{}] Failed to check for SMS replies: {}", timestamp, e);
+            }
+            tokio::time::sleep(SMS_REPLY_INTERVAL).await;
+        }
+    }
+
+    /// Fetches the messages received since yesterday and acts on new
+    /// replies. Returns the doors that were acknowledged.
+    pub async fn poll_once(&mut self) -> Result<Vec<String>, Error> {
+        let since = (Utc::now() - chrono::Duration::days(1)).format("%Y-%m-%d").to_string();
+        let response = self
+            .client
+            .get(&self.api_url)
+            .query(&[
+                ("api_username", self.username.as_str()),
+                ("api_password", self.password.as_str()),
+                ("method", "getSMS"),
+                ("type", "1"),
+                ("did", self.did.as_str()),
+                ("from", since.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|source| Error::NotifierHttp {
+                provider: PROVIDER.to_string(),
+                source,
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::NotifierRejected {
+                provider: PROVIDER.to_string(),
+                status,
+                body,
+            });
+        }
+        let received: ReceivedMessages = response.json().await.map_err(|source| Error::NotifierHttp {
+            provider: PROVIDER.to_string(),
+            source,
+        })?;
+        if received.status != "no_sms" {
+            check_status(&received.status)?;
+        }
+
+        let mut messages: Vec<(u64, ReceivedMessage)> = received
+            .sms
+            .into_iter()
+            .filter(|sms| self.contacts.contains(&sms.contact))
+            .filter_map(|sms| Some((sms.id.parse().ok()?, sms)))
+            .collect();
+        messages.sort_by_key(|(id, _)| *id);
+
+        let Some(last_id) = self.last_id else {
+            // Replies from before we started were for alerts that are long gone
+            self.last_id = Some(messages.last().map_or(0, |(id, _)| *id));
+            return Ok(Vec::new());
+        };
+
+        let mut acknowledged = Vec::new();
+        for (id, sms) in messages.into_iter().filter(|(id, _)| *id > last_id) {
+            self.last_id = Some(id);
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            println!("[{}] SMS reply from {}: {}", timestamp, sms.contact, sms.message);
+
+            let text = sms.message.trim().to_lowercase();
+            if !ACKNOWLEDGE_WORDS.iter().any(|word| text.starts_with(word)) {
+                continue;
+            }
+            let door = self
+                .doors
+                .iter()
+                .map(DoorHandle::door)
+                .find(|door| text.contains(&door.to_lowercase()));
+            let by = format!("SMS reply from {}", sms.contact);
+            acknowledged.extend(acknowledge_doors(&self.doors, door.as_deref(), &by));
+        }
+        Ok(acknowledged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_send_sms_without_credentials() {
+        let args = Args::try_parse_from(["test", "--api-url", "http://test.com"]).unwrap();
+        let result = send_sms(&reqwest::Client::new(), &args, "Door has been opened").await;
+
+        assert!(matches!(result, Err(Error::MissingCredentials { provider }) if provider == "voip.ms"));
+    }
+
+    #[tokio::test]
+    async fn test_send_sms_checks_api_status() {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let args = Args::try_parse_from([
+            "test", "--sms-api-url", &format!("{}/api/v1/rest.php", server.url()),
+            "--sms-api-username", "user", "--sms-api-password", "wrong",
+            "--sms-from-phone-number", "5551234567", "--sms-to-phone-number", "5559876543",
+        ]).unwrap();
+        let client = reqwest::Client::new();
+
+        let mock = server.mock("GET", "/api/v1/rest.php")
+            .match_query(Matcher::UrlEncoded("method".into(), "sendSMS".into()))
+            .with_status(200)
+            .with_body(r#"{"status":"invalid_credentials"}"#)
+            .create_async()
+            .await;
+        let error = send_sms(&client, &args, "Door has been opened").await.unwrap_err();
+        mock.assert_async().await;
+        assert!(!error.is_transient());
+        assert_eq!(
+            error.to_string(),
+            "voip.ms refused the message: invalid_credentials (the API username or password is wrong)"
+        );
+        mock.remove_async().await;
+
+        server.mock("GET", "/api/v1/rest.php")
+            .match_query(Matcher::Any)
+            .with_body(r#"{"status":"success","sms":"23434"}"#)
+            .create_async()
+            .await;
+        send_sms(&client, &args, "Door has been opened").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_send_sms_to_every_number() {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let delivered = server.mock("GET", "/")
+            .match_query(Matcher::Regex("dst=555000000[12]".to_string()))
+            .with_body(r#"{"status":"success","sms":"1"}"#)
+            .expect(2)
+            .create_async()
+            .await;
+        let refused = server.mock("GET", "/")
+            .match_query(Matcher::UrlEncoded("dst".into(), "123".into()))
+            .with_body(r#"{"status":"invalid_dst"}"#)
+            .create_async()
+            .await;
+
+        let args = Args::try_parse_from([
+            "test", "--sms-api-url", &server.url(),
+            "--sms-api-username", "user", "--sms-api-password", "secret", "--sms-from-phone-number", "5551234567",
+            "--sms-to-phone-number", "5550000001", "--sms-to-phone-number", "123", "--sms-to-phone-number", "5550000002",
+        ]).unwrap();
+        let error = send_sms(&reqwest::Client::new(), &args, "Door has been opened").await.unwrap_err();
+
+        delivered.assert_async().await;
+        refused.assert_async().await;
+        assert_eq!(
+            error.to_string(),
+            "voip.ms failed for 1 of 3 recipients: 123: voip.ms refused the message: invalid_dst (the to number is not valid)"
+        );
+    }
+
+    #[test]
+    fn test_check_status() {
+        assert!(check_status("success").is_ok());
+        assert!(matches!(
+            check_status("missing_message"),
+            Err(Error::NotifierStatus { transient: false, .. })
+        ));
+        assert!(check_status("limit_reached").unwrap_err().is_transient());
+        assert!(check_status("something_new").unwrap_err().to_string().contains("unknown status"));
+    }
+
+    #[tokio::test]
+    async fn test_sms_reply_acknowledges_alert() {
+        use crate::door::DoorState;
+        use crate::state_machine::{AlertPolicy, DoorStateMachine};
+        use mockito::{Matcher, Server};
+        use std::sync::{Arc, Mutex};
+        use tokio::sync::mpsc;
+
+        let mut server = Server::new_async().await;
+        let query = Matcher::AllOf(vec![
+            Matcher::UrlEncoded("method".into(), "getSMS".into()),
+            Matcher::UrlEncoded("type".into(), "1".into()),
+            Matcher::UrlEncoded("did".into(), "5551234567".into()),
+        ]);
+        let before = server.mock("GET", "/api/v1/rest.php")
+            .match_query(query.clone())
+            .with_body(r#"{"status":"success","sms":[
+                {"id":"100","date":"2025-06-28 09:00:00","type":"1","did":"5551234567","contact":"5559876543","message":"ok"}
+            ]}"#)
+            .create_async()
+            .await;
+
+        let args = Args::try_parse_from([
+            "test", "--sms-replies", "--sms-api-url", &format!("{}/api/v1/rest.php", server.url()),
+            "--sms-api-username", "user", "--sms-api-password", "secret",
+            "--sms-from-phone-number", "5551234567", "--sms-to-phone-number", "5559876543",
+        ]).unwrap();
+        let start = Instant::now() - Duration::from_secs(60);
+        let mut garage = DoorStateMachine::new("Garage", AlertPolicy::default());
+        garage.start(DoorState::Open, start);
+        garage.tick(Instant::now());
+        let mut shed = DoorStateMachine::new("Shed", AlertPolicy::default());
+        shed.start(DoorState::Open, start);
+        shed.tick(Instant::now());
+        let (sender, mut notify) = mpsc::channel(8);
+        let doors = vec![
+            DoorHandle::new(Arc::new(Mutex::new(garage)), sender.clone()),
+            DoorHandle::new(Arc::new(Mutex::new(shed)), sender),
+        ];
+        let mut replies = SmsReplies::from_args(reqwest::Client::new(), &args, doors).unwrap();
+
+        // The reply that was already there is old news
+        assert!(replies.poll_once().await.unwrap().is_empty());
+        before.assert_async().await;
+        before.remove_async().await;
+
+        server.mock("GET", "/api/v1/rest.php")
+            .match_query(query)
+            .with_body(r#"{"status":"success","sms":[
+                {"id":"102","date":"2025-06-28 10:01:00","type":"1","did":"5551234567","contact":"5559876543","message":"On it, closing the shed"},
+                {"id":"101","date":"2025-06-28 10:00:00","type":"1","did":"5551234567","contact":"5559876543","message":"what?"},
+                {"id":"103","date":"2025-06-28 10:02:00","type":"1","did":"5551234567","contact":"5550000000","message":"ok"},
+                {"id":"100","date":"2025-06-28 09:00:00","type":"1","did":"5551234567","contact":"5559876543","message":"ok"}
+            ]}"#)
+            .create_async()
+            .await;
+        assert_eq!(replies.poll_once().await.unwrap(), vec!["Shed"]);
+        assert!(matches!(notify.try_recv(), Ok(Event::Acknowledged { by, .. }) if by == "SMS reply from 5559876543"));
+        assert!(notify.try_recv().is_err());
+        // The reply from a number alerts don't go to was left alone
+        assert_eq!(replies.last_id, Some(102));
     }
-    
-    Ok(())
 }
@@ -0,0 +1,1416 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Args;
+use crate::debounce::{Debouncer, FlapDetector};
+use crate::door::DoorState;
+use crate::error::Error;
+use crate::utils::format_duration;
+
+/// Open too long reminders: 5, 15, 30, 60 minutes, then every 60 minutes
+pub const OPEN_REMINDER_INTERVALS: [Duration; 4] = [
+    Duration::from_secs(5 * 60),   // 5 minutes
+    Duration::from_secs(15 * 60),  // 15 minutes
+    Duration::from_secs(30 * 60),  // 30 minutes
+    Duration::from_secs(60 * 60),  // 60 minutes
+];
+pub const OPEN_REMINDER_REPEAT: Duration = Duration::from_secs(60 * 60);
+
+/// Sensor offline reminders: 30 minutes, 1 hour, 2 hours, then every 4 hours
+pub const OFFLINE_REMINDER_INTERVALS: [Duration; 3] = [
+    Duration::from_secs(30 * 60),      // 30 minutes
+    Duration::from_secs(60 * 60),      // 1 hour
+    Duration::from_secs(2 * 60 * 60),  // 2 hours
+];
+pub const OFFLINE_REMINDER_REPEAT: Duration = Duration::from_secs(4 * 60 * 60);
+
+/// How many recent events `MonitorState::history` keeps.
+pub const HISTORY_LEN: usize = 50;
+
+pub struct MonitorState {
+    pub door_opened_time: Option<Instant>,
+    pub door_closed_time: Option<Instant>,
+    pub last_door_state: Option<bool>,
+    /// The latest raw reading, which may not have been accepted by the debouncer yet
+    pub last_reading: Option<bool>,
+    pub sms_sent: bool,
+    pub sms_backoff_index: usize,
+    pub last_sms_time: Option<Instant>,
+    pub debouncer: Debouncer,
+    pub flap_detector: FlapDetector,
+    pub consecutive_sensor_failures: u32,
+    pub last_good_reading: Option<Instant>,
+    /// When the current run of sensor failures started
+    pub sensor_failing_since: Option<Instant>,
+    pub sensor_offline_alerts_sent: usize,
+    pub last_sensor_offline_alert: Option<Instant>,
+    /// The most recent events, oldest first, whether or not they were sent
+    pub history: VecDeque<(Instant, Event)>,
+    /// Notifications are held back until then
+    pub muted_until: Option<Instant>,
+    /// Who acknowledged the open door alert, and when. Cleared when the door closes
+    pub acknowledged: Option<(String, Instant)>,
+}
+
+impl MonitorState {
+    pub fn new() -> Self {
+        Self {
+            door_opened_time: None,
+            door_closed_time: None,
+            last_door_state: None,
+            last_reading: None,
+            sms_sent: false,
+            sms_backoff_index: 0,
+            last_sms_time: None,
+            debouncer: Debouncer::default(),
+            flap_detector: FlapDetector::default(),
+            consecutive_sensor_failures: 0,
+            last_good_reading: None,
+            sensor_failing_since: None,
+            sensor_offline_alerts_sent: 0,
+            last_sensor_offline_alert: None,
+            history: VecDeque::new(),
+            muted_until: None,
+            acknowledged: None,
+        }
+    }
+
+    /// How long the sensor has been unreachable at `now`, or `None` if the last reading succeeded.
+    pub fn sensor_offline_for(&self, now: Instant) -> Option<Duration> {
+        if self.consecutive_sensor_failures == 0 {
+            return None;
+        }
+        self.last_good_reading
+            .or(self.sensor_failing_since)
+            .map(|since| now.saturating_duration_since(since))
+    }
+
+    pub fn reset_sms_state(&mut self) {
+        self.sms_sent = false;
+        self.sms_backoff_index = 0;
+        self.last_sms_time = None;
+        self.acknowledged = None;
+    }
+}
+
+impl Default for MonitorState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Something people should be told about. The state machine only decides
+/// that a notification is due, sending it is up to the caller.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    /// The monitor started and read the door for the first time
+    Started { state: DoorState },
+    Opened,
+    Closed { open_for: Duration },
+    /// The door has been open longer than the threshold. `reminder` is 0 for
+    /// the first alert and counts up with every reminder after it.
+    OpenTooLong { open_for: Duration, reminder: usize },
+    FlappingStarted { transitions: usize, window: Duration },
+    FlappingSettled { state: DoorState },
+    /// The sensor returned an error that won't go away by itself
+    SensorFailing { error: String },
+    SensorOffline { offline_for: Duration, failures: u32, reminder: usize },
+    SensorOnline { offline_for: Duration, state: DoorState },
+    /// Someone has seen the open door alert and is dealing with it
+    Acknowledged { by: String, open_for: Duration },
+    /// A notification channel keeps refusing messages, sent through the
+    /// other channels so a broken one doesn't go unnoticed
+    ChannelFailing { channel: String, error: String },
+    /// Notifications that couldn't be sent at the time, sent together once
+    /// the channel works again. Each one starts with when it was due.
+    Missed { notifications: Vec<String> },
+    /// A channel reached its rate limit or quota and holds back messages
+    /// until it resets, sent through a cheaper channel
+    LimitReached { channel: String, limit: String },
+    /// `event` with its message replaced by a user's template, see
+    /// `templates::Templates`
+    Templated { event: Box<Event>, message: String },
+}
+
+impl Event {
+    /// Short name used in logs, e.g. "Sending door opened SMS...".
+    pub fn label(&self) -> &'static str {
+        match self {
+            Event::Started { .. } => "initial status",
+            Event::Opened => "door opened",
+            Event::Closed { .. } => "door closed",
+            Event::OpenTooLong { .. } => "open too long",
+            Event::FlappingStarted { .. } => "sensor flapping",
+            Event::FlappingSettled { .. } => "sensor settled",
+            Event::SensorFailing { .. } => "sensor error",
+            Event::SensorOffline { .. } => "sensor offline",
+            Event::SensorOnline { .. } => "sensor online",
+            Event::Acknowledged { .. } => "acknowledgement",
+            Event::ChannelFailing { .. } => "channel failing",
+            Event::Missed { .. } => "missed notifications",
+            Event::LimitReached { .. } => "limit reached",
+            Event::Templated { event, .. } => event.label(),
+        }
+    }
+
+    /// Machine-readable event type, e.g. "door_opened".
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::Started { .. } => "started",
+            Event::Opened => "door_opened",
+            Event::Closed { .. } => "door_closed",
+            Event::OpenTooLong { .. } => "open_too_long",
+            Event::FlappingStarted { .. } => "sensor_flapping",
+            Event::FlappingSettled { .. } => "sensor_settled",
+            Event::SensorFailing { .. } => "sensor_error",
+            Event::SensorOffline { .. } => "sensor_offline",
+            Event::SensorOnline { .. } => "sensor_online",
+            Event::Acknowledged { .. } => "acknowledged",
+            Event::ChannelFailing { .. } => "channel_failing",
+            Event::Missed { .. } => "missed",
+            Event::LimitReached { .. } => "limit_reached",
+            Event::Templated { event, .. } => event.kind(),
+        }
+    }
+
+    /// The door state this event reports, if it reports one.
+    pub fn door_state(&self) -> Option<DoorState> {
+        match self {
+            Event::Started { state } | Event::FlappingSettled { state } | Event::SensorOnline { state, .. } => {
+                Some(*state)
+            }
+            Event::Opened | Event::OpenTooLong { .. } | Event::Acknowledged { .. } => Some(DoorState::Open),
+            Event::Closed { .. } => Some(DoorState::Closed),
+            Event::FlappingStarted { .. }
+            | Event::SensorFailing { .. }
+            | Event::SensorOffline { .. }
+            | Event::ChannelFailing { .. }
+            | Event::Missed { .. }
+            | Event::LimitReached { .. } => None,
+            Event::Templated { event, .. } => event.door_state(),
+        }
+    }
+
+    /// How long the door has been, or was, open.
+    pub fn open_for(&self) -> Option<Duration> {
+        match self {
+            Event::Closed { open_for } | Event::OpenTooLong { open_for, .. } | Event::Acknowledged { open_for, .. } => {
+                Some(*open_for)
+            }
+            Event::Templated { event, .. } => event.open_for(),
+            _ => None,
+        }
+    }
+
+    /// The event as the state machine raised it, without a template.
+    pub fn base(&self) -> &Event {
+        match self {
+            Event::Templated { event, .. } => event.base(),
+            _ => self,
+        }
+    }
+
+    /// How long the sensor has been, or was, unreachable.
+    pub fn offline_for(&self) -> Option<Duration> {
+        match self {
+            Event::SensorOffline { offline_for, .. } | Event::SensorOnline { offline_for, .. } => Some(*offline_for),
+            Event::Templated { event, .. } => event.offline_for(),
+            _ => None,
+        }
+    }
+
+    /// Which reminder this is, 0 for the first alert.
+    pub fn reminder(&self) -> Option<usize> {
+        match self {
+            Event::OpenTooLong { reminder, .. } | Event::SensorOffline { reminder, .. } => Some(*reminder),
+            Event::Templated { event, .. } => event.reminder(),
+            _ => None,
+        }
+    }
+
+    /// A short headline for this event about `door`, used as email subject
+    /// and push notification title.
+    pub fn title(&self, door: &str) -> String {
+        match self {
+            Event::Started { .. } => "Door Monitor started".to_string(),
+            Event::Opened => format!("{} opened", door),
+            Event::Closed { .. } => format!("{} closed", door),
+            Event::OpenTooLong { reminder: 0, .. } => format!("ALERT: {} open too long", door),
+            Event::OpenTooLong { .. } => format!("REMINDER: {} still open", door),
+            Event::FlappingStarted { .. } => format!("WARNING: {} sensor flapping", door),
+            Event::FlappingSettled { .. } => format!("{} sensor settled", door),
+            Event::SensorFailing { .. } => format!("ALERT: {} sensor error", door),
+            Event::SensorOffline { reminder: 0, .. } => format!("ALERT: {} sensor offline", door),
+            Event::SensorOffline { .. } => format!("REMINDER: {} sensor still offline", door),
+            Event::SensorOnline { .. } => format!("{} sensor back online", door),
+            Event::Acknowledged { by, .. } => format!("{} alert acknowledged by {}", door, by),
+            Event::ChannelFailing { channel, .. } => format!("WARNING: {} notifications failing", channel),
+            Event::Missed { notifications } => format!("{}: {} delayed notifications", door, notifications.len()),
+            Event::LimitReached { channel, .. } => format!("WARNING: {} limit reached", channel),
+            Event::Templated { event, .. } => event.title(door),
+        }
+    }
+
+    /// The message sent for this event about `door`.
+    pub fn message(&self, door: &str) -> String {
+        match self {
+            Event::Started { state } => format!("Door Monitor started. {} is currently {}", door, state),
+            Event::Opened => format!("{} has been opened", door),
+            Event::Closed { open_for } => {
+                format!("{} is now closed after being open for {}", door, format_duration(*open_for))
+            }
+            Event::OpenTooLong { open_for, reminder: 0 } => {
+                format!("ALERT: {} has been open for {}", door, format_duration(*open_for))
+            }
+            Event::OpenTooLong { open_for, .. } => {
+                format!("REMINDER: {} still open for {}", door, format_duration(*open_for))
+            }
+            Event::FlappingStarted { transitions, window } => format!(
+                "WARNING: {} sensor is flapping ({} changes in {}), pausing open/close messages until it settles",
+                door,
+                transitions,
+                format_duration(*window)
+            ),
+            Event::FlappingSettled { state } => format!("{} sensor has settled, door is {}", door, state),
+            Event::SensorFailing { error } => format!("ALERT: {} sensor is returning errors: {}", door, error),
+            Event::SensorOffline { offline_for, failures, reminder: 0 } => format!(
+                "ALERT: {} sensor unreachable for {} ({} failed checks)",
+                door,
+                format_duration(*offline_for),
+                failures
+            ),
+            Event::SensorOffline { offline_for, .. } => {
+                format!("REMINDER: {} sensor still unreachable after {}", door, format_duration(*offline_for))
+            }
+            Event::SensorOnline { offline_for, state } => format!(
+                "{} sensor back online after {}, door is {}",
+                door,
+                format_duration(*offline_for),
+                state
+            ),
+            Event::Acknowledged { by, open_for } => format!(
+                "{} is on it: {} alert acknowledged, open for {}",
+                by,
+                door,
+                format_duration(*open_for)
+            ),
+            Event::ChannelFailing { channel, error } => {
+                format!("WARNING: {} notifications for {} are failing: {}", channel, door, error)
+            }
+            Event::Missed { notifications } => format!(
+                "{} notifications about {} couldn't be sent at the time:\n{}",
+                notifications.len(),
+                door,
+                notifications.join("\n")
+            ),
+            Event::LimitReached { channel, limit } => format!(
+                "WARNING: {} {} reached, {} notifications only go through the other channels until it resets",
+                channel, limit, door
+            ),
+            Event::Templated { message, .. } => message.clone(),
+        }
+    }
+}
+
+/// When to alert, taken from the command line options.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertPolicy {
+    pub open_too_long: Duration,
+    /// Keep reminding with `OPEN_REMINDER_INTERVALS` instead of alerting once
+    pub reminder_backoff: bool,
+    /// `None` turns sensor offline alerts off
+    pub sensor_offline_after: Option<Duration>,
+    /// How long an acknowledgement holds back reminders. `None` holds them
+    /// back until the door closes
+    pub acknowledge_snooze: Option<Duration>,
+}
+
+impl AlertPolicy {
+    pub fn from_args(args: &Args) -> Self {
+        Self {
+            open_too_long: Duration::from_secs(args.open_too_long_seconds),
+            reminder_backoff: args.sms_backoff(),
+            sensor_offline_after: Some(Duration::from_secs(args.sensor_offline_seconds))
+                .filter(|after| !after.is_zero()),
+            acknowledge_snooze: args.acknowledge_snooze_minutes.map(|minutes| Duration::from_secs(minutes * 60)),
+        }
+    }
+}
+
+impl Default for AlertPolicy {
+    fn default() -> Self {
+        Self {
+            open_too_long: Duration::from_secs(15),
+            reminder_backoff: true,
+            sensor_offline_after: Some(Duration::from_secs(5 * 60)),
+            acknowledge_snooze: None,
+        }
+    }
+}
+
+/// A line for the log about what the machine saw, printed by whoever fed it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Log {
+    Info(String),
+    Warning(String),
+}
+
+/// What the machine hands back for a reading, error or tick: the
+/// notifications that are due and the lines to log.
+#[derive(Debug, Default, PartialEq)]
+pub struct Output {
+    pub events: Vec<Event>,
+    pub logs: Vec<Log>,
+}
+
+impl Output {
+    fn info(&mut self, line: String) {
+        self.logs.push(Log::Info(line));
+    }
+
+    fn warn(&mut self, line: String) {
+        self.logs.push(Log::Warning(line));
+    }
+}
+
+/// The door monitoring logic without any I/O.
+///
+/// It is fed sensor readings, sensor errors and clock ticks, each with the
+/// current time, and returns the notifications that are due along with what
+/// to log. Because time is
+/// always passed in, the whole alert schedule can be run over hours of
+/// virtual time in a test.
+pub struct DoorStateMachine {
+    door: String,
+    pub policy: AlertPolicy,
+    pub state: MonitorState,
+}
+
+impl DoorStateMachine {
+    pub fn new(door: impl Into<String>, policy: AlertPolicy) -> Self {
+        Self {
+            door: door.into(),
+            policy,
+            state: MonitorState::new(),
+        }
+    }
+
+    /// Applies the alert, debounce and flap detection options.
+    pub fn configure(&mut self, args: &Args) {
+        self.policy = AlertPolicy::from_args(args);
+        self.state.debouncer = Debouncer::new(args.debounce_readings, Duration::from_secs(args.debounce_seconds));
+        self.state.flap_detector = FlapDetector::new(args.flap_threshold, Duration::from_secs(args.flap_window_seconds));
+    }
+
+    pub fn door(&self) -> &str {
+        &self.door
+    }
+
+    /// Whether the accepted door state is open.
+    pub fn is_open(&self) -> bool {
+        self.state.last_door_state == Some(false)
+    }
+
+    /// Holds back notifications until `until`. Events are still tracked and
+    /// kept in the history while muted.
+    pub fn mute(&mut self, until: Instant) {
+        self.state.muted_until = Some(until);
+    }
+
+    pub fn unmute(&mut self) {
+        self.state.muted_until = None;
+    }
+
+    /// How much longer notifications are muted for at `now`, if they are.
+    pub fn muted_for(&self, now: Instant) -> Option<Duration> {
+        self.state
+            .muted_until
+            .map(|until| until.saturating_duration_since(now))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Whether the door has been alerted about as open too long and nobody is
+    /// dealing with it, either because nobody acknowledged the alert or the
+    /// acknowledgement has run out.
+    pub fn needs_acknowledgement(&self, now: Instant) -> bool {
+        self.is_open() && self.state.sms_sent && self.acknowledged_by(now).is_none()
+    }
+
+    /// Who acknowledged the open door alert, while that still holds back reminders.
+    pub fn acknowledged_by(&self, now: Instant) -> Option<&str> {
+        let (by, at) = self.state.acknowledged.as_ref()?;
+        match self.policy.acknowledge_snooze {
+            Some(snooze) if now.saturating_duration_since(*at) >= snooze => None,
+            _ => Some(by),
+        }
+    }
+
+    /// Records that `by` is dealing with the open door alert, which holds
+    /// back reminders for `acknowledge_snooze` or until the door closes.
+    /// Returns the event telling everyone else, or `None` if there was no
+    /// alert to acknowledge.
+    pub fn acknowledge(&mut self, by: &str, now: Instant) -> Option<Event> {
+        if !self.needs_acknowledgement(now) {
+            return None;
+        }
+        let open_for = now.saturating_duration_since(self.state.door_opened_time?);
+        self.state.acknowledged = Some((by.to_string(), now));
+        let event = Event::Acknowledged { by: by.to_string(), open_for };
+        self.record(std::slice::from_ref(&event), now);
+        Some(event)
+    }
+
+    /// Adds `events` to the history.
+    fn record(&mut self, events: &[Event], now: Instant) {
+        for event in events {
+            if self.state.history.len() == HISTORY_LEN {
+                self.state.history.pop_front();
+            }
+            self.state.history.push_back((now, event.clone()));
+        }
+    }
+
+    /// Handles the first reading after the monitor starts.
+    pub fn start(&mut self, door_state: DoorState, now: Instant) -> Output {
+        let mut output = Output::default();
+        self.state.last_good_reading = Some(now);
+        let door_closed = match door_state {
+            DoorState::Closed => true,
+            DoorState::Open => false,
+            DoorState::Unknown => {
+                output.warn(format!("Initial {} state is unknown", self.door));
+                return output;
+            }
+        };
+
+        if door_closed {
+            self.state.door_closed_time = Some(now);
+        } else {
+            self.state.door_opened_time = Some(now);
+        }
+        self.state.last_door_state = Some(door_closed);
+        output.events.push(Event::Started { state: door_state });
+        self.record(&output.events, now);
+        output
+    }
+
+    /// Handles a reading the sensor returned or pushed.
+    pub fn reading(&mut self, door_state: DoorState, now: Instant) -> Output {
+        let mut output = Output::default();
+        self.handle_sensor_ok(door_state, now, &mut output);
+        self.handle_door_status(door_state, true, now, &mut output);
+        self.record(&output.events, now);
+        output
+    }
+
+    /// Re-checks the last reading, e.g. how long the door has been open,
+    /// between polls of a sensor that pushes its changes.
+    pub fn tick(&mut self, now: Instant) -> Output {
+        let mut output = Output::default();
+        if let Some(door_closed) = self.state.last_reading.or(self.state.last_door_state) {
+            self.handle_door_status(DoorState::from_closed(door_closed), false, now, &mut output);
+        }
+        self.record(&output.events, now);
+        output
+    }
+
+    /// Records a failed sensor read. Alerts once the sensor has been
+    /// unreachable for `sensor_offline_after`, repeating with
+    /// `OFFLINE_REMINDER_INTERVALS`.
+    ///
+    /// Errors that won't go away by themselves, like a 404 or a response that
+    /// can't be parsed, are alerted on straight away.
+    pub fn sensor_error(&mut self, error: &Error, now: Instant) -> Output {
+        let mut output = Output::default();
+        if self.state.consecutive_sensor_failures == 0 {
+            self.state.sensor_failing_since = Some(now);
+        }
+        self.state.consecutive_sensor_failures += 1;
+
+        let transient = error.is_transient();
+        if !transient {
+            output.warn(format!("{} sensor error is not temporary, check the sensor URL and settings", self.door));
+        }
+
+        let Some(offline_after) = self.policy.sensor_offline_after else {
+            return output;
+        };
+        let Some(offline_for) = self.state.sensor_offline_for(now) else {
+            return output;
+        };
+        if transient && offline_for < offline_after {
+            return output;
+        }
+
+        let should_send_message = match self.state.last_sensor_offline_alert {
+            None => true,
+            Some(last_alert) => {
+                let index = self.state.sensor_offline_alerts_sent - 1;
+                let next_interval = OFFLINE_REMINDER_INTERVALS
+                    .get(index)
+                    .copied()
+                    .unwrap_or(OFFLINE_REMINDER_REPEAT);
+                now.saturating_duration_since(last_alert) >= next_interval
+            }
+        };
+        if !should_send_message {
+            return output;
+        }
+
+        let event = if self.state.sensor_offline_alerts_sent == 0 && !transient {
+            Event::SensorFailing { error: error.to_string() }
+        } else {
+            Event::SensorOffline {
+                offline_for,
+                failures: self.state.consecutive_sensor_failures,
+                reminder: self.state.sensor_offline_alerts_sent,
+            }
+        };
+        self.state.sensor_offline_alerts_sent += 1;
+        self.state.last_sensor_offline_alert = Some(now);
+        output.events.push(event);
+        self.record(&output.events, now);
+        output
+    }
+
+    /// Resets the failure tracking, with a recovery message if the sensor
+    /// had been reported offline.
+    fn handle_sensor_ok(&mut self, door_state: DoorState, now: Instant, output: &mut Output) {
+        let offline_for = self.state.sensor_offline_for(now);
+        let alerted = self.state.sensor_offline_alerts_sent > 0;
+
+        self.state.consecutive_sensor_failures = 0;
+        self.state.last_good_reading = Some(now);
+        self.state.sensor_failing_since = None;
+        self.state.sensor_offline_alerts_sent = 0;
+        self.state.last_sensor_offline_alert = None;
+
+        let Some(offline_for) = offline_for else {
+            return;
+        };
+        output.info(format!("{} sensor is reachable again after {}", self.door, format_duration(offline_for)));
+        if alerted {
+            output.events.push(Event::SensorOnline { offline_for, state: door_state });
+        }
+    }
+
+    /// `reading` is false on a tick, which only lets a pending change settle
+    /// rather than counting as another reading.
+    fn handle_door_status(&mut self, door_state: DoorState, reading: bool, now: Instant, output: &mut Output) {
+        let door_closed = match door_state {
+            DoorState::Closed => true,
+            DoorState::Open => false,
+            DoorState::Unknown => {
+                // Keep the last known state rather than guessing
+                output.info(format!("{} state is unknown", self.door));
+                return;
+            }
+        };
+        self.state.last_reading = Some(door_closed);
+
+        // Hold on to the accepted state until the debouncer confirms the change
+        let door_closed = match self.state.last_door_state {
+            Some(accepted) if accepted != door_closed => {
+                let confirmed = if reading {
+                    self.state.debouncer.confirm(door_closed, now)
+                } else {
+                    self.state.debouncer.settle(now)
+                };
+                if confirmed {
+                    door_closed
+                } else {
+                    output.info(format!("{} reads {}, waiting for the reading to settle",
+                           self.door, if door_closed { "closed" } else { "open" }));
+                    accepted
+                }
+            }
+            _ => {
+                self.state.debouncer.reset();
+                door_closed
+            }
+        };
+
+        // Always log the current door state
+        if door_closed {
+            if let Some(closed_time) = self.state.door_closed_time {
+                let closed_duration = now.saturating_duration_since(closed_time);
+                output.info(format!("{} is closed (closed for {})", self.door, format_duration(closed_duration)));
+            } else {
+                output.info(format!("{} is closed", self.door));
+            }
+        } else if let Some(opened_time) = self.state.door_opened_time {
+            let open_duration = now.saturating_duration_since(opened_time);
+            output.info(format!("{} is open (open for {})", self.door, format_duration(open_duration)));
+        } else {
+            output.info(format!("{} is open", self.door));
+        }
+
+        // Track when door state changes
+        if self.state.last_door_state != Some(door_closed) {
+            if self.state.last_door_state.is_some() && self.state.flap_detector.record_transition(now) {
+                output.events.push(Event::FlappingStarted {
+                    transitions: self.state.flap_detector.transition_count(),
+                    window: self.state.flap_detector.window,
+                });
+            }
+            let event = self.handle_door_state_change(door_closed, now, output);
+            output.events.extend(event);
+            self.state.last_door_state = Some(door_closed);
+        } else if self.state.flap_detector.settle(now) {
+            output.events.push(Event::FlappingSettled { state: DoorState::from_closed(door_closed) });
+        }
+
+        // Check if door has been open too long
+        if !door_closed {
+            let event = self.handle_door_open_too_long(now, output);
+            output.events.extend(event);
+        }
+    }
+
+    fn handle_door_state_change(&mut self, door_closed: bool, now: Instant, output: &mut Output) -> Option<Event> {
+        // A flapping sensor still updates the state, but stays quiet until it settles
+        let flapping = self.state.flap_detector.is_flapping();
+        if flapping {
+            output.info(format!("{} sensor is flapping, not sending {} messages",
+                   self.door, if door_closed { "door closed" } else { "door opened" }));
+        }
+
+        let event = if door_closed {
+            // Door just closed - always notify if door was open
+            let event = self.state.door_opened_time.map(|opened_time| Event::Closed {
+                open_for: now.saturating_duration_since(opened_time),
+            });
+            self.state.door_opened_time = None;
+            self.state.door_closed_time = Some(now);
+            self.state.reset_sms_state();
+            event
+        } else {
+            // Door just opened - notify immediately
+            self.state.door_opened_time = Some(now);
+            self.state.door_closed_time = None;
+            Some(Event::Opened)
+        };
+
+        event.filter(|_| !flapping)
+    }
+
+    fn handle_door_open_too_long(&mut self, now: Instant, output: &mut Output) -> Option<Event> {
+        let opened_time = self.state.door_opened_time?;
+        let time_open = now.saturating_duration_since(opened_time);
+        if time_open < self.policy.open_too_long {
+            return None;
+        }
+
+        if self.acknowledged_by(now).is_some() {
+            return None;
+        }
+
+        output.info(format!("{} has been opened for too long ({})",
+               self.door, format_duration(time_open)));
+
+        if self.policy.reminder_backoff {
+            self.handle_reminder_with_backoff(time_open, now)
+        } else {
+            self.handle_single_reminder(time_open)
+        }
+    }
+
+    fn handle_reminder_with_backoff(&mut self, time_open: Duration, now: Instant) -> Option<Event> {
+        let should_send_message = if !self.state.sms_sent {
+            // First Message - send immediately when threshold is reached
+            true
+        } else if let Some(last_message) = self.state.last_sms_time {
+            // Determine next interval based on backoff index
+            let next_interval = OPEN_REMINDER_INTERVALS
+                .get(self.state.sms_backoff_index)
+                .copied()
+                .unwrap_or(OPEN_REMINDER_REPEAT);
+            now.saturating_duration_since(last_message) >= next_interval
+        } else {
+            false
+        };
+        if !should_send_message {
+            return None;
+        }
+
+        let reminder = if self.state.sms_sent { self.state.sms_backoff_index } else { 0 };
+        self.state.sms_sent = true;
+        self.state.last_sms_time = Some(now);
+        self.state.sms_backoff_index += 1;
+        Some(Event::OpenTooLong { open_for: time_open, reminder })
+    }
+
+    fn handle_single_reminder(&mut self, time_open: Duration) -> Option<Event> {
+        if self.state.sms_sent {
+            return None;
+        }
+        self.state.sms_sent = true;
+        Some(Event::OpenTooLong { open_for: time_open, reminder: 0 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    fn machine() -> DoorStateMachine {
+        DoorStateMachine::new("Door", AlertPolicy {
+            open_too_long: Duration::from_secs(60),
+            ..AlertPolicy::default()
+        })
+    }
+
+    fn connection_refused() -> Error {
+        Error::Io(std::io::Error::from(std::io::ErrorKind::ConnectionRefused))
+    }
+
+    #[test]
+    fn test_monitor_state_new() {
+        let state = MonitorState::new();
+        assert!(state.door_opened_time.is_none());
+        assert!(state.door_closed_time.is_none());
+        assert!(state.last_door_state.is_none());
+        assert!(!state.sms_sent);
+        assert_eq!(state.sms_backoff_index, 0);
+        assert!(state.last_sms_time.is_none());
+    }
+
+    #[test]
+    fn test_monitor_state_reset_sms_state() {
+        let mut state = MonitorState::new();
+        state.sms_sent = true;
+        state.sms_backoff_index = 3;
+        state.last_sms_time = Some(Instant::now());
+
+        state.reset_sms_state();
+
+        assert!(!state.sms_sent);
+        assert_eq!(state.sms_backoff_index, 0);
+        assert!(state.last_sms_time.is_none());
+    }
+
+    #[test]
+    fn test_open_reminder_intervals() {
+        assert_eq!(OPEN_REMINDER_INTERVALS[0], Duration::from_secs(300));
+        assert_eq!(OPEN_REMINDER_INTERVALS[1], Duration::from_secs(900));
+        assert_eq!(OPEN_REMINDER_INTERVALS[2], Duration::from_secs(1800));
+        assert_eq!(OPEN_REMINDER_INTERVALS[3], Duration::from_secs(3600));
+        assert_eq!(OPEN_REMINDER_REPEAT, Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_alert_policy_from_args() {
+        use clap::Parser;
+
+        let args = Args::try_parse_from([
+            "test", "--open-too-long-seconds", "120", "--no-sms-backoff", "--sensor-offline-seconds", "0",
+        ]).unwrap();
+        let policy = AlertPolicy::from_args(&args);
+
+        assert_eq!(policy.open_too_long, Duration::from_secs(120));
+        assert!(!policy.reminder_backoff);
+        assert!(policy.sensor_offline_after.is_none());
+    }
+
+    #[test]
+    fn test_event_messages() {
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+
+        assert_eq!(Event::Started { state: DoorState::Closed }.message("Garage door"),
+                   "Door Monitor started. Garage door is currently closed");
+        assert_eq!(Event::Opened.message("Garage door"), "Garage door has been opened");
+        assert_eq!(Event::Closed { open_for: minutes(5) }.message("Garage door"),
+                   "Garage door is now closed after being open for 00:05:00");
+        assert_eq!(Event::OpenTooLong { open_for: minutes(5), reminder: 0 }.message("Garage door"),
+                   "ALERT: Garage door has been open for 00:05:00");
+        assert_eq!(Event::OpenTooLong { open_for: minutes(10), reminder: 1 }.message("Garage door"),
+                   "REMINDER: Garage door still open for 00:10:00");
+        assert_eq!(Event::SensorOffline { offline_for: minutes(5), failures: 60, reminder: 0 }.message("Garage door"),
+                   "ALERT: Garage door sensor unreachable for 00:05:00 (60 failed checks)");
+        assert_eq!(Event::SensorOnline { offline_for: minutes(7), state: DoorState::Open }.message("Garage door"),
+                   "Garage door sensor back online after 00:07:00, door is open");
+        assert_eq!(Event::Opened.label(), "door opened");
+    }
+
+    #[test]
+    fn test_event_titles() {
+        assert_eq!(Event::Opened.title("Garage door"), "Garage door opened");
+        assert_eq!(
+            Event::OpenTooLong { open_for: Duration::from_secs(900), reminder: 1 }.title("Garage door"),
+            "REMINDER: Garage door still open"
+        );
+        assert_eq!(
+            Event::SensorOnline { offline_for: Duration::from_secs(60), state: DoorState::Closed }.title("Garage door"),
+            "Garage door sensor back online"
+        );
+        let missed = Event::Missed {
+            notifications: vec!["10:15:00 UTC Garage door has been opened".to_string()],
+        };
+        assert_eq!(missed.title("Garage door"), "Garage door: 1 delayed notifications");
+    }
+
+    #[test]
+    fn test_start_sets_initial_state() {
+        let mut machine = machine();
+        let now = Instant::now();
+
+        assert_eq!(machine.start(DoorState::Open, now).events, vec![Event::Started { state: DoorState::Open }]);
+        assert_eq!(machine.state.door_opened_time, Some(now));
+        assert_eq!(machine.state.last_door_state, Some(false));
+        assert_eq!(machine.state.last_good_reading, Some(now));
+
+        let mut machine = self::machine();
+        assert_eq!(
+            machine.start(DoorState::Unknown, now),
+            Output {
+                events: Vec::new(),
+                logs: vec![Log::Warning("Initial Door state is unknown".to_string())],
+            }
+        );
+        assert!(machine.state.last_door_state.is_none());
+    }
+
+    #[test]
+    fn test_unknown_reading_keeps_last_state() {
+        let mut machine = machine();
+        let now = Instant::now();
+        machine.state.door_closed_time = Some(now - Duration::from_secs(60));
+        machine.state.last_door_state = Some(true);
+
+        let output = machine.reading(DoorState::Unknown, now);
+        assert!(output.events.is_empty());
+        assert_eq!(output.logs, vec![Log::Info("Door state is unknown".to_string())]);
+
+        assert_eq!(machine.state.last_door_state, Some(true));
+        assert!(machine.state.door_closed_time.is_some());
+        assert!(machine.state.door_opened_time.is_none());
+    }
+
+    #[test]
+    fn test_door_state_change_door_opens() {
+        let mut machine = machine();
+        let now = Instant::now();
+
+        assert_eq!(machine.handle_door_state_change(false, now, &mut Output::default()), Some(Event::Opened));
+
+        assert_eq!(machine.state.door_opened_time, Some(now));
+        assert!(machine.state.door_closed_time.is_none());
+    }
+
+    #[test]
+    fn test_door_state_change_door_closes() {
+        let mut machine = machine();
+        let now = Instant::now();
+        machine.state.door_opened_time = Some(now - Duration::from_secs(300)); // 5 minutes ago
+        machine.state.sms_sent = true;
+        machine.state.sms_backoff_index = 2;
+
+        // Closing always notifies, regardless of earlier alerts
+        assert_eq!(
+            machine.handle_door_state_change(true, now, &mut Output::default()),
+            Some(Event::Closed { open_for: Duration::from_secs(300) })
+        );
+
+        assert!(machine.state.door_opened_time.is_none());
+        assert_eq!(machine.state.door_closed_time, Some(now));
+        assert!(!machine.state.sms_sent);
+        assert_eq!(machine.state.sms_backoff_index, 0);
+    }
+
+    #[test]
+    fn test_door_closed_with_duration() {
+        let mut machine = machine();
+        let now = Instant::now();
+        machine.state.door_closed_time = Some(now - Duration::from_secs(180)); // 3 minutes ago
+        machine.state.last_door_state = Some(true);
+
+        // Already closed, no state change
+        assert!(machine.reading(DoorState::Closed, now).events.is_empty());
+
+        assert!(machine.state.door_closed_time.is_some());
+        assert!(machine.state.door_opened_time.is_none());
+        assert_eq!(machine.state.last_door_state, Some(true));
+    }
+
+    #[test]
+    fn test_door_open_with_duration_below_threshold() {
+        let mut machine = machine();
+        let now = Instant::now();
+        machine.state.door_opened_time = Some(now - Duration::from_secs(30));
+        machine.state.last_door_state = Some(false);
+
+        assert!(machine.reading(DoorState::Open, now).events.is_empty());
+
+        assert!(machine.state.door_opened_time.is_some());
+        assert_eq!(machine.state.last_door_state, Some(false));
+        assert!(!machine.state.sms_sent);
+    }
+
+    #[test]
+    fn test_first_reading_closed() {
+        let mut machine = machine();
+        let now = Instant::now();
+
+        // Closed without having seen it open, nothing to report
+        assert!(machine.reading(DoorState::Closed, now).events.is_empty());
+
+        assert_eq!(machine.state.door_closed_time, Some(now));
+        assert!(machine.state.door_opened_time.is_none());
+        assert_eq!(machine.state.last_door_state, Some(true));
+    }
+
+    #[test]
+    fn test_first_reading_open() {
+        let mut machine = machine();
+        let now = Instant::now();
+
+        assert_eq!(machine.reading(DoorState::Open, now).events, vec![Event::Opened]);
+
+        assert_eq!(machine.state.door_opened_time, Some(now));
+        assert!(machine.state.door_closed_time.is_none());
+        assert_eq!(machine.state.last_door_state, Some(false));
+    }
+
+    #[test]
+    fn test_door_state_change_from_closed_to_open() {
+        let mut machine = machine();
+        let now = Instant::now();
+        machine.state.door_closed_time = Some(now - Duration::from_secs(300));
+        machine.state.last_door_state = Some(true);
+
+        assert_eq!(machine.reading(DoorState::Open, now).events, vec![Event::Opened]);
+
+        assert!(machine.state.door_opened_time.is_some());
+        assert!(machine.state.door_closed_time.is_none());
+        assert_eq!(machine.state.last_door_state, Some(false));
+    }
+
+    #[test]
+    fn test_door_state_change_from_open_to_closed() {
+        let mut machine = machine();
+        let now = Instant::now();
+        machine.state.door_opened_time = Some(now - Duration::from_secs(300));
+        machine.state.last_door_state = Some(false);
+
+        assert_eq!(
+            machine.reading(DoorState::Closed, now).events,
+            vec![Event::Closed { open_for: Duration::from_secs(300) }]
+        );
+
+        assert!(machine.state.door_opened_time.is_none());
+        assert!(machine.state.door_closed_time.is_some());
+        assert_eq!(machine.state.last_door_state, Some(true));
+        assert!(!machine.state.sms_sent);
+        assert_eq!(machine.state.sms_backoff_index, 0);
+    }
+
+    #[test]
+    fn test_debounces_changes() {
+        let mut machine = machine();
+        machine.state.debouncer = Debouncer::new(3, Duration::ZERO);
+        let now = Instant::now();
+
+        machine.reading(DoorState::Closed, now);
+        assert_eq!(machine.state.last_door_state, Some(true));
+
+        // A single bounce is ignored
+        assert!(machine.reading(DoorState::Open, now).events.is_empty());
+        assert!(machine.reading(DoorState::Closed, now).events.is_empty());
+        assert!(machine.reading(DoorState::Open, now).events.is_empty());
+        assert!(machine.reading(DoorState::Open, now).events.is_empty());
+        assert_eq!(machine.state.last_door_state, Some(true));
+        assert_eq!(machine.state.last_reading, Some(false));
+        assert!(machine.state.door_opened_time.is_none());
+
+        // The third open reading in a row is accepted
+        assert_eq!(machine.reading(DoorState::Open, now).events, vec![Event::Opened]);
+        assert_eq!(machine.state.last_door_state, Some(false));
+        assert!(machine.state.door_opened_time.is_some());
+    }
+
+    #[test]
+    fn test_ticks_do_not_count_as_readings() {
+        let mut machine = machine();
+        machine.state.debouncer = Debouncer::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+        let at = |seconds: u64| start + Duration::from_secs(seconds);
+
+        machine.reading(DoorState::Closed, at(0));
+        assert!(machine.reading(DoorState::Open, at(1)).events.is_empty());
+        assert!(machine.tick(at(2)).events.is_empty());
+        assert!(machine.tick(at(3)).events.is_empty());
+        assert!(machine.state.debouncer.is_pending());
+
+        // Once the sensor has agreed often enough, a tick lets the change settle
+        assert!(machine.reading(DoorState::Open, at(4)).events.is_empty());
+        assert!(machine.tick(at(8)).events.is_empty());
+        assert_eq!(machine.state.last_door_state, Some(true));
+        assert_eq!(machine.tick(at(11)).events, vec![Event::Opened]);
+        assert_eq!(machine.state.last_door_state, Some(false));
+    }
+
+    #[test]
+    fn test_detects_flapping() {
+        let mut machine = machine();
+        machine.state.flap_detector = FlapDetector::new(3, Duration::from_secs(60));
+        let start = Instant::now();
+        let at = |seconds: u64| start + Duration::from_secs(seconds);
+
+        machine.reading(DoorState::Closed, at(0));
+        assert_eq!(machine.reading(DoorState::Open, at(1)).events, vec![Event::Opened]);
+        assert_eq!(machine.reading(DoorState::Closed, at(2)).events, vec![Event::Closed { open_for: Duration::from_secs(1) }]);
+        assert!(!machine.state.flap_detector.is_flapping());
+
+        // The third change starts flapping and the open message is held back
+        assert_eq!(
+            machine.reading(DoorState::Open, at(3)).events,
+            vec![Event::FlappingStarted { transitions: 3, window: Duration::from_secs(60) }]
+        );
+        assert!(machine.state.flap_detector.is_flapping());
+        assert!(machine.reading(DoorState::Closed, at(4)).events.is_empty());
+
+        // State keeps being tracked while flapping
+        assert_eq!(machine.state.last_door_state, Some(true));
+        assert!(machine.state.door_closed_time.is_some());
+
+        assert!(machine.reading(DoorState::Closed, at(30)).events.is_empty());
+        assert_eq!(
+            machine.reading(DoorState::Closed, at(64)).events,
+            vec![Event::FlappingSettled { state: DoorState::Closed }]
+        );
+        assert!(!machine.state.flap_detector.is_flapping());
+    }
+
+    #[test]
+    fn test_open_too_long_below_threshold() {
+        let mut machine = machine();
+        let now = Instant::now();
+        machine.state.door_opened_time = Some(now - Duration::from_secs(30)); // threshold is 60 seconds
+
+        assert!(machine.handle_door_open_too_long(now, &mut Output::default()).is_none());
+
+        assert!(!machine.state.sms_sent);
+        assert_eq!(machine.state.sms_backoff_index, 0);
+        assert!(machine.state.last_sms_time.is_none());
+    }
+
+    #[test]
+    fn test_open_too_long_above_threshold_with_backoff() {
+        let mut machine = machine();
+        let now = Instant::now();
+        machine.state.door_opened_time = Some(now - Duration::from_secs(120));
+
+        assert_eq!(
+            machine.handle_door_open_too_long(now, &mut Output::default()),
+            Some(Event::OpenTooLong { open_for: Duration::from_secs(120), reminder: 0 })
+        );
+
+        assert!(machine.state.sms_sent);
+        assert_eq!(machine.state.sms_backoff_index, 1);
+        assert_eq!(machine.state.last_sms_time, Some(now));
+    }
+
+    #[test]
+    fn test_open_too_long_above_threshold_no_backoff() {
+        let mut machine = machine();
+        machine.policy.reminder_backoff = false;
+        let now = Instant::now();
+        machine.state.door_opened_time = Some(now - Duration::from_secs(120));
+
+        assert_eq!(
+            machine.handle_door_open_too_long(now, &mut Output::default()),
+            Some(Event::OpenTooLong { open_for: Duration::from_secs(120), reminder: 0 })
+        );
+
+        assert!(machine.state.sms_sent);
+        assert_eq!(machine.state.sms_backoff_index, 0); // No backoff increment
+        assert!(machine.state.last_sms_time.is_none()); // No last reminder time tracking
+
+        // Only ever one alert
+        assert!(machine.handle_door_open_too_long(now + Duration::from_secs(24 * 60 * 60), &mut Output::default()).is_none());
+    }
+
+    #[test]
+    fn test_open_too_long_no_door_open_time() {
+        let mut machine = machine();
+
+        assert!(machine.handle_door_open_too_long(Instant::now(), &mut Output::default()).is_none());
+
+        assert!(!machine.state.sms_sent);
+        assert_eq!(machine.state.sms_backoff_index, 0);
+        assert!(machine.state.last_sms_time.is_none());
+    }
+
+    #[test]
+    fn test_reminder_with_backoff_second_too_early() {
+        let mut machine = machine();
+        let now = Instant::now();
+        machine.state.sms_sent = true;
+        machine.state.sms_backoff_index = 0;
+        machine.state.last_sms_time = Some(now - Duration::from_secs(120)); // first interval is 5 minutes
+
+        assert!(machine.handle_reminder_with_backoff(Duration::from_secs(900), now).is_none());
+
+        assert!(machine.state.sms_sent);
+        assert_eq!(machine.state.sms_backoff_index, 0);
+    }
+
+    #[test]
+    fn test_reminder_with_backoff_second_ready() {
+        let mut machine = machine();
+        let now = Instant::now();
+        machine.state.sms_sent = true;
+        machine.state.sms_backoff_index = 0;
+        machine.state.last_sms_time = Some(now - Duration::from_secs(360)); // past the 5 minute interval
+
+        assert_eq!(
+            machine.handle_reminder_with_backoff(Duration::from_secs(900), now),
+            Some(Event::OpenTooLong { open_for: Duration::from_secs(900), reminder: 0 })
+        );
+
+        assert!(machine.state.sms_sent);
+        assert_eq!(machine.state.sms_backoff_index, 1);
+        assert_eq!(machine.state.last_sms_time, Some(now));
+    }
+
+    #[test]
+    fn test_reminder_with_backoff_beyond_intervals() {
+        let mut machine = machine();
+        let now = Instant::now();
+        machine.state.sms_sent = true;
+        machine.state.sms_backoff_index = 5; // Beyond the 4 defined intervals
+        machine.state.last_sms_time = Some(now - Duration::from_secs(3700)); // 61+ minutes ago
+
+        assert_eq!(
+            machine.handle_reminder_with_backoff(Duration::from_secs(7200), now),
+            Some(Event::OpenTooLong { open_for: Duration::from_secs(7200), reminder: 5 })
+        );
+        assert_eq!(machine.state.sms_backoff_index, 6);
+    }
+
+    #[test]
+    fn test_reminder_with_backoff_no_last_sms_time() {
+        let mut machine = machine();
+        // Inconsistent state that should not happen in normal operation
+        machine.state.sms_sent = true;
+        machine.state.sms_backoff_index = 1;
+        machine.state.last_sms_time = None;
+
+        assert!(machine.handle_reminder_with_backoff(Duration::from_secs(900), Instant::now()).is_none());
+
+        assert!(machine.state.sms_sent);
+        assert_eq!(machine.state.sms_backoff_index, 1);
+        assert!(machine.state.last_sms_time.is_none());
+    }
+
+    #[test]
+    fn test_single_reminder_already_sent() {
+        let mut machine = machine();
+        machine.state.sms_sent = true;
+
+        assert!(machine.handle_single_reminder(Duration::from_secs(900)).is_none());
+
+        assert!(machine.state.sms_sent);
+        assert_eq!(machine.state.sms_backoff_index, 0);
+        assert!(machine.state.last_sms_time.is_none());
+    }
+
+    #[test]
+    fn test_open_door_alert_schedule_over_hours() {
+        let mut machine = machine();
+        let start = Instant::now();
+        machine.start(DoorState::Closed, start);
+        machine.reading(DoorState::Open, start);
+
+        // Poll every 5 seconds for 4 hours and note when reminders go out
+        let mut alerts = Vec::new();
+        let mut elapsed = Duration::ZERO;
+        while elapsed <= Duration::from_secs(4 * 60 * 60) {
+            elapsed += Duration::from_secs(5);
+            for event in machine.reading(DoorState::Open, start + elapsed).events {
+                if let Event::OpenTooLong { open_for, reminder } = event {
+                    assert_eq!(open_for, elapsed);
+                    alerts.push((elapsed.as_secs(), reminder));
+                }
+            }
+        }
+
+        // Alert at the 60 second threshold. The alert itself takes the first
+        // backoff step, so reminders follow 15, 30 and 60 minutes after the
+        // previous one, then every hour
+        let mut expected = vec![(60, 0)];
+        let mut at = 60;
+        for (reminder, minutes) in [15, 30, 60, 60, 60].iter().enumerate() {
+            at += minutes * 60;
+            expected.push((at, reminder + 1));
+        }
+        assert_eq!(alerts, expected);
+
+        // Closing reports the full time and starts over
+        assert_eq!(
+            machine.reading(DoorState::Closed, start + elapsed).events,
+            vec![Event::Closed { open_for: elapsed }]
+        );
+        assert!(!machine.state.sms_sent);
+    }
+
+    #[test]
+    fn test_sensor_offline_schedule_and_recovery() {
+        let mut machine = machine();
+        let start = Instant::now();
+        machine.start(DoorState::Closed, start);
+
+        let mut alerts = Vec::new();
+        let mut elapsed = Duration::ZERO;
+        while elapsed < Duration::from_secs(8 * 60 * 60) {
+            elapsed += Duration::from_secs(5);
+            for event in machine.sensor_error(&connection_refused(), start + elapsed).events {
+                if let Event::SensorOffline { offline_for, reminder, .. } = event {
+                    assert_eq!(offline_for, elapsed);
+                    alerts.push((elapsed.as_secs() / 60, reminder));
+                }
+            }
+        }
+
+        // Alert after 5 minutes, then after 30 minutes, 1 hour, 2 hours and every 4 hours
+        assert_eq!(alerts, vec![(5, 0), (35, 1), (95, 2), (215, 3), (455, 4)]);
+
+        assert_eq!(
+            machine.reading(DoorState::Open, start + elapsed).events,
+            vec![
+                Event::SensorOnline { offline_for: elapsed, state: DoorState::Open },
+                Event::Opened,
+            ]
+        );
+        assert_eq!(machine.state.consecutive_sensor_failures, 0);
+        assert_eq!(machine.state.sensor_offline_alerts_sent, 0);
+        assert!(machine.state.last_sensor_offline_alert.is_none());
+        assert!(machine.state.sensor_offline_for(start + elapsed).is_none());
+    }
+
+    #[test]
+    fn test_acknowledgement_stops_reminders() {
+        let mut machine = machine();
+        let start = Instant::now();
+        let at = |seconds: u64| start + Duration::from_secs(seconds);
+        machine.start(DoorState::Closed, start);
+        machine.reading(DoorState::Open, start);
+
+        // Nothing to acknowledge before the alert
+        assert!(machine.acknowledge("Alice", at(30)).is_none());
+        assert!(matches!(machine.tick(at(60)).events.as_slice(), [Event::OpenTooLong { reminder: 0, .. }]));
+        assert!(machine.needs_acknowledgement(at(60)));
+
+        assert_eq!(
+            machine.acknowledge("Alice", at(120)),
+            Some(Event::Acknowledged { by: "Alice".to_string(), open_for: Duration::from_secs(120) })
+        );
+        assert!(machine.acknowledge("Bob", at(130)).is_none());
+        assert_eq!(machine.acknowledged_by(at(130)), Some("Alice"));
+
+        // No reminders until the door closes
+        for minute in 2..180 {
+            assert!(machine.tick(at(minute * 60)).events.is_empty());
+        }
+        assert!(matches!(machine.reading(DoorState::Closed, at(3 * 60 * 60)).events.as_slice(), [Event::Closed { .. }]));
+        assert!(machine.state.acknowledged.is_none());
+    }
+
+    #[test]
+    fn test_acknowledgement_snooze() {
+        let mut machine = machine();
+        machine.policy.acknowledge_snooze = Some(Duration::from_secs(30 * 60));
+        let start = Instant::now();
+        let at = |seconds: u64| start + Duration::from_secs(seconds);
+        machine.start(DoorState::Open, start);
+
+        assert_eq!(machine.tick(at(60)).events.len(), 1);
+        assert!(machine.acknowledge("Alice", at(120)).is_some());
+
+        // The 15 minute reminder waits until the snooze is over
+        assert!(machine.tick(at(31 * 60)).events.is_empty());
+        assert!(machine.acknowledged_by(at(32 * 60)).is_none());
+        assert!(matches!(machine.tick(at(32 * 60)).events.as_slice(), [Event::OpenTooLong { reminder: 1, .. }]));
+
+        // After which it can be acknowledged again
+        assert!(machine.needs_acknowledgement(at(32 * 60)));
+        assert!(machine.acknowledge("Bob", at(33 * 60)).is_some());
+    }
+
+    #[test]
+    fn test_history_and_mute() {
+        let mut machine = machine();
+        let start = Instant::now();
+        machine.start(DoorState::Closed, start);
+        machine.reading(DoorState::Open, start + Duration::from_secs(10));
+        machine.reading(DoorState::Closed, start + Duration::from_secs(20));
+
+        let history: Vec<_> = machine.state.history.iter().map(|(at, event)| (*at, event.kind())).collect();
+        assert_eq!(history, vec![
+            (start, "started"),
+            (start + Duration::from_secs(10), "door_opened"),
+            (start + Duration::from_secs(20), "door_closed"),
+        ]);
+
+        for i in 0..HISTORY_LEN as u64 {
+            machine.reading(if i % 2 == 0 { DoorState::Open } else { DoorState::Closed }, start + Duration::from_secs(30 + i));
+        }
+        assert_eq!(machine.state.history.len(), HISTORY_LEN);
+
+        assert!(machine.muted_for(start).is_none());
+        machine.mute(start + Duration::from_secs(30 * 60));
+        assert_eq!(machine.muted_for(start + Duration::from_secs(60)), Some(Duration::from_secs(29 * 60)));
+        assert!(machine.muted_for(start + Duration::from_secs(30 * 60)).is_none());
+        machine.unmute();
+        assert!(machine.state.muted_until.is_none());
+    }
+
+    #[test]
+    fn test_sensor_recovers_before_alert() {
+        let mut machine = machine();
+        let start = Instant::now();
+        machine.start(DoorState::Closed, start);
+
+        assert!(machine.sensor_error(&connection_refused(), start + Duration::from_secs(60)).events.is_empty());
+        assert_eq!(machine.state.consecutive_sensor_failures, 1);
+
+        // Back before anyone was told, so nothing to report
+        assert!(machine.reading(DoorState::Closed, start + Duration::from_secs(65)).events.is_empty());
+        assert_eq!(machine.state.consecutive_sensor_failures, 0);
+    }
+
+    #[test]
+    fn test_sensor_offline_alerts_disabled() {
+        let mut machine = machine();
+        machine.policy.sensor_offline_after = None;
+        let now = Instant::now();
+        machine.state.last_good_reading = Some(now - Duration::from_secs(600));
+
+        assert!(machine.sensor_error(&connection_refused(), now).events.is_empty());
+        assert_eq!(machine.state.consecutive_sensor_failures, 1);
+        assert_eq!(machine.state.sensor_offline_alerts_sent, 0);
+    }
+
+    #[test]
+    fn test_sensor_permanent_error_alerts_immediately() {
+        let mut machine = machine();
+        let now = Instant::now();
+        machine.state.last_good_reading = Some(now);
+
+        assert_eq!(
+            machine.sensor_error(&Error::SensorStatus(StatusCode::NOT_FOUND), now).events,
+            vec![Event::SensorFailing { error: "HTTP error: 404 Not Found".to_string() }]
+        );
+        assert_eq!(machine.state.sensor_offline_alerts_sent, 1);
+
+        // A timeout has to last for sensor_offline_after first
+        let mut machine = self::machine();
+        machine.state.last_good_reading = Some(now);
+        let timeout = Error::Io(std::io::Error::from(std::io::ErrorKind::TimedOut));
+        assert!(machine.sensor_error(&timeout, now).events.is_empty());
+        assert_eq!(machine.state.sensor_offline_alerts_sent, 0);
+    }
+}
@@ -1,47 +1,514 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
+use serde_json::{Value, json};
+
 use crate::config::Args;
+use crate::error::Error;
+use crate::notifier::{Notifier, check_recipients, check_response};
+use crate::state_machine::Event;
+use crate::telegram_bot::ACKNOWLEDGE_PREFIX;
+use crate::utils::format_duration;
+
+const PROVIDER: &str = "Telegram";
+
+/// Sends events to every configured Telegram chat through a bot.
+///
+/// With `--telegram-live-message` an open door gets a single pinned message
+/// per chat that is edited as reminders come due and when the door closes,
+/// instead of a new message for each. If editing fails a new message is
+/// sent, which then becomes the one that is edited.
+pub struct TelegramNotifier {
+    client: reqwest::Client,
+    args: Args,
+    /// The live message for each open door and chat
+    live_messages: Mutex<HashMap<(String, String), i64>>,
+}
+
+impl TelegramNotifier {
+    pub fn new(client: reqwest::Client, args: Args) -> Self {
+        Self {
+            client,
+            args,
+            live_messages: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates or edits the door's live message in `chat` for events that
+    /// have a live text, and sends other events as new messages. A templated
+    /// event's message replaces its live text.
+    async fn send_live(&self, chat: &str, door: &str, event: &Event, keyboard: Option<&Value>) -> Result<(), Error> {
+        let key = (door.to_string(), chat.to_string());
+        let Some(text) = live_text(door, event.base()) else {
+            if let Event::Acknowledged { by, open_for } = event.base() {
+                // Take the button off the live message, nobody needs to press it now
+                let text = format!("{} open for {}, acknowledged by {}", door, format_duration(*open_for), by);
+                self.edit_live(&key, &text, None).await;
+            }
+            send_telegram_to(&self.client, &self.args, chat, &event.message(door), keyboard).await?;
+            return Ok(());
+        };
+        let text = match event {
+            Event::Templated { message, .. } => message.clone(),
+            _ => text,
+        };
+
+        let closed = matches!(event.base(), Event::Closed { .. });
+        let edited = !matches!(event.base(), Event::Opened) && self.edit_live(&key, &text, keyboard).await;
+        if edited && !closed {
+            return Ok(());
+        }
+
+        // The episode is over, or its message couldn't be edited and is replaced
+        let finished = self.live_messages.lock().unwrap().remove(&key);
+        if let Some(message_id) = finished {
+            self.unpin(chat, message_id).await;
+        }
+        if edited {
+            return Ok(());
+        }
+        let message_id = send_telegram_to(&self.client, &self.args, chat, &text, keyboard).await?;
+        if !closed {
+            self.live_messages.lock().unwrap().insert(key, message_id);
+            self.pin(chat, message_id).await;
+        }
+        Ok(())
+    }
+
+    /// Edits the live message for a door and chat, if there is one. Returns
+    /// whether that worked.
+    async fn edit_live(&self, key: &(String, String), text: &str, keyboard: Option<&Value>) -> bool {
+        let Some(message_id) = self.live_messages.lock().unwrap().get(key).copied() else {
+            return false;
+        };
+        let (door, chat) = key;
+        match edit_telegram(&self.client, &self.args, chat, message_id, text, keyboard).await {
+            Ok(()) => true,
+            Err(e) => {
+                let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                eprintln!("[{}] Failed to edit Telegram message for {} in chat {}, sending a new one: {}",
+                       timestamp, door, chat, e);
+                false
+            }
+        }
+    }
+
+    /// Sends `event` about `door` to one chat.
+    async fn send_chat(&self, chat: &str, door: &str, event: &Event) -> Result<(), Error> {
+        // The button is only useful while the bot is there to answer it
+        let keyboard = if self.args.telegram_commands && matches!(event.base(), Event::OpenTooLong { .. }) {
+            Some(acknowledge_keyboard(door))
+        } else {
+            None
+        };
+        let keyboard = keyboard.as_ref();
+
+        if self.args.telegram_live_message {
+            self.send_live(chat, door, event, keyboard).await
+        } else {
+            send_telegram_to(&self.client, &self.args, chat, &event.message(door), keyboard).await.map(|_| ())
+        }
+    }
+
+    /// Pinning is a nicety, so failures, e.g. for lack of admin rights in a
+    /// group, are only logged.
+    async fn pin(&self, chat: &str, message_id: i64) {
+        let params = [("disable_notification", "true".to_string())];
+        if let Err(e) = call_message_method(&self.client, &self.args, "pinChatMessage", chat, message_id, &params).await {
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            eprintln!("[{}] Failed to pin Telegram message in chat {}: {}", timestamp, chat, e);
+        }
+    }
+
+    async fn unpin(&self, chat: &str, message_id: i64) {
+        if let Err(e) = call_message_method(&self.client, &self.args, "unpinChatMessage", chat, message_id, &[]).await {
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            eprintln!("[{}] Failed to unpin Telegram message in chat {}: {}", timestamp, chat, e);
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &str {
+        "Telegram"
+    }
+
+    async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+        if self.args.telegram_token.is_none() || self.args.telegram_conversation_ids.is_empty() {
+            return Err(Error::MissingCredentials { provider: PROVIDER.to_string() });
+        }
+        let sends = self.args.telegram_conversation_ids.iter().map(|chat| async move {
+            (chat.clone(), self.send_chat(chat, door, event).await)
+        });
+        check_recipients(PROVIDER, join_all(sends).await)
+    }
+
+    fn recipients(&self) -> Vec<String> {
+        self.args.telegram_conversation_ids.clone()
+    }
+
+    async fn send_to(&self, recipient: &str, door: &str, event: &Event, _at: DateTime<Utc>) -> Result<(), Error> {
+        if self.args.telegram_token.is_none() {
+            return Err(Error::MissingCredentials { provider: PROVIDER.to_string() });
+        }
+        self.send_chat(recipient, door, event).await
+    }
+}
+
+/// The text of the live message for `event`, for the events that update it.
+pub fn live_text(door: &str, event: &Event) -> Option<String> {
+    match event {
+        Event::Opened => Some(format!("{} open", door)),
+        Event::OpenTooLong { open_for, reminder: 0 } => {
+            Some(format!("ALERT: {} open for {}", door, format_duration(*open_for)))
+        }
+        Event::OpenTooLong { open_for, reminder } => {
+            Some(format!("{} open for {} \u{2014} reminder {}", door, format_duration(*open_for), reminder))
+        }
+        Event::Closed { open_for } => Some(format!("{} closed after {}", door, format_duration(*open_for))),
+        _ => None,
+    }
+}
+
+/// An inline keyboard with an "I'm on it" button that acknowledges the alert for `door`.
+pub fn acknowledge_keyboard(door: &str) -> Value {
+    json!({
+        "inline_keyboard": [[{
+            "text": "I'm on it",
+            "callback_data": format!("{}{}", ACKNOWLEDGE_PREFIX, door),
+        }]]
+    })
+}
 
+/// Sends `message` to every configured chat and returns the message IDs, in
+/// the order of the chats. Fails if any chat didn't get it.
 pub async fn send_telegram(
     client: &reqwest::Client,
     args: &Args,
     message: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<Vec<i64>, Error> {
     // Debug print all available arguments
     println!("Telegram Function Args Debug:");
     println!("  telegram_token {:?}", args.telegram_token.as_ref().map(|_| "[REDACTED]"));
-    println!("  telegram_conversation_id: {:?}", args.telegram_conversation_id);
+    println!("  telegram_conversation_ids: {:?}", args.telegram_conversation_ids);
     println!("  message: {:?}", message);
 
-    if let (Some(token), Some(conversation_id)) = (
-        &args.telegram_token,
-        &args.telegram_conversation_id,
-    ) {
-        let uri = format!("https://api.telegram.org/bot{}/sendMessage", token);
+    if args.telegram_conversation_ids.is_empty() {
+        println!("Telegram args not supplied");
+        return Err(Error::MissingCredentials { provider: PROVIDER.to_string() });
+    }
+    let sends = args.telegram_conversation_ids.iter().map(|chat| async move {
+        (chat.clone(), send_telegram_to(client, args, chat, message, None).await)
+    });
+    let results = join_all(sends).await;
+    let message_ids = results.iter().filter_map(|(_, result)| result.as_ref().ok().copied()).collect();
+    check_recipients(PROVIDER, results.into_iter().map(|(chat, result)| (chat, result.map(|_| ()))).collect())?;
+    Ok(message_ids)
+}
+
+/// Sends `message` to one chat, with `keyboard` as its inline keyboard if
+/// given, and returns its message ID.
+pub async fn send_telegram_to(
+    client: &reqwest::Client,
+    args: &Args,
+    conversation_id: &str,
+    message: &str,
+    keyboard: Option<&Value>,
+) -> Result<i64, Error> {
+    if let Some(token) = &args.telegram_token {
+        let uri = format!("{}/bot{}/sendMessage", args.telegram_api_url.trim_end_matches('/'), token);
 
-        println!("Telegram URI: {}", uri);
-        println!("Telegram Conversation ID: {}", conversation_id.clone());
+        println!("Telegram Conversation ID: {}", conversation_id);
         println!("Test Message: {}", &message);
 
-        let params = [
-            ("chat_id", conversation_id.as_str()),
-            ("text", message),
+        let mut params = vec![
+            ("chat_id", conversation_id.to_string()),
+            ("text", message.to_string()),
         ];
+        if let Some(keyboard) = keyboard {
+            params.push(("reply_markup", keyboard.to_string()));
+        }
 
         let response = client
             .post(&uri)
             .form(&params)
             .send()
-            .await?;
+            .await
+            .map_err(|source| Error::NotifierHttp {
+                provider: PROVIDER.to_string(),
+                source,
+            })?;
 
         println!("Telegram Response: {}", response.status().as_str());
         
-        if response.status().is_success() {
-            println!("Telegram sent successfully: {}", message);
-        } else {
-            eprintln!("Failed to send Telegram: HTTP {}", response.status());
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(Error::NotifierRejected {
+                provider: PROVIDER.to_string(),
+                status,
+                body,
+            });
+        }
+        let message_id = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|response| response["result"]["message_id"].as_i64());
+        match message_id {
+            Some(message_id) => {
+                println!("Telegram sent successfully: {}", message);
+                Ok(message_id)
+            }
+            None => Err(Error::NotifierRejected {
+                provider: PROVIDER.to_string(),
+                status,
+                body: format!("no message_id in response: {}", body),
+            }),
         }
     } else {
         println!("Telegram args not supplied");
+        Err(Error::MissingCredentials { provider: PROVIDER.to_string() })
+    }
+}
+
+/// Replaces the text and inline keyboard of a message sent earlier to `chat`.
+pub async fn edit_telegram(
+    client: &reqwest::Client,
+    args: &Args,
+    chat: &str,
+    message_id: i64,
+    message: &str,
+    keyboard: Option<&Value>,
+) -> Result<(), Error> {
+    let mut params = vec![("text", message.to_string())];
+    if let Some(keyboard) = keyboard {
+        params.push(("reply_markup", keyboard.to_string()));
+    }
+    match call_message_method(client, args, "editMessageText", chat, message_id, &params).await {
+        // Telegram refuses edits that don't change anything, which is as good as done
+        Err(Error::NotifierRejected { body, .. }) if body.contains("message is not modified") => Ok(()),
+        result => result,
+    }
+}
+
+/// Calls a Bot API method that acts on one message in `chat`.
+async fn call_message_method(
+    client: &reqwest::Client,
+    args: &Args,
+    method: &str,
+    chat: &str,
+    message_id: i64,
+    params: &[(&str, String)],
+) -> Result<(), Error> {
+    let Some(token) = &args.telegram_token else {
+        return Err(Error::MissingCredentials { provider: PROVIDER.to_string() });
+    };
+    let uri = format!("{}/bot{}/{}", args.telegram_api_url.trim_end_matches('/'), token, method);
+    let mut form = vec![
+        ("chat_id", chat.to_string()),
+        ("message_id", message_id.to_string()),
+    ];
+    form.extend(params.iter().cloned());
+
+    let response = client
+        .post(&uri)
+        .form(&form)
+        .send()
+        .await
+        .map_err(|source| Error::NotifierHttp {
+            provider: PROVIDER.to_string(),
+            source,
+        })?;
+    check_response(PROVIDER, response).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[tokio::test]
+    async fn test_send_telegram_without_credentials() {
+        let args = Args::try_parse_from(["test", "--api-url", "http://test.com", "--telegram-token", "123:abc"]).unwrap();
+        let result = send_telegram(&reqwest::Client::new(), &args, "Door has been opened").await;
+
+        assert!(matches!(result, Err(Error::MissingCredentials { provider }) if provider == "Telegram"));
+    }
+
+    #[tokio::test]
+    async fn test_alert_has_acknowledge_button() {
+        use mockito::{Matcher, Server};
+        use std::time::Duration;
+
+        let mut server = Server::new_async().await;
+        let alert = server.mock("POST", "/bot123:abc/sendMessage")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("chat_id".into(), "42".into()),
+                Matcher::UrlEncoded("text".into(), "ALERT: Garage door has been open for 00:05:00".into()),
+                Matcher::UrlEncoded(
+                    "reply_markup".into(),
+                    r#"{"inline_keyboard":[[{"callback_data":"ack:Garage door","text":"I'm on it"}]]}"#.into(),
+                ),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"ok":true,"result":{"message_id":7}}"#)
+            .create_async()
+            .await;
+        let opened = server.mock("POST", "/bot123:abc/sendMessage")
+            .match_request(|request| !String::from_utf8_lossy(request.body().unwrap()).contains("reply_markup"))
+            .with_status(200)
+            .with_body(r#"{"ok":true,"result":{"message_id":8}}"#)
+            .create_async()
+            .await;
+
+        let args = Args::try_parse_from([
+            "test", "--telegram-token", "123:abc", "--telegram-conversation-id", "42",
+            "--telegram-commands", "--telegram-api-url", &server.url(),
+        ]).unwrap();
+        let notifier = TelegramNotifier::new(reqwest::Client::new(), args);
+        let event = Event::OpenTooLong { open_for: Duration::from_secs(300), reminder: 0 };
+        notifier.send("Garage door", &event).await.unwrap();
+        notifier.send("Garage door", &Event::Opened).await.unwrap();
+
+        alert.assert_async().await;
+        opened.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_send_to_every_chat() {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        server.mock("POST", "/bot123:abc/sendMessage")
+            .match_body(Matcher::UrlEncoded("chat_id".into(), "42".into()))
+            .with_body(r#"{"ok":true,"result":{"message_id":1234,"chat":{"id":42},"text":"hi"}}"#)
+            .create_async()
+            .await;
+        server.mock("POST", "/bot123:abc/sendMessage")
+            .match_body(Matcher::UrlEncoded("chat_id".into(), "-100200".into()))
+            .with_body(r#"{"ok":true,"result":{"message_id":77,"chat":{"id":-100200},"text":"hi"}}"#)
+            .create_async()
+            .await;
+        server.mock("POST", "/bot123:abc/sendMessage")
+            .match_body(Matcher::UrlEncoded("chat_id".into(), "13".into()))
+            .with_status(403)
+            .with_body(r#"{"ok":false,"error_code":403,"description":"Forbidden: bot was blocked by the user"}"#)
+            .create_async()
+            .await;
+
+        let args = Args::try_parse_from([
+            "test", "--telegram-token", "123:abc", "--telegram-api-url", &server.url(),
+            "--telegram-conversation-id", "42", "--telegram-conversation-id", "-100200",
+        ]).unwrap();
+        assert_eq!(send_telegram(&reqwest::Client::new(), &args, "hi").await.unwrap(), vec![1234, 77]);
+
+        // One chat that blocked the bot doesn't stop the others
+        let args = Args::try_parse_from([
+            "test", "--telegram-token", "123:abc", "--telegram-api-url", &server.url(),
+            "--telegram-conversation-id", "42", "--telegram-conversation-id", "13",
+        ]).unwrap();
+        let error = TelegramNotifier::new(reqwest::Client::new(), args).send("Garage", &Event::Opened).await.unwrap_err();
+        match &error {
+            Error::RecipientsFailed { recipients, failures, .. } => {
+                assert_eq!(*recipients, 2);
+                assert_eq!(failures.len(), 1);
+                assert_eq!(failures[0].0, "13");
+            }
+            _ => panic!("unexpected error: {}", error),
+        }
+        assert!(!error.is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_live_message() {
+        use mockito::{Matcher, Server};
+        use std::time::Duration;
+
+        let mut server = Server::new_async().await;
+        let ok = r#"{"ok":true,"result":true}"#;
+        let field = |name: &str, value: &str| Matcher::UrlEncoded(name.into(), value.into());
+
+        let opened = server.mock("POST", "/bot123:abc/sendMessage")
+            .match_body(field("text", "Garage open"))
+            .with_body(r#"{"ok":true,"result":{"message_id":10}}"#)
+            .create_async()
+            .await;
+        let pinned = server.mock("POST", "/bot123:abc/pinChatMessage")
+            .match_body(Matcher::AllOf(vec![field("chat_id", "42"), field("message_id", "10")]))
+            .with_body(ok)
+            .create_async()
+            .await;
+        let reminder = server.mock("POST", "/bot123:abc/editMessageText")
+            .match_body(Matcher::AllOf(vec![
+                field("message_id", "10"),
+                field("text", "Garage open for 00:12:30 \u{2014} reminder 2"),
+            ]))
+            .with_body(ok)
+            .create_async()
+            .await;
+
+        let args = Args::try_parse_from([
+            "test", "--telegram-token", "123:abc", "--telegram-conversation-id", "42",
+            "--telegram-live-message", "--telegram-api-url", &server.url(),
+        ]).unwrap();
+        let notifier = TelegramNotifier::new(reqwest::Client::new(), args);
+        notifier.send("Garage", &Event::Opened).await.unwrap();
+        notifier.send("Garage", &Event::OpenTooLong { open_for: Duration::from_secs(750), reminder: 2 }).await.unwrap();
+        opened.assert_async().await;
+        pinned.assert_async().await;
+        reminder.assert_async().await;
+
+        // Someone deleted the message, so the next reminder starts a new one
+        server.mock("POST", "/bot123:abc/editMessageText")
+            .match_body(field("message_id", "10"))
+            .with_status(400)
+            .with_body(r#"{"ok":false,"error_code":400,"description":"Bad Request: message to edit not found"}"#)
+            .create_async()
+            .await;
+        let replacement = server.mock("POST", "/bot123:abc/sendMessage")
+            .match_body(field("text", "Garage open for 00:27:30 \u{2014} reminder 3"))
+            .with_body(r#"{"ok":true,"result":{"message_id":11}}"#)
+            .create_async()
+            .await;
+        server.mock("POST", "/bot123:abc/unpinChatMessage").with_body(ok).create_async().await;
+        server.mock("POST", "/bot123:abc/pinChatMessage").with_body(ok).create_async().await;
+        notifier.send("Garage", &Event::OpenTooLong { open_for: Duration::from_secs(1650), reminder: 3 }).await.unwrap();
+        replacement.assert_async().await;
+
+        let closed = server.mock("POST", "/bot123:abc/editMessageText")
+            .match_body(Matcher::AllOf(vec![field("message_id", "11"), field("text", "Garage closed after 00:40:02")]))
+            .with_body(ok)
+            .create_async()
+            .await;
+        notifier.send("Garage", &Event::Closed { open_for: Duration::from_secs(2402) }).await.unwrap();
+        closed.assert_async().await;
+        assert!(notifier.live_messages.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_live_message_uses_templates() {
+        use crate::templates::{Templates, parse_event_template, template_notifiers};
+        use mockito::{Matcher, Server};
+        use std::sync::Arc;
+
+        let mut server = Server::new_async().await;
+        let opened = server.mock("POST", "/bot123:abc/sendMessage")
+            .match_body(Matcher::UrlEncoded("text".into(), "\u{1F6AA} Garage opened".into()))
+            .with_body(r#"{"ok":true,"result":{"message_id":10}}"#)
+            .create_async()
+            .await;
+        server.mock("POST", "/bot123:abc/pinChatMessage").with_body(r#"{"ok":true,"result":true}"#).create_async().await;
+
+        let args = Args::try_parse_from([
+            "test", "--telegram-token", "123:abc", "--telegram-conversation-id", "42",
+            "--telegram-live-message", "--telegram-api-url", &server.url(),
+        ]).unwrap();
+        let templates = Arc::new(Templates::new(vec![parse_event_template("door_opened=\u{1F6AA} {door} opened").unwrap()]));
+        let notifiers = template_notifiers(vec![Arc::new(TelegramNotifier::new(reqwest::Client::new(), args))], &templates);
+        notifiers[0].send("Garage", &Event::Opened).await.unwrap();
+        opened.assert_async().await;
     }
-    
-    Ok(())
 }
@@ -0,0 +1,622 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::clock::{Clock, SystemClock};
+use crate::config::Args;
+use crate::error::Error;
+use crate::monitor::{DoorHandle, acknowledge_doors};
+use crate::notifier::check_response;
+use crate::state_machine::{DoorStateMachine, OPEN_REMINDER_INTERVALS, OPEN_REMINDER_REPEAT};
+use crate::utils::{format_duration, parse_duration};
+
+const PROVIDER: &str = "Telegram";
+
+/// Starts the callback data of the "I'm on it" button, followed by the door name.
+pub const ACKNOWLEDGE_PREFIX: &str = "ack:";
+
+/// How long `/mute` without a duration mutes for.
+pub const DEFAULT_MUTE: Duration = Duration::from_secs(30 * 60);
+
+/// How many events `/history` without a count lists.
+const DEFAULT_HISTORY: usize = 10;
+
+const HELP: &str = "Commands:\n\
+/status - door state and how long it has been that way\n\
+/history [n] - the last n events (default 10)\n\
+/mute [30m] - hold back notifications, for e.g. 45s, 30m, 2h or 1d (default 30m)\n\
+/unmute - send notifications again\n\
+/ack [door] - acknowledge open door alerts, which holds back reminders\n\
+/thresholds - when alerts and reminders are sent";
+
+#[derive(Debug, Deserialize)]
+struct Updates {
+    result: Vec<Update>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+    callback_query: Option<CallbackQuery>,
+}
+
+/// A press on an inline keyboard button
+#[derive(Debug, Deserialize)]
+struct CallbackQuery {
+    id: String,
+    from: User,
+    message: Option<Message>,
+    data: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    chat: Chat,
+    from: Option<User>,
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    id: i64,
+    first_name: Option<String>,
+    username: Option<String>,
+}
+
+impl User {
+    /// How acknowledgements name this user.
+    fn display_name(&self) -> String {
+        match (&self.username, &self.first_name) {
+            (Some(username), _) => format!("@{}", username),
+            (None, Some(first_name)) => first_name.clone(),
+            (None, None) => format!("Telegram user {}", self.id),
+        }
+    }
+}
+
+/// Answers commands sent to the Telegram bot, using long polling with
+/// `getUpdates` so no public URL is needed.
+///
+/// Commands act on the live state of every monitored door. Messages are
+/// only answered if their chat or sender is in the allowlist, and the same
+/// goes for presses on the "I'm on it" button under alerts.
+pub struct TelegramBot {
+    client: reqwest::Client,
+    api_url: String,
+    token: String,
+    allowed: Vec<i64>,
+    doors: Vec<DoorHandle>,
+    clock: Arc<dyn Clock>,
+    /// The first update not handled yet
+    offset: i64,
+    poll_timeout: Duration,
+}
+
+impl TelegramBot {
+    pub fn new(
+        client: reqwest::Client,
+        api_url: impl Into<String>,
+        token: impl Into<String>,
+        allowed: Vec<i64>,
+        doors: Vec<DoorHandle>,
+    ) -> Self {
+        Self {
+            client,
+            api_url: api_url.into(),
+            token: token.into(),
+            allowed,
+            doors,
+            clock: Arc::new(SystemClock),
+            offset: 0,
+            poll_timeout: Duration::from_secs(50),
+        }
+    }
+
+    /// The bot configured with the `--telegram-*` options, if `--telegram-commands`
+    /// and a token are set. Without `--telegram-allowed-id` only the conversations
+    /// notifications go to may send commands.
+    pub fn from_args(client: reqwest::Client, args: &Args, doors: Vec<DoorHandle>) -> Option<Self> {
+        if !args.telegram_commands {
+            return None;
+        }
+        let token = args.telegram_token.as_ref()?;
+        let allowed = if args.telegram_allowed_ids.is_empty() {
+            args.telegram_conversation_ids.iter().filter_map(|id| id.parse().ok()).collect()
+        } else {
+            args.telegram_allowed_ids.clone()
+        };
+        Some(Self::new(client, &args.telegram_api_url, token, allowed, doors))
+    }
+
+    /// Uses `clock` for durations and mute times instead of the system clock,
+    /// which should be the same clock the monitors use.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// How long `getUpdates` waits for a message before returning empty.
+    pub fn with_poll_timeout(mut self, poll_timeout: Duration) -> Self {
+        self.poll_timeout = poll_timeout;
+        self
+    }
+
+    fn method_url(&self, method: &str) -> String {
+        format!("{}/bot{}/{}", self.api_url.trim_end_matches('/'), self.token, method)
+    }
+
+    /// Polls for updates until the process exits, waiting a few seconds
+    /// after a failed poll.
+    pub async fn run(mut self) {
+        if self.allowed.is_empty() {
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            eprintln!("[{}] No Telegram chat is allowed to send commands, all of them will be ignored", timestamp);
+        }
+        loop {
+            if let Err(e) = self.poll_once().await {
+                let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                eprintln!("[{}] Failed to get Telegram commands: {}", timestamp, e);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        }
+    }
+
+    /// Fetches one batch of updates and answers the commands in it. Returns
+    /// how many commands were answered.
+    pub async fn poll_once(&mut self) -> Result<usize, Error> {
+        let response = self
+            .client
+            .get(self.method_url("getUpdates"))
+            .query(&[
+                ("offset", self.offset.to_string()),
+                ("timeout", self.poll_timeout.as_secs().to_string()),
+            ])
+            // The server holds the request open for up to `poll_timeout`
+            .timeout(self.poll_timeout + Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(|source| Error::NotifierHttp {
+                provider: PROVIDER.to_string(),
+                source,
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::NotifierRejected {
+                provider: PROVIDER.to_string(),
+                status,
+                body,
+            });
+        }
+        let updates: Updates = response.json().await.map_err(|source| Error::NotifierHttp {
+            provider: PROVIDER.to_string(),
+            source,
+        })?;
+
+        let mut answered = 0;
+        for update in updates.result {
+            self.offset = self.offset.max(update.update_id + 1);
+            if let Some(query) = update.callback_query {
+                self.answer_button(query).await?;
+                answered += 1;
+                continue;
+            }
+            let Some(message) = update.message else {
+                continue;
+            };
+            let Some(text) = message.text.as_deref().filter(|text| text.starts_with('/')) else {
+                continue;
+            };
+
+            let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+            let sender = message.from.as_ref().map(|from| from.id);
+            if !self.is_allowed(message.chat.id, sender) {
+                println!("[{}] Ignoring Telegram command from chat {} that isn't allowed: {}",
+                       timestamp, message.chat.id, text);
+                continue;
+            }
+
+            println!("[{}] Telegram command from chat {}: {}", timestamp, message.chat.id, text);
+            let by = message.from.as_ref().map(User::display_name).unwrap_or_else(|| format!("chat {}", message.chat.id));
+            let reply = self.handle_command(text, &by);
+            self.reply(message.chat.id, &reply).await?;
+            answered += 1;
+        }
+        Ok(answered)
+    }
+
+    /// Acknowledges the alert whose "I'm on it" button was pressed.
+    async fn answer_button(&self, query: CallbackQuery) -> Result<(), Error> {
+        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+        let chat = query.message.as_ref().map(|message| message.chat.id).unwrap_or(query.from.id);
+        let text = if !self.is_allowed(chat, Some(query.from.id)) {
+            println!("[{}] Ignoring Telegram button from user {} that isn't allowed", timestamp, query.from.id);
+            "You aren't allowed to acknowledge alerts".to_string()
+        } else if let Some(door) = query.data.as_deref().and_then(|data| data.strip_prefix(ACKNOWLEDGE_PREFIX)) {
+            let by = query.from.display_name();
+            if acknowledge_doors(&self.doors, Some(door), &by).is_empty() {
+                format!("Nothing to acknowledge for {}", door)
+            } else {
+                format!("Acknowledged, reminders for {} are held back", door)
+            }
+        } else {
+            "Unknown button".to_string()
+        };
+
+        let response = self
+            .client
+            .post(self.method_url("answerCallbackQuery"))
+            .json(&json!({ "callback_query_id": query.id, "text": text }))
+            .send()
+            .await
+            .map_err(|source| Error::NotifierHttp {
+                provider: PROVIDER.to_string(),
+                source,
+            })?;
+        check_response(PROVIDER, response).await
+    }
+
+    fn is_allowed(&self, chat: i64, sender: Option<i64>) -> bool {
+        self.allowed.contains(&chat) || sender.is_some_and(|sender| self.allowed.contains(&sender))
+    }
+
+    async fn reply(&self, chat: i64, text: &str) -> Result<(), Error> {
+        let response = self
+            .client
+            .post(self.method_url("sendMessage"))
+            .json(&json!({ "chat_id": chat, "text": text }))
+            .send()
+            .await
+            .map_err(|source| Error::NotifierHttp {
+                provider: PROVIDER.to_string(),
+                source,
+            })?;
+        check_response(PROVIDER, response).await
+    }
+
+    /// The reply to a command from `by`, e.g. "/mute 2h". A "@botname" suffix
+    /// on the command, as sent in group chats, is ignored.
+    pub fn handle_command(&self, text: &str, by: &str) -> String {
+        let mut words = text.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let command = command.split('@').next().unwrap_or_default();
+        let argument = words.next();
+        let now = self.clock.now();
+
+        match command {
+            "/status" => self.each_door(|machine| status(machine, now)),
+            "/history" => match argument.map(str::parse::<usize>) {
+                None => self.history(DEFAULT_HISTORY, now),
+                Some(Ok(count)) if count > 0 => self.history(count, now),
+                Some(_) => format!("Not a number of events: {}", argument.unwrap_or_default()),
+            },
+            "/mute" => {
+                let duration = match argument.map(parse_duration) {
+                    None => DEFAULT_MUTE,
+                    Some(Some(duration)) => duration,
+                    Some(None) => {
+                        return format!("Not a duration: {}, try e.g. 30m or 2h", argument.unwrap_or_default());
+                    }
+                };
+                let Some(until) = now.checked_add(duration) else {
+                    return format!("Can't mute for that long: {}", argument.unwrap_or_default());
+                };
+                for door in &self.doors {
+                    door.machine.lock().unwrap().mute(until);
+                }
+                format!("Notifications muted for {}", format_duration(duration))
+            }
+            "/unmute" => {
+                for door in &self.doors {
+                    door.machine.lock().unwrap().unmute();
+                }
+                "Notifications unmuted".to_string()
+            }
+            "/ack" => {
+                let acknowledged = acknowledge_doors(&self.doors, argument, by);
+                if acknowledged.is_empty() {
+                    "No open door alerts to acknowledge".to_string()
+                } else {
+                    format!("Acknowledged {}", acknowledged.join(", "))
+                }
+            }
+            "/thresholds" => self.each_door(thresholds),
+            _ => HELP.to_string(),
+        }
+    }
+
+    /// `describe` applied to every door, one paragraph each.
+    fn each_door(&self, describe: impl Fn(&DoorStateMachine) -> String) -> String {
+        let paragraphs: Vec<String> = self.doors.iter().map(|door| describe(&door.machine.lock().unwrap())).collect();
+        if paragraphs.is_empty() {
+            "No doors are monitored".to_string()
+        } else {
+            paragraphs.join("\n\n")
+        }
+    }
+
+    /// The last `count` events across all doors, oldest first.
+    fn history(&self, count: usize, now: Instant) -> String {
+        let mut events: Vec<(Instant, String)> = Vec::new();
+        for door in &self.doors {
+            let machine = door.machine.lock().unwrap();
+            events.extend(machine.state.history.iter().map(|(at, event)| (*at, event.message(machine.door()))));
+        }
+        if events.is_empty() {
+            return "Nothing has happened yet".to_string();
+        }
+        events.sort_by_key(|(at, _)| *at);
+
+        let skip = events.len().saturating_sub(count);
+        events
+            .into_iter()
+            .skip(skip)
+            .map(|(at, message)| format!("{} ago: {}", format_duration(now.saturating_duration_since(at)), message))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn status(machine: &DoorStateMachine, now: Instant) -> String {
+    let state = &machine.state;
+    let mut lines = vec![match state.last_door_state {
+        Some(false) => match state.door_opened_time {
+            Some(opened) => format!("{}: open for {}", machine.door(), format_duration(now.saturating_duration_since(opened))),
+            None => format!("{}: open", machine.door()),
+        },
+        Some(true) => match state.door_closed_time {
+            Some(closed) => format!("{}: closed for {}", machine.door(), format_duration(now.saturating_duration_since(closed))),
+            None => format!("{}: closed", machine.door()),
+        },
+        None => format!("{}: waiting for the first reading", machine.door()),
+    }];
+    if state.flap_detector.is_flapping() {
+        lines.push("Sensor is flapping".to_string());
+    }
+    if let Some(offline_for) = state.sensor_offline_for(now) {
+        lines.push(format!("Sensor offline for {}", format_duration(offline_for)));
+    } else if state.consecutive_sensor_failures > 0 {
+        lines.push(format!("Last {} sensor reads failed", state.consecutive_sensor_failures));
+    }
+    if let Some(by) = machine.acknowledged_by(now) {
+        lines.push(format!("Alert acknowledged by {}", by));
+    }
+    if let Some(muted_for) = machine.muted_for(now) {
+        lines.push(format!("Notifications muted for another {}", format_duration(muted_for)));
+    }
+    lines.join("\n")
+}
+
+fn thresholds(machine: &DoorStateMachine) -> String {
+    let policy = &machine.policy;
+    let state = &machine.state;
+    let reminders = if policy.reminder_backoff {
+        let intervals: Vec<String> = OPEN_REMINDER_INTERVALS.iter().skip(1).map(|interval| format_duration(*interval)).collect();
+        format!("Reminders after {}, then every {}", intervals.join(", "), format_duration(OPEN_REMINDER_REPEAT))
+    } else {
+        "No reminders".to_string()
+    };
+    let offline = match policy.sensor_offline_after {
+        Some(after) => format!("Sensor offline alert after {}", format_duration(after)),
+        None => "No sensor offline alerts".to_string(),
+    };
+    let acknowledged = match policy.acknowledge_snooze {
+        Some(snooze) => format!("Acknowledging holds back reminders for {}", format_duration(snooze)),
+        None => "Acknowledging holds back reminders until the door closes".to_string(),
+    };
+    [
+        format!("{}:", machine.door()),
+        format!("Open too long alert after {}", format_duration(policy.open_too_long)),
+        reminders,
+        acknowledged,
+        offline,
+        format!(
+            "Readings need {} in a row over {} to count",
+            state.debouncer.readings_required,
+            format_duration(state.debouncer.stable_for)
+        ),
+        format!(
+            "Flapping at {} changes in {}",
+            state.flap_detector.threshold,
+            format_duration(state.flap_detector.window)
+        ),
+    ]
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::door::DoorState;
+    use crate::state_machine::{AlertPolicy, Event};
+    use mockito::{Matcher, Server};
+    use std::sync::Mutex;
+    use tokio::sync::mpsc;
+
+    /// A door that is closed at the start, with the receiving end of the
+    /// events it asks its monitor to send.
+    fn door(clock: &Arc<ManualClock>) -> (DoorHandle, mpsc::Receiver<Event>) {
+        let mut machine = DoorStateMachine::new("Garage door", AlertPolicy::default());
+        machine.start(DoorState::Closed, clock.now());
+        let (sender, receiver) = mpsc::channel(8);
+        (DoorHandle::new(Arc::new(Mutex::new(machine)), sender).with_clock(clock.clone()), receiver)
+    }
+
+    #[test]
+    fn test_commands() {
+        let clock = Arc::new(ManualClock::new());
+        let (garage, mut notify) = door(&clock);
+        let bot = TelegramBot::new(reqwest::Client::new(), "http://telegram.test", "123:abc", vec![42], vec![garage.clone()])
+            .with_clock(clock.clone());
+
+        clock.advance(Duration::from_secs(60));
+        garage.machine.lock().unwrap().reading(DoorState::Open, clock.now());
+        clock.advance(Duration::from_secs(90));
+
+        assert_eq!(bot.handle_command("/status", "@alice"), "Garage door: open for 00:01:30");
+        assert_eq!(
+            bot.handle_command("/history@door_bot", "@alice"),
+            "00:02:30 ago: Door Monitor started. Garage door is currently closed\n00:01:30 ago: Garage door has been opened"
+        );
+        assert_eq!(bot.handle_command("/history 1", "@alice"), "00:01:30 ago: Garage door has been opened");
+
+        assert_eq!(bot.handle_command("/mute 2h", "@alice"), "Notifications muted for 02:00:00");
+        assert_eq!(
+            garage.machine.lock().unwrap().muted_for(clock.now()),
+            Some(Duration::from_secs(2 * 60 * 60))
+        );
+        assert!(bot.handle_command("/status", "@alice").ends_with("Notifications muted for another 02:00:00"));
+        assert!(bot.handle_command("/mute later", "@alice").starts_with("Not a duration"));
+        // Past what the clock can count to
+        assert!(bot.handle_command("/mute 200000000000000d", "@alice").starts_with("Can't mute for that long"));
+        assert!(bot.handle_command("/status", "@alice").ends_with("Notifications muted for another 02:00:00"));
+        assert!(bot.handle_command("/mute 9999999999d", "@alice").starts_with("Notifications muted for"));
+        assert_eq!(bot.handle_command("/unmute", "@alice"), "Notifications unmuted");
+        assert!(garage.machine.lock().unwrap().muted_for(clock.now()).is_none());
+
+        assert!(bot.handle_command("/thresholds", "@alice").contains("Open too long alert after 00:00:15"));
+        assert_eq!(bot.handle_command("/start", "@alice"), HELP);
+
+        assert_eq!(bot.handle_command("/ack", "@alice"), "No open door alerts to acknowledge");
+        garage.machine.lock().unwrap().tick(clock.now());
+        assert_eq!(bot.handle_command("/ack shed", "@alice"), "No open door alerts to acknowledge");
+        assert_eq!(bot.handle_command("/ack", "@alice"), "Acknowledged Garage door");
+        assert!(matches!(notify.try_recv(), Ok(Event::Acknowledged { by, .. }) if by == "@alice"));
+        assert!(bot.handle_command("/status", "@alice").contains("Alert acknowledged by @alice"));
+    }
+
+    #[tokio::test]
+    async fn test_poll_answers_allowed_chats_only() {
+        let mut server = Server::new_async().await;
+        let updates = server.mock("GET", "/bot123:abc/getUpdates")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("offset".into(), "0".into()),
+                Matcher::UrlEncoded("timeout".into(), "0".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"ok":true,"result":[
+                {"update_id":7,"message":{"message_id":1,"from":{"id":42},"chat":{"id":42,"type":"private"},"text":"/unmute"}},
+                {"update_id":8,"message":{"message_id":2,"from":{"id":99},"chat":{"id":99,"type":"private"},"text":"/mute 1d"}},
+                {"update_id":9,"message":{"message_id":3,"from":{"id":42},"chat":{"id":42,"type":"private"},"text":"hello"}}
+            ]}"#)
+            .create_async()
+            .await;
+        let reply = server.mock("POST", "/bot123:abc/sendMessage")
+            .match_body(Matcher::Json(json!({ "chat_id": 42, "text": "Notifications unmuted" })))
+            .with_status(200)
+            .with_body(r#"{"ok":true,"result":{}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let clock = Arc::new(ManualClock::new());
+        let (garage, _notify) = door(&clock);
+        let mut bot = TelegramBot::new(reqwest::Client::new(), server.url(), "123:abc", vec![42], vec![garage.clone()])
+            .with_clock(clock.clone())
+            .with_poll_timeout(Duration::ZERO);
+
+        assert_eq!(bot.poll_once().await.unwrap(), 1);
+        updates.assert_async().await;
+        reply.assert_async().await;
+        // The next poll confirms everything up to the last update
+        assert_eq!(bot.offset, 10);
+        // The command from the chat that isn't allowed did nothing
+        assert!(garage.machine.lock().unwrap().muted_for(clock.now()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_button_acknowledges_alert() {
+        let mut server = Server::new_async().await;
+        let updates = server.mock("GET", Matcher::Regex("^/bot123:abc/getUpdates".to_string()))
+            .with_status(200)
+            .with_body(r#"{"ok":true,"result":[
+                {"update_id":3,"callback_query":{"id":"q1","from":{"id":42,"first_name":"Alice"},
+                 "message":{"message_id":1,"chat":{"id":-100,"type":"group"}},"data":"ack:Garage door"}},
+                {"update_id":4,"callback_query":{"id":"q2","from":{"id":99,"first_name":"Mallory"},
+                 "message":{"message_id":1,"chat":{"id":-200,"type":"group"}},"data":"ack:Garage door"}}
+            ]}"#)
+            .create_async()
+            .await;
+        let acknowledged = server.mock("POST", "/bot123:abc/answerCallbackQuery")
+            .match_body(Matcher::Json(json!({
+                "callback_query_id": "q1",
+                "text": "Acknowledged, reminders for Garage door are held back",
+            })))
+            .with_status(200)
+            .with_body(r#"{"ok":true,"result":true}"#)
+            .create_async()
+            .await;
+        let refused = server.mock("POST", "/bot123:abc/answerCallbackQuery")
+            .match_body(Matcher::PartialJson(json!({ "callback_query_id": "q2" })))
+            .with_status(200)
+            .with_body(r#"{"ok":true,"result":true}"#)
+            .create_async()
+            .await;
+
+        let clock = Arc::new(ManualClock::new());
+        let (garage, mut notify) = door(&clock);
+        garage.machine.lock().unwrap().reading(DoorState::Open, clock.now());
+        clock.advance(Duration::from_secs(60));
+        garage.machine.lock().unwrap().tick(clock.now());
+
+        let mut bot = TelegramBot::new(reqwest::Client::new(), server.url(), "123:abc", vec![42], vec![garage.clone()])
+            .with_clock(clock.clone())
+            .with_poll_timeout(Duration::ZERO);
+        bot.poll_once().await.unwrap();
+
+        updates.assert_async().await;
+        acknowledged.assert_async().await;
+        refused.assert_async().await;
+        assert_eq!(garage.machine.lock().unwrap().acknowledged_by(clock.now()), Some("Alice"));
+        assert!(matches!(notify.try_recv(), Ok(Event::Acknowledged { by, .. }) if by == "Alice"));
+        assert!(notify.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_poll_rejected() {
+        let mut server = Server::new_async().await;
+        let mock = server.mock("GET", Matcher::Regex("^/bot123:abc/getUpdates".to_string()))
+            .with_status(401)
+            .with_body(r#"{"ok":false,"error_code":401,"description":"Unauthorized"}"#)
+            .create_async()
+            .await;
+
+        let mut bot = TelegramBot::new(reqwest::Client::new(), server.url(), "123:abc", vec![42], Vec::new())
+            .with_poll_timeout(Duration::ZERO);
+        let error = bot.poll_once().await.unwrap_err();
+
+        mock.assert_async().await;
+        assert!(matches!(error, Error::NotifierRejected { provider, .. } if provider == "Telegram"));
+    }
+
+    #[test]
+    fn test_from_args() {
+        use clap::Parser;
+
+        let args = Args::try_parse_from(["test", "--telegram-token", "123:abc", "--telegram-conversation-id", "42"]).unwrap();
+        assert!(TelegramBot::from_args(reqwest::Client::new(), &args, Vec::new()).is_none());
+
+        let args = Args::try_parse_from([
+            "test", "--telegram-commands", "--telegram-token", "123:abc", "--telegram-conversation-id", "42",
+        ]).unwrap();
+        let bot = TelegramBot::from_args(reqwest::Client::new(), &args, Vec::new()).unwrap();
+        assert_eq!(bot.allowed, vec![42]);
+
+        let args = Args::try_parse_from([
+            "test", "--telegram-commands", "--telegram-token", "123:abc", "--telegram-conversation-id", "42",
+            "--telegram-allowed-id", "7", "--telegram-allowed-id", "-100123",
+        ]).unwrap();
+        let bot = TelegramBot::from_args(reqwest::Client::new(), &args, Vec::new()).unwrap();
+        assert_eq!(bot.allowed, vec![7, -100123]);
+    }
+}
@@ -0,0 +1,439 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::config::{Args, DEFAULT_DOOR_NAME};
+use crate::door::DoorState;
+use crate::error::Error;
+use crate::notifier::Notifier;
+use crate::state_machine::Event;
+use crate::utils::{format_duration, hostname};
+
+/// The placeholders a template can use.
+pub const PLACEHOLDERS: [&str; 9] = [
+    "door",
+    "state",
+    "duration",
+    "opened_at",
+    "reminder_number",
+    "hostname",
+    "message",
+    "event",
+    "channel",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Placeholder(&'static str),
+}
+
+/// A message with `{name}` placeholders, e.g. "{door} has been open for
+/// {duration}". `{{` and `}}` stand for literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parses `text`, failing on unknown placeholders and unmatched braces.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("'{{{}' is missing its closing '}}'", name)),
+                        }
+                    }
+                    let Some(placeholder) = PLACEHOLDERS.iter().find(|placeholder| **placeholder == name) else {
+                        return Err(format!("unknown placeholder {{{}}}, expected one of {}", name, PLACEHOLDERS.join(", ")));
+                    };
+                    if !literal.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Placeholder(placeholder));
+                }
+                '}' => return Err("'}' without an opening '{', use '}}' for a brace".to_string()),
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Text(literal));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Fills in the placeholders with `value`.
+    pub fn render(&self, value: impl Fn(&str) -> String) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Placeholder(name) => value(name),
+            })
+            .collect()
+    }
+}
+
+/// A `--template` value: the template for one kind of event, through one
+/// channel or all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventTemplate {
+    /// An `Event::kind`, e.g. "door_opened"
+    pub event: String,
+    /// `None` for every channel without a template of its own
+    pub channel: Option<String>,
+    pub template: Template,
+}
+
+/// Parses a `--template` value in the form `EVENT=TEMPLATE` or
+/// `EVENT:CHANNEL=TEMPLATE`.
+pub fn parse_event_template(value: &str) -> Result<EventTemplate, String> {
+    let Some((key, template)) = value.split_once('=') else {
+        return Err(format!("expected EVENT=TEMPLATE or EVENT:CHANNEL=TEMPLATE, got '{}'", value));
+    };
+    let (event, channel) = match key.split_once(':') {
+        Some((event, channel)) if !channel.trim().is_empty() => (event.trim(), Some(channel.trim().to_string())),
+        Some(_) => return Err(format!("missing channel in '{}'", key)),
+        None => (key.trim(), None),
+    };
+    let kinds: Vec<&str> = sample_events().iter().map(Event::kind).collect();
+    if !kinds.contains(&event) {
+        return Err(format!("unknown event '{}', expected one of {}", event, kinds.join(", ")));
+    }
+    Ok(EventTemplate {
+        event: event.to_string(),
+        channel,
+        template: Template::parse(template)?,
+    })
+}
+
+/// User templates for messages, by event and channel. Events without a
+/// template keep their usual message.
+pub struct Templates {
+    templates: Vec<EventTemplate>,
+    hostname: String,
+}
+
+impl Templates {
+    pub fn new(templates: Vec<EventTemplate>) -> Self {
+        Self {
+            templates,
+            hostname: hostname(),
+        }
+    }
+
+    /// The templates from `--template`.
+    pub fn from_args(args: &Args) -> Self {
+        Self::new(args.templates.clone())
+    }
+
+    /// Fills in `{hostname}` with `hostname` instead of this machine's name.
+    pub fn with_hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = hostname.into();
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    /// The template for `kind` through `channel`: the channel's own if it
+    /// has one, otherwise the one for every channel.
+    pub fn find(&self, kind: &str, channel: &str) -> Option<&Template> {
+        let matching = |template: &&EventTemplate| template.event == kind;
+        self.templates
+            .iter()
+            .filter(matching)
+            .find(|template| template.channel.as_ref().is_some_and(|name| name.eq_ignore_ascii_case(channel)))
+            .or_else(|| self.templates.iter().filter(matching).find(|template| template.channel.is_none()))
+            .map(|template| &template.template)
+    }
+
+    /// The message for `event` about `door` through `channel`, which
+    /// happened at `at`.
+    pub fn message(&self, channel: &str, door: &str, event: &Event, at: DateTime<Utc>) -> String {
+        match self.find(event.kind(), channel) {
+            Some(template) => template.render(|name| self.value(name, channel, door, event, at)),
+            None => event.message(door),
+        }
+    }
+
+    /// `event` with its message for `channel` replaced by the template, if
+    /// there is one.
+    pub fn apply(&self, channel: &str, door: &str, event: &Event, at: DateTime<Utc>) -> Event {
+        if self.find(event.kind(), channel).is_none() {
+            return event.clone();
+        }
+        Event::Templated {
+            event: Box::new(event.base().clone()),
+            message: self.message(channel, door, event.base(), at),
+        }
+    }
+
+    fn value(&self, name: &str, channel: &str, door: &str, event: &Event, at: DateTime<Utc>) -> String {
+        match name {
+            "door" => door.to_string(),
+            "state" => event.door_state().map(|state| state.to_string()).unwrap_or_default(),
+            "duration" => event.open_for().or(event.offline_for()).map(format_duration).unwrap_or_default(),
+            "opened_at" => match event {
+                Event::Opened => Some(at),
+                _ => event
+                    .open_for()
+                    .and_then(|open_for| chrono::Duration::from_std(open_for).ok())
+                    .map(|open_for| at - open_for),
+            }
+            .map(|opened_at| opened_at.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+            .unwrap_or_default(),
+            "reminder_number" => event.reminder().map(|reminder| reminder.to_string()).unwrap_or_default(),
+            "hostname" => self.hostname.clone(),
+            "message" => event.message(door),
+            "event" => event.kind().to_string(),
+            "channel" => channel.to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// Every event's message filled in with sample data, once for the
+    /// channels without a template of their own and once for each channel
+    /// that has one.
+    pub fn preview(&self, door: &str, now: DateTime<Utc>) -> String {
+        let mut lines = Vec::new();
+        for event in sample_events() {
+            lines.push(event.kind().to_string());
+            lines.push(format!("  {}", self.message("", door, &event, now)));
+            let mut channels: Vec<&str> = self
+                .templates
+                .iter()
+                .filter(|template| template.event == event.kind())
+                .filter_map(|template| template.channel.as_deref())
+                .collect();
+            channels.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+            for channel in channels {
+                lines.push(format!("  {}: {}", channel, self.message(channel, door, &event, now)));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// Prints every message filled in with sample data, for `render-templates`.
+pub fn render_templates(args: &Args) {
+    let door = args
+        .all_doors()
+        .first()
+        .map_or_else(|| DEFAULT_DOOR_NAME.to_string(), |door| door.name.clone());
+    println!("{}", Templates::from_args(args).preview(&door, Utc::now()));
+}
+
+/// One of every kind of event, with made up details.
+pub fn sample_events() -> Vec<Event> {
+    let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+    vec![
+        Event::Started { state: DoorState::Closed },
+        Event::Opened,
+        Event::Closed { open_for: minutes(12) + Duration::from_secs(30) },
+        Event::OpenTooLong { open_for: minutes(5), reminder: 0 },
+        Event::OpenTooLong { open_for: minutes(20), reminder: 1 },
+        Event::FlappingStarted { transitions: 6, window: minutes(1) },
+        Event::FlappingSettled { state: DoorState::Closed },
+        Event::SensorFailing { error: "HTTP error: 404 Not Found".to_string() },
+        Event::SensorOffline { offline_for: minutes(30), failures: 180, reminder: 0 },
+        Event::SensorOnline { offline_for: minutes(42), state: DoorState::Closed },
+        Event::Acknowledged { by: "Alice".to_string(), open_for: minutes(7) },
+        Event::ChannelFailing {
+            channel: "SMS".to_string(),
+            error: "voip.ms refused the message: invalid_credentials (the API username or password is wrong)".to_string(),
+        },
+        Event::Missed {
+            notifications: vec![
+                "10:15:00 UTC Door has been opened".to_string(),
+                "10:35:00 UTC Door is now closed after being open for 00:20:00".to_string(),
+            ],
+        },
+        Event::LimitReached {
+            channel: "SMS".to_string(),
+            limit: "daily quota (20 messages)".to_string(),
+        },
+    ]
+}
+
+/// Sends through `inner` with the message from a template where there is one.
+pub struct TemplatedNotifier {
+    inner: Arc<dyn Notifier>,
+    templates: Arc<Templates>,
+}
+
+impl TemplatedNotifier {
+    pub fn new(inner: Arc<dyn Notifier>, templates: Arc<Templates>) -> Self {
+        Self { inner, templates }
+    }
+}
+
+#[async_trait]
+impl Notifier for TemplatedNotifier {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+        self.send_at(door, event, Utc::now()).await
+    }
+
+    async fn send_at(&self, door: &str, event: &Event, at: DateTime<Utc>) -> Result<(), Error> {
+        let event = self.templates.apply(self.name(), door, event, at);
+        self.inner.send_at(door, &event, at).await
+    }
+
+    fn recipients(&self) -> Vec<String> {
+        self.inner.recipients()
+    }
+
+    async fn send_to(&self, recipient: &str, door: &str, event: &Event, at: DateTime<Utc>) -> Result<(), Error> {
+        let event = self.templates.apply(self.name(), door, event, at);
+        self.inner.send_to(recipient, door, &event, at).await
+    }
+}
+
+/// Wraps every notifier in a `TemplatedNotifier`, unless there are no templates.
+pub fn template_notifiers(notifiers: Vec<Arc<dyn Notifier>>, templates: &Arc<Templates>) -> Vec<Arc<dyn Notifier>> {
+    if templates.is_empty() {
+        return notifiers;
+    }
+    notifiers
+        .into_iter()
+        .map(|notifier| -> Arc<dyn Notifier> { Arc::new(TemplatedNotifier::new(notifier, templates.clone())) })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use clap::Parser;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_parse_template() {
+        let template = Template::parse("{door} open for {duration} {{not a placeholder}}").unwrap();
+        let rendered = template.render(|name| name.to_uppercase());
+        assert_eq!(rendered, "DOOR open for DURATION {not a placeholder}");
+
+        assert!(Template::parse("{door").unwrap_err().contains("missing its closing"));
+        assert!(Template::parse("{colour}").unwrap_err().starts_with("unknown placeholder {colour}"));
+        assert!(Template::parse("door}").is_err());
+    }
+
+    #[test]
+    fn test_parse_event_template() {
+        let template = parse_event_template("open_too_long:SMS=ALERT {door} on {hostname}").unwrap();
+        assert_eq!(template.event, "open_too_long");
+        assert_eq!(template.channel, Some("SMS".to_string()));
+        assert_eq!(parse_event_template("door_opened={door}: {state}").unwrap().channel, None);
+
+        assert!(parse_event_template("door_opened").is_err());
+        assert!(parse_event_template("door_slammed={door}").unwrap_err().starts_with("unknown event 'door_slammed'"));
+        assert!(parse_event_template("door_opened:={door}").is_err());
+    }
+
+    #[test]
+    fn test_message_placeholders() {
+        let args = Args::try_parse_from([
+            "test",
+            "--template", "open_too_long={door} is {state}, open since {opened_at} ({duration}), reminder {reminder_number}",
+            "--template", "open_too_long:SMS=[{hostname}] {message}",
+        ]).unwrap();
+        let templates = Templates::from_args(&args).with_hostname("pi-zero");
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 10, 35, 0).unwrap();
+        let event = Event::OpenTooLong { open_for: Duration::from_secs(20 * 60), reminder: 1 };
+
+        assert_eq!(
+            templates.message("Telegram", "Garage", &event, now),
+            "Garage is open, open since 2026-10-17 10:15:00 UTC (00:20:00), reminder 1"
+        );
+        assert_eq!(
+            templates.message("sms", "Garage", &event, now),
+            "[pi-zero] REMINDER: Garage still open for 00:20:00"
+        );
+        // Events without a template keep their usual message
+        assert_eq!(templates.message("SMS", "Garage", &Event::Opened, now), "Garage has been opened");
+        let opened = Templates::new(vec![parse_event_template("door_opened={door} opened at {opened_at}").unwrap()]);
+        assert_eq!(
+            opened.message("SMS", "Garage", &Event::Opened, now),
+            "Garage opened at 2026-10-17 10:35:00 UTC"
+        );
+
+        let applied = templates.apply("SMS", "Garage", &event, now);
+        assert_eq!(applied.kind(), "open_too_long");
+        assert_eq!(applied.base(), &event);
+        assert_eq!(applied.message("Garage"), "[pi-zero] REMINDER: Garage still open for 00:20:00");
+    }
+
+    #[test]
+    fn test_preview() {
+        let args = Args::try_parse_from([
+            "test", "--template", "door_opened:Telegram=\u{1F6AA} {door} opened",
+        ]).unwrap();
+        let preview = Templates::from_args(&args).preview("Garage", Utc::now());
+        let lines: Vec<&str> = preview.lines().collect();
+
+        assert_eq!(lines[0], "started");
+        assert_eq!(lines[1], "  Door Monitor started. Garage is currently closed");
+        assert_eq!(&lines[2..5], ["door_opened", "  Garage has been opened", "  Telegram: \u{1F6AA} Garage opened"]);
+        // Every kind of event is shown
+        for event in sample_events() {
+            assert!(lines.contains(&event.kind()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_templated_notifier() {
+        struct Recording(Mutex<Vec<String>>);
+
+        #[async_trait]
+        impl Notifier for Recording {
+            fn name(&self) -> &str {
+                "SMS"
+            }
+
+            async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+                self.0.lock().unwrap().push(event.message(door));
+                Ok(())
+            }
+        }
+
+        let sms = Arc::new(Recording(Mutex::new(Vec::new())));
+        let templates = Arc::new(Templates::new(vec![parse_event_template("door_opened={door} is {state}").unwrap()]));
+        let notifiers = template_notifiers(vec![sms.clone()], &templates);
+
+        notifiers[0].send("Garage", &Event::Opened).await.unwrap();
+        notifiers[0].send("Garage", &Event::Closed { open_for: Duration::from_secs(60) }).await.unwrap();
+        assert_eq!(*sms.0.lock().unwrap(), vec!["Garage is open", "Garage is now closed after being open for 00:01:00"]);
+
+        // A notification sent later from the outbox keeps the time it happened
+        let templates = Arc::new(Templates::new(vec![parse_event_template("door_opened={door} opened at {opened_at}").unwrap()]));
+        let notifiers = template_notifiers(vec![sms.clone()], &templates);
+        let outbox = crate::outbox::Outbox::in_memory();
+        let opened_at = Utc.with_ymd_and_hms(2026, 10, 17, 10, 15, 0).unwrap();
+        outbox.enqueue("Garage", &Event::Opened, &["SMS".into()], opened_at);
+        outbox.deliver(&notifiers, "Garage", opened_at + chrono::Duration::minutes(5)).await;
+        assert_eq!(sms.0.lock().unwrap()[2], "Garage opened at 2026-10-17 10:15:00 UTC");
+    }
+}
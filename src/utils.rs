@@ -1,5 +1,26 @@
 use std::time::Duration;
 
+/// Builds the HTTP client shared by sensors and notifications, so a device
+/// that stops answering can't hang a request forever.
+pub fn http_client(connect_timeout: Duration, request_timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(connect_timeout)
+        .timeout(request_timeout)
+        .build()
+        .expect("Failed to build HTTP client")
+}
+
+/// This machine's name, to tell apart messages from monitors on different machines.
+pub fn hostname() -> String {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
 pub fn format_duration(duration: Duration) -> String {
     let total_seconds = duration.as_secs();
     let days = total_seconds / 86400;
@@ -14,6 +35,23 @@ pub fn format_duration(duration: Duration) -> String {
     }
 }
 
+/// Parses durations like "45s", "30m", "2h" or "1d". A plain number is minutes.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => text.split_at(index),
+        None => (text, "m"),
+    };
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return None,
+    };
+    Some(Duration::from_secs(number.checked_mul(seconds)?)).filter(|duration| !duration.is_zero())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -60,4 +98,16 @@ mod tests {
         let duration = Duration::from_secs(365 * 86400 + 12 * 3600 + 30 * 60 + 45);
         assert_eq!(format_duration(duration), "365d 12:30:45");
     }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("45s"), Some(Duration::from_secs(45)));
+        assert_eq!(parse_duration("30m"), Some(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("30"), Some(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(2 * 60 * 60)));
+        assert_eq!(parse_duration("1d"), Some(Duration::from_secs(24 * 60 * 60)));
+        assert_eq!(parse_duration("0m"), None);
+        assert_eq!(parse_duration("2w"), None);
+        assert_eq!(parse_duration("soon"), None);
+    }
 }
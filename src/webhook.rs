@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::Router;
+use axum::extract::{Query, Request, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use chrono::Utc;
+use serde::Deserialize;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+use crate::door::DoorState;
+use crate::monitor::{DoorHandle, acknowledge_doors};
+
+/// Senders for pushing door readings into each door's monitor, keyed by door name.
+pub type DoorEventSenders = HashMap<String, mpsc::Sender<DoorState>>;
+
+#[derive(Debug, Deserialize)]
+pub struct DoorEvent {
+    pub door: String,
+    pub state: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Acknowledgement {
+    /// Only acknowledge this door, instead of every door with an alert
+    pub door: Option<String>,
+    /// Who is dealing with it, named in the message to everyone else
+    pub by: Option<String>,
+}
+
+/// The `token` query parameter, for callers that can't set headers
+#[derive(Debug, Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+#[derive(Clone)]
+struct ListenerState {
+    senders: Arc<DoorEventSenders>,
+    doors: Arc<Vec<DoorHandle>>,
+    token: Option<Arc<str>>,
+}
+
+/// Builds the webhook router.
+///
+/// `GET` or `POST /event?door=<name>&state=<open|closed>` feeds a reading to the
+/// named door, which is what a Shelly "action URL" or Gen2 webhook calls. Door
+/// names are matched case-insensitively.
+///
+/// `POST /acknowledge?door=<name>&by=<who>` acknowledges open door alerts,
+/// holding back their reminders. Without `door` every door's alert is acknowledged.
+///
+/// With a `token`, both answer 401 unless the request has it in an
+/// `Authorization: Bearer <token>` header or a `token` query parameter.
+pub fn router(senders: DoorEventSenders, doors: Vec<DoorHandle>, token: Option<String>) -> Router {
+    let senders: Arc<DoorEventSenders> = Arc::new(
+        senders
+            .into_iter()
+            .map(|(name, sender)| (name.to_lowercase(), sender))
+            .collect(),
+    );
+    let state = ListenerState {
+        senders,
+        doors: Arc::new(doors),
+        token: token.map(Arc::from),
+    };
+
+    Router::new()
+        .route("/event", get(handle_event).post(handle_event))
+        .route("/acknowledge", post(handle_acknowledge))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .with_state(state)
+}
+
+async fn require_token(
+    State(state): State<ListenerState>,
+    Query(query): Query<TokenQuery>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(expected) = &state.token else {
+        return next.run(request).await;
+    };
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let given = bearer.or(query.token.as_deref());
+    if given.is_some_and(|given| tokens_match(given, expected)) {
+        return next.run(request).await;
+    }
+
+    let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+    eprintln!("[{}] Rejected {} {} without a valid token", timestamp, request.method(), request.uri().path());
+    (StatusCode::UNAUTHORIZED, "missing or wrong token".to_string()).into_response()
+}
+
+/// Compares the whole of both tokens, so the time taken doesn't give away
+/// how much of a guess was right.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
+
+async fn handle_acknowledge(
+    State(state): State<ListenerState>,
+    Query(acknowledgement): Query<Acknowledgement>,
+) -> (StatusCode, String) {
+    let door = acknowledgement.door.as_deref();
+    if let Some(door) = door
+        && !state.doors.iter().any(|handle| handle.door().eq_ignore_ascii_case(door))
+    {
+        return (StatusCode::NOT_FOUND, format!("unknown door '{}'", door));
+    }
+
+    let by = acknowledgement.by.as_deref().unwrap_or("someone using the HTTP API");
+    let acknowledged = acknowledge_doors(&state.doors, door, by);
+    if acknowledged.is_empty() {
+        (StatusCode::CONFLICT, "no open door alerts to acknowledge".to_string())
+    } else {
+        (StatusCode::OK, format!("acknowledged {}", acknowledged.join(", ")))
+    }
+}
+
+async fn handle_event(
+    State(ListenerState { senders, .. }): State<ListenerState>,
+    Query(event): Query<DoorEvent>,
+) -> (StatusCode, String) {
+    let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+
+    // An unknown state isn't a reading, and would pass as the sensor being back online
+    let door_state = match event.state.parse::<DoorState>() {
+        Ok(DoorState::Unknown) => Err(format!("'{}' is not a door state, send open or closed", event.state)),
+        parsed => parsed,
+    };
+    let door_state = match door_state {
+        Ok(door_state) => door_state,
+        Err(e) => {
+            eprintln!("[{}] Webhook rejected event for {}: {}", timestamp, event.door, e);
+            return (StatusCode::BAD_REQUEST, e);
+        }
+    };
+
+    let Some(sender) = senders.get(&event.door.to_lowercase()) else {
+        eprintln!("[{}] Webhook event for unknown door {}", timestamp, event.door);
+        return (StatusCode::NOT_FOUND, format!("unknown door '{}'", event.door));
+    };
+
+    println!("[{}] Webhook event: {} is {}", timestamp, event.door, door_state);
+    match sender.send(door_state).await {
+        Ok(()) => (StatusCode::OK, "ok".to_string()),
+        Err(_) => (StatusCode::SERVICE_UNAVAILABLE, format!("{} is not being monitored", event.door)),
+    }
+}
+
+/// Serves the webhook router on an already bound listener until the process exits.
+pub async fn serve(
+    listener: TcpListener,
+    senders: DoorEventSenders,
+    doors: Vec<DoorHandle>,
+    token: Option<String>,
+) -> std::io::Result<()> {
+    axum::serve(listener, router(senders, doors, token)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn start_server(door: &str) -> (String, mpsc::Receiver<DoorState>) {
+        let (sender, receiver) = mpsc::channel(8);
+        let mut senders = DoorEventSenders::new();
+        senders.insert(door.to_string(), sender);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, senders, Vec::new(), None));
+        (url, receiver)
+    }
+
+    #[tokio::test]
+    async fn test_post_event_is_forwarded_to_door() {
+        let (url, mut receiver) = start_server("Garage").await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/event?door=garage&state=open", url))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(receiver.recv().await, Some(DoorState::Open));
+    }
+
+    #[tokio::test]
+    async fn test_get_event_is_forwarded_to_door() {
+        let (url, mut receiver) = start_server("Side door").await;
+
+        let response = reqwest::Client::new()
+            .get(format!("{}/event?door=Side%20door&state=closed", url))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(receiver.recv().await, Some(DoorState::Closed));
+    }
+
+    #[tokio::test]
+    async fn test_quick_open_close_cycle_keeps_both_events() {
+        let (url, mut receiver) = start_server("Garage").await;
+        let client = reqwest::Client::new();
+
+        for state in ["open", "closed"] {
+            client
+                .post(format!("{}/event?door=garage&state={}", url, state))
+                .send()
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(receiver.recv().await, Some(DoorState::Open));
+        assert_eq!(receiver.recv().await, Some(DoorState::Closed));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_door_is_not_found() {
+        let (url, _receiver) = start_server("Garage").await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/event?door=shed&state=open", url))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status().as_u16(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_state_is_bad_request() {
+        let (url, _receiver) = start_server("Garage").await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/event?door=garage&state=ajar", url))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status().as_u16(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_state_is_bad_request() {
+        let (url, mut receiver) = start_server("Garage").await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/event?door=garage&state=unknown", url))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status().as_u16(), 400);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_acknowledge() {
+        use crate::clock::{Clock, ManualClock};
+        use crate::state_machine::{AlertPolicy, DoorStateMachine, Event};
+        use std::sync::Mutex;
+        use std::time::Duration;
+
+        let clock = Arc::new(ManualClock::new());
+        let mut machine = DoorStateMachine::new("Garage", AlertPolicy::default());
+        machine.start(DoorState::Open, clock.now());
+        let (sender, mut notify) = mpsc::channel(8);
+        let handle = DoorHandle::new(Arc::new(Mutex::new(machine)), sender).with_clock(clock.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, DoorEventSenders::new(), vec![handle.clone()], None));
+        let client = reqwest::Client::new();
+        let acknowledge = |query: &'static str| client.post(format!("{}/acknowledge{}", url, query)).send();
+
+        assert_eq!(acknowledge("?door=shed").await.unwrap().status().as_u16(), 404);
+        // No alert yet
+        assert_eq!(acknowledge("?door=garage").await.unwrap().status().as_u16(), 409);
+
+        clock.advance(Duration::from_secs(60));
+        handle.machine.lock().unwrap().tick(clock.now());
+        clock.advance(Duration::from_secs(30));
+        let response = acknowledge("?by=Bob").await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.text().await.unwrap(), "acknowledged Garage");
+        // Timed by the monitor's clock, not the system's
+        assert!(matches!(
+            notify.recv().await,
+            Some(Event::Acknowledged { by, open_for }) if by == "Bob" && open_for == Duration::from_secs(90)
+        ));
+        assert_eq!(acknowledge("").await.unwrap().status().as_u16(), 409);
+    }
+
+    #[tokio::test]
+    async fn test_token_is_required() {
+        let (sender, mut receiver) = mpsc::channel(8);
+        let mut senders = DoorEventSenders::new();
+        senders.insert("Garage".to_string(), sender);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, senders, Vec::new(), Some("s3cret".to_string())));
+        let client = reqwest::Client::new();
+
+        for path in ["/event?door=garage&state=open", "/acknowledge", "/event?door=garage&state=open&token=guess"] {
+            let response = client.post(format!("{}{}", url, path)).send().await.unwrap();
+            assert_eq!(response.status().as_u16(), 401, "{}", path);
+        }
+        let response = client.post(format!("{}/acknowledge", url)).bearer_auth("guess").send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+
+        let response = client.get(format!("{}/event?door=garage&state=open&token=s3cret", url)).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(receiver.recv().await, Some(DoorState::Open));
+        let response = client
+            .post(format!("{}/event?door=garage&state=closed", url))
+            .bearer_auth("s3cret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(receiver.recv().await, Some(DoorState::Closed));
+        // No door has an alert, but the token got the request through
+        let response = client.post(format!("{}/acknowledge?token=s3cret", url)).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 409);
+    }
+}
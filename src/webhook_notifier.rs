@@ -0,0 +1,210 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+
+use crate::error::Error;
+use crate::notifier::{Notifier, check_response};
+use crate::state_machine::Event;
+use crate::utils::format_duration;
+
+const PROVIDER: &str = "Webhook";
+
+/// POSTs a JSON document for every event to a URL, for routing alerts into
+/// other tools.
+///
+/// Without a template the body is a fixed document with the event type,
+/// door, state, message, open duration, timestamps and backoff index. With a
+/// template, `{name}` placeholders in it are replaced by the same values.
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+    headers: Vec<(String, String)>,
+    template: Option<String>,
+}
+
+impl WebhookNotifier {
+    pub fn new(client: reqwest::Client, url: impl Into<String>) -> Self {
+        Self {
+            client,
+            url: url.into(),
+            headers: Vec::new(),
+            template: None,
+        }
+    }
+
+    /// Adds headers to every request, e.g. for authentication.
+    pub fn with_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Sends `template`, with its placeholders filled in, instead of the default document.
+    pub fn with_template(mut self, template: Option<String>) -> Self {
+        self.template = template;
+        self
+    }
+
+    /// The request body for `event` about `door`, as of `now`.
+    pub fn body(&self, door: &str, event: &Event, now: DateTime<Utc>) -> Result<Value, Error> {
+        let open_for = event.open_for();
+        let opened_at = open_for
+            .and_then(|open_for| chrono::Duration::from_std(open_for).ok())
+            .map(|open_for| (now - open_for).to_rfc3339());
+
+        let Some(template) = &self.template else {
+            return Ok(json!({
+                "event": event.kind(),
+                "door": door,
+                "state": event.door_state().map(|state| state.to_string()),
+                "message": event.message(door),
+                "open_seconds": open_for.map(|open_for| open_for.as_secs()),
+                "opened_at": opened_at,
+                "timestamp": now.to_rfc3339(),
+                "backoff_index": event.reminder(),
+            }));
+        };
+
+        let values = [
+            ("event", event.kind().to_string()),
+            ("door", door.to_string()),
+            ("state", event.door_state().map(|state| state.to_string()).unwrap_or_default()),
+            ("message", event.message(door)),
+            ("open_seconds", open_for.map(|open_for| open_for.as_secs().to_string()).unwrap_or_default()),
+            ("duration", open_for.map(format_duration).unwrap_or_default()),
+            ("opened_at", opened_at.unwrap_or_default()),
+            ("timestamp", now.to_rfc3339()),
+            ("backoff_index", event.reminder().map(|index| index.to_string()).unwrap_or_default()),
+        ];
+        let rendered = values.iter().fold(template.clone(), |body, (name, value)| {
+            body.replace(&format!("{{{}}}", name), &json_escape(value))
+        });
+        serde_json::from_str(&rendered).map_err(|e| {
+            Error::InvalidConfig(format!("Webhook template is not valid JSON once filled in: {}", e))
+        })
+    }
+}
+
+/// Escapes `value` for use inside a JSON string, without the surrounding quotes.
+fn json_escape(value: &str) -> String {
+    let quoted = Value::String(value.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "Webhook"
+    }
+
+    async fn send(&self, door: &str, event: &Event) -> Result<(), Error> {
+        let body = self.body(door, event, Utc::now())?;
+
+        let mut request = self.client.post(&self.url).json(&body);
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        let response = request.send().await.map_err(|source| Error::NotifierHttp {
+            provider: PROVIDER.to_string(),
+            source,
+        })?;
+        check_response(PROVIDER, response).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_posts_default_document() {
+        let mut server = Server::new_async().await;
+        let mock = server.mock("POST", "/hooks/door")
+            .match_header("content-type", "application/json")
+            .match_header("authorization", "Bearer secret")
+            .match_body(Matcher::PartialJson(json!({
+                "event": "open_too_long",
+                "door": "Garage door",
+                "state": "open",
+                "message": "REMINDER: Garage door still open for 00:20:00",
+                "open_seconds": 1200,
+                "backoff_index": 2,
+            })))
+            .with_status(204)
+            .create_async()
+            .await;
+
+        let notifier = WebhookNotifier::new(reqwest::Client::new(), format!("{}/hooks/door", server.url()))
+            .with_headers(vec![("Authorization".to_string(), "Bearer secret".to_string())]);
+        let event = Event::OpenTooLong { open_for: Duration::from_secs(1200), reminder: 2 };
+
+        notifier.send("Garage door", &event).await.unwrap();
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_default_document_timestamps() {
+        let notifier = WebhookNotifier::new(reqwest::Client::new(), "http://hooks.test");
+        let now = DateTime::parse_from_rfc3339("2025-06-28T14:30:00Z").unwrap().with_timezone(&Utc);
+
+        let body = notifier.body("Garage door", &Event::Closed { open_for: Duration::from_secs(90) }, now).unwrap();
+        assert_eq!(body["timestamp"], "2025-06-28T14:30:00+00:00");
+        assert_eq!(body["opened_at"], "2025-06-28T14:28:30+00:00");
+        assert_eq!(body["backoff_index"], Value::Null);
+
+        let body = notifier.body("Garage door", &Event::SensorFailing { error: "HTTP error: 404 Not Found".to_string() }, now).unwrap();
+        assert_eq!(body["event"], "sensor_error");
+        assert_eq!(body["state"], Value::Null);
+        assert_eq!(body["opened_at"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_posts_template() {
+        let mut server = Server::new_async().await;
+        let mock = server.mock("POST", "/")
+            .match_body(Matcher::Json(json!({
+                "text": "Garage \"back\" door has been opened",
+                "kind": "door_opened",
+                "state": "open",
+            })))
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let template = r#"{"text": "{message}", "kind": "{event}", "state": "{state}"}"#;
+        let notifier = WebhookNotifier::new(reqwest::Client::new(), server.url())
+            .with_template(Some(template.to_string()));
+
+        notifier.send("Garage \"back\" door", &Event::Opened).await.unwrap();
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_invalid_template() {
+        let notifier = WebhookNotifier::new(reqwest::Client::new(), "http://hooks.test")
+            .with_template(Some("{door} opened".to_string()));
+
+        let result = notifier.body("Garage door", &Event::Opened, Utc::now());
+        assert!(matches!(result, Err(Error::InvalidConfig(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rejected() {
+        let mut server = Server::new_async().await;
+        let mock = server.mock("POST", "/")
+            .with_status(401)
+            .with_body("bad token")
+            .create_async()
+            .await;
+
+        let notifier = WebhookNotifier::new(reqwest::Client::new(), server.url());
+        let result = notifier.send("Garage door", &Event::Opened).await;
+
+        mock.assert_async().await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Webhook rejected the message with HTTP 401 Unauthorized: bad token"
+        );
+    }
+}
@@ -16,7 +16,7 @@ async fn test_door_monitor_integration() {
         .create_async()
         .await;
 
-    let args = Args::try_parse_from(&[
+    let args = Args::try_parse_from([
         "door-monitor",
         "--api-url", &server.url(),
         "--check-interval-seconds", "1",
@@ -33,7 +33,7 @@ async fn test_door_monitor_integration() {
     
     let status = result.unwrap();
     assert_eq!(status.id, 0);
-    assert_eq!(status.state, true);
+    assert!(status.state);
 }
 
 #[test]
@@ -61,14 +61,14 @@ fn test_args_parsing_real_world_scenarios() {
     // Test realistic command line scenarios
     
     // Minimal setup
-    let args = Args::try_parse_from(&[
+    let args = Args::try_parse_from([
         "door-monitor",
         "--api-url", "http://192.168.1.226/rpc/Input.GetStatus?id=0"
     ]).unwrap();
     assert_eq!(args.api_url, Some("http://192.168.1.226/rpc/Input.GetStatus?id=0".to_string()));
     
     // Full SMS setup
-    let args = Args::try_parse_from(&[
+    let args = Args::try_parse_from([
         "door-monitor",
         "--api-url", "http://192.168.1.226/rpc/Input.GetStatus?id=0",
         "--sms-api-username", "myuser",
@@ -82,7 +82,7 @@ fn test_args_parsing_real_world_scenarios() {
     assert!(args.sms_api_username.is_some());
     assert!(args.sms_api_password.is_some());
     assert!(args.sms_from_phone_number.is_some());
-    assert!(args.sms_to_phone_number.is_some());
+    assert_eq!(args.sms_to_phone_numbers, vec!["5559876543"]);
     assert_eq!(args.check_interval_seconds, 10);
     assert_eq!(args.open_too_long_seconds, 30);
 }
@@ -116,5 +116,5 @@ fn test_door_status_serialization() {
     
     let deserialized: DoorStatus = serde_json::from_str(&json).unwrap();
     assert_eq!(deserialized.id, 42);
-    assert_eq!(deserialized.state, false);
+    assert!(!deserialized.state);
 }